@@ -0,0 +1,134 @@
+//! Representative workloads for measuring the lexer and parser.
+//!
+//! Run with `cargo bench --features examples`, adding the `profiling` feature to also print the
+//! counters of each lexer.
+
+use std::{
+    hint::black_box,
+    io::Cursor,
+    time::{Duration, Instant},
+};
+
+use parsley_rs::{
+    examples::{arithmetic, json},
+    lexical::{Lexer, TokenValue},
+    parsing::{error::ParseError, ParseCursor},
+};
+
+/// Runs `workload` repeatedly for about a second and prints the average time it took.
+fn bench<O>(name: &str, mut workload: impl FnMut() -> O) {
+    black_box(workload());
+
+    let started = Instant::now();
+    let mut iterations = 0u32;
+    while iterations == 0 || started.elapsed() < Duration::from_secs(1) {
+        black_box(workload());
+        iterations += 1;
+    }
+    println!(
+        "{:<24} {:>12?} per iteration ({} iterations)",
+        name,
+        started.elapsed() / iterations,
+        iterations
+    );
+}
+
+/// Lexes `input` to completion with the lexer created by `lexer`.
+fn lex_all<'i, TokenType: TokenValue>(
+    lexer: impl Fn(Cursor<&'i str>) -> Lexer<'i, TokenType>,
+    input: &'i str,
+) -> Lexer<'i, TokenType> {
+    let mut lexer = lexer(Cursor::new(input));
+    lexer.tokenize().unwrap();
+    lexer
+}
+
+/// Prints the counters of `lexer`.
+#[cfg(feature = "profiling")]
+fn report<TokenType: TokenValue>(name: &str, lexer: &Lexer<'_, TokenType>) {
+    println!("{}:\n{}\n", name, lexer.profile_report());
+}
+
+#[cfg(not(feature = "profiling"))]
+fn report<TokenType: TokenValue>(_: &str, _: &Lexer<'_, TokenType>) {}
+
+/// A JSON document of `records` objects with a mix of strings, numbers and nested arrays.
+fn json_document(records: usize) -> String {
+    let records: Vec<_> = (0..records)
+        .map(|record| {
+            format!(
+                r#"{{"id": {}, "name": "record \"{}\" é", "score": {}.5e-3, "tags": ["a", "b", null, true]}}"#,
+                record, record, record
+            )
+        })
+        .collect();
+    format!("[{}]", records.join(",\n "))
+}
+
+/// A long arithmetic expression, standing in for a large source file.
+fn source_file(lines: usize) -> String {
+    (0..lines)
+        .map(|line| format!("({} + {}.25) * {} - ", line, line, line % 7 + 1))
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "1"
+}
+
+/// `sum := number '+' sum ')' | number '+' sum | number`, where the first alternative always fails
+/// after parsing the whole rest of the input, so the work doubles with every term.
+fn backtracking_sum(
+    cursor: &mut ParseCursor<arithmetic::ArithmeticToken>,
+) -> Result<f64, ParseError> {
+    use arithmetic::ArithmeticToken::{Number, Plus, RightParen};
+
+    fn number(cursor: &mut ParseCursor<arithmetic::ArithmeticToken>) -> Result<f64, ParseError> {
+        match cursor
+            .expect(|token| matches!(token, Number(_)), "a number")?
+            .token()
+        {
+            Number(number) => Ok(*number),
+            _ => unreachable!(),
+        }
+    }
+
+    cursor.any_of(&mut [
+        &mut |cursor| {
+            let left = number(cursor)?;
+            cursor.expect(|token| *token == Plus, "+")?;
+            let right = backtracking_sum(cursor)?;
+            cursor.expect(|token| *token == RightParen, ")")?;
+            Ok(left + right)
+        },
+        &mut |cursor| {
+            let left = number(cursor)?;
+            cursor.expect(|token| *token == Plus, "+")?;
+            Ok(left + backtracking_sum(cursor)?)
+        },
+        &mut number,
+    ])
+}
+
+fn main() {
+    let document = json_document(2_000);
+    report("json lexer", &lex_all(json::lexer, &document));
+    bench("json parse", || json::parse(&document).unwrap());
+
+    let source = source_file(5_000);
+    let lexer = lex_all(arithmetic::lexer, &source);
+    report("source lexer", &lexer);
+    let tokens = lexer.take();
+    bench("source lex", || {
+        lex_all(arithmetic::lexer, &source).take().len()
+    });
+    bench("source parse", || {
+        ParseCursor::parse_all(&tokens, arithmetic::expression).unwrap()
+    });
+
+    let terms = vec!["1"; 16].join(" + ");
+    let lexer = lex_all(arithmetic::lexer, &terms);
+    report("backtracking lexer", &lexer);
+    let tokens = lexer.take();
+    bench("backtracking parse", || {
+        ParseCursor::parse_all(&tokens, backtracking_sum).unwrap()
+    });
+}
@@ -0,0 +1,34 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+#[derive(Debug, Clone, Default)]
+/// A flag used to cooperatively cancel lexing or parsing from another thread, such as when
+/// a language server receives an edit that makes the current analysis stale.
+///
+/// Clones share the same flag, so one clone can be handed to the lexer or parser while
+/// another is kept to cancel it.
+pub struct CancelFlag(Arc<AtomicBool>);
+
+impl CancelFlag {
+    /// Create a flag that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Request cancellation.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed)
+    }
+
+    /// Returns whether cancellation has been requested.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Clear a previous cancellation request, allowing the flag to be reused.
+    pub fn reset(&self) {
+        self.0.store(false, Ordering::Relaxed)
+    }
+}
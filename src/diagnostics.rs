@@ -0,0 +1,219 @@
+use std::{error::Error, fmt::Display, ops::Range};
+
+use miette::{Diagnostic, LabeledSpan, MietteError, SourceCode, SourceSpan, SpanContents};
+
+use crate::lexical::{error::LexError, SourceBuffer};
+#[cfg(feature = "parsing")]
+use crate::parsing::error::{Label, ParseError};
+
+/// Allows miette to render the text that a lexer consumed.
+///
+/// Spans given to miette are byte ranges, see [SourceBuffer::byte_span].
+impl SourceCode for SourceBuffer {
+    fn read_span<'a>(
+        &'a self,
+        span: &SourceSpan,
+        context_lines_before: usize,
+        context_lines_after: usize,
+    ) -> Result<Box<dyn SpanContents<'a> + 'a>, MietteError> {
+        self.text()
+            .read_span(span, context_lines_before, context_lines_after)
+    }
+}
+
+/// Provides a code and help without labels, as the error does not hold the source text.
+/// See [SourceReport] for labels.
+impl Diagnostic for LexError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let code = match self.inner() {
+            LexError::UnexpectedEndOfStream => "parsley::lex::unexpected_end",
+            LexError::UnterminatedToken { .. } => "parsley::lex::unterminated",
+            LexError::LimitExceeded { .. } => "parsley::lex::limit_exceeded",
+            LexError::Cancelled => "parsley::lex::cancelled",
+            _ => "parsley::lex::other",
+        };
+        Some(Box::new(code))
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let name = self.tokenizer()?;
+        Some(Box::new(format!("Raised by the tokenizer {}.", name)))
+    }
+}
+
+/// Provides a code without labels, as the error does not hold the source text.
+/// See [SourceReport] for labels.
+#[cfg(feature = "parsing")]
+impl Diagnostic for ParseError {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        let code = if self.is_cancelled() {
+            "parsley::parse::cancelled"
+        } else if self.is_max_depth_exceeded() {
+            "parsley::parse::max_depth_exceeded"
+        } else if self.message().is_some() {
+            "parsley::parse::custom"
+        } else {
+            "parsley::parse::unexpected"
+        };
+        Some(Box::new(code))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// A [LexError] or [ParseError] paired with the [SourceBuffer] it occurred in, so that miette
+/// reports it with the source text and a label at the location of the error.
+///
+/// The buffer should hold the text of the lexer that produced the error or the tokens,
+/// see [Lexer::store_source](crate::lexical::Lexer::store_source).
+pub struct SourceReport<E> {
+    error: E,
+    source: SourceBuffer,
+}
+
+impl<E> SourceReport<E> {
+    /// Pair `error` with the `source` it occurred in.
+    pub fn new(error: E, source: SourceBuffer) -> Self {
+        Self { error, source }
+    }
+
+    /// Returns the error.
+    pub fn error(&self) -> &E {
+        &self.error
+    }
+
+    /// Returns the source the error occurred in.
+    pub fn source_buffer(&self) -> &SourceBuffer {
+        &self.source
+    }
+
+    /// Returns the error, discarding the source.
+    pub fn into_inner(self) -> E {
+        self.error
+    }
+
+    /// Returns a label covering the graphemes within `span`, if they lie within the source.
+    fn label(&self, text: String, span: Option<Range<usize>>) -> Option<LabeledSpan> {
+        let range = self.source.byte_span(span?)?;
+        Some(LabeledSpan::new_with_span(Some(text), range))
+    }
+}
+
+impl<E: Display> Display for SourceReport<E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<E: Error> Error for SourceReport<E> {}
+
+impl Diagnostic for SourceReport<LexError> {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.code()
+    }
+
+    fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.help()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.source)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let text = match self.error.inner() {
+            LexError::UnterminatedToken { kind, .. } => {
+                format!("this {} is never terminated", kind)
+            }
+            _ => "here".to_string(),
+        };
+        let label = self.label(text, self.error.grapheme_span())?;
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+#[cfg(feature = "parsing")]
+impl Diagnostic for SourceReport<ParseError> {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.error.code()
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        Some(&self.source)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        let text = match self.error.expected() {
+            [] => "here".to_string(),
+            expected => format!(
+                "expected {}",
+                expected
+                    .iter()
+                    .map(Label::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" or ")
+            ),
+        };
+        let label = self.label(text, self.error.grapheme_span())?;
+        Some(Box::new(std::iter::once(label)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use miette::{Diagnostic, SourceCode};
+
+    use super::SourceReport;
+    use crate::lexical::{error::LexError, GraphemeLocation, SourceBuffer};
+
+    #[test]
+    fn test_lex_report() {
+        let error = LexError::at(
+            GraphemeLocation::new(2, 0, 2),
+            LexError::other("bad grapheme").in_tokenizer("Word"),
+        );
+        assert_eq!(error.code().unwrap().to_string(), "parsley::lex::other");
+        assert_eq!(
+            error.help().unwrap().to_string(),
+            "Raised by the tokenizer Word."
+        );
+
+        let report = SourceReport::new(error, SourceBuffer::from_text("ab\u{e9}c"));
+        let labels: Vec<_> = report.labels().unwrap().collect();
+        assert_eq!(labels.len(), 1);
+        assert_eq!((labels[0].offset(), labels[0].len()), (2, 2));
+        assert!(report.source_code().is_some());
+        assert_eq!(report.to_string(), report.error().to_string());
+    }
+
+    #[test]
+    fn test_unterminated_label() {
+        let source = SourceBuffer::from_text("x = \"\u{e9}t\u{e9}");
+        let error = LexError::unterminated("string", 4..=4);
+        let report = SourceReport::new(error, source.clone());
+
+        let label = report.labels().unwrap().next().unwrap();
+        assert_eq!(label.label(), Some("this string is never terminated"));
+        assert_eq!((label.offset(), label.len()), (4, 1));
+
+        let contents = source.read_span(&(5..7).into(), 0, 0).unwrap();
+        assert_eq!(contents.data(), "\u{e9}".as_bytes());
+    }
+
+    #[cfg(feature = "parsing")]
+    #[test]
+    fn test_parse_report() {
+        use crate::parsing::error::ParseError;
+
+        let source = SourceBuffer::from_text("[1, ");
+        let error = ParseError::unexpected(3, None, ["value"], None).with_empty_span(4);
+        assert_eq!(
+            error.code().unwrap().to_string(),
+            "parsley::parse::unexpected"
+        );
+
+        let report = SourceReport::new(error, source);
+        let label = report.labels().unwrap().next().unwrap();
+        assert_eq!(label.label(), Some("expected value"));
+        assert_eq!((label.offset(), label.len()), (4, 0));
+    }
+}
@@ -0,0 +1,249 @@
+use std::io::Cursor;
+
+use crate::{
+    lexical::{
+        error::LexError, GraphemeLocation, Graphemes, LexContext, Lexer, LiteralSetTokenizer,
+        SmallString, Token, TokenValue, Tokenizer,
+    },
+    parsing::{
+        error::{Label, ParseError},
+        ParseCursor,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq)]
+/// The tokens of an arithmetic expression.
+pub enum ArithmeticToken {
+    Number(f64),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LeftParen,
+    RightParen,
+    Whitespace,
+}
+
+impl TokenValue for ArithmeticToken {
+    fn should_skip(&self) -> bool {
+        matches!(self, ArithmeticToken::Whitespace)
+    }
+
+    fn display_name(&self) -> String {
+        let name = match self {
+            ArithmeticToken::Number(_) => "a number",
+            ArithmeticToken::Plus => "`+`",
+            ArithmeticToken::Minus => "`-`",
+            ArithmeticToken::Star => "`*`",
+            ArithmeticToken::Slash => "`/`",
+            ArithmeticToken::LeftParen => "`(`",
+            ArithmeticToken::RightParen => "`)`",
+            ArithmeticToken::Whitespace => "whitespace",
+        };
+        name.to_string()
+    }
+}
+
+/// Lexes runs of whitespace.
+pub struct WhitespaceTokenizer;
+
+fn is_whitespace(grapheme: &str) -> bool {
+    matches!(grapheme, " " | "\t" | "\n" | "\r" | "\r\n")
+}
+
+impl Tokenizer<ArithmeticToken> for WhitespaceTokenizer {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<ArithmeticToken>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut (),
+    ) -> bool {
+        is_whitespace(grapheme)
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<ArithmeticToken>>,
+        _: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut (),
+    ) -> Result<ArithmeticToken, LexError> {
+        while let Some(next) = incoming.try_peek()? {
+            if !is_whitespace(&next) {
+                break;
+            }
+            incoming.next();
+        }
+        incoming.reset_peek();
+
+        Ok(ArithmeticToken::Whitespace)
+    }
+}
+
+/// Lexes numbers, such as `3` or `1.5`.
+pub struct NumberTokenizer;
+
+fn is_digit(grapheme: &str) -> bool {
+    grapheme.len() == 1 && grapheme.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+impl Tokenizer<ArithmeticToken> for NumberTokenizer {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<ArithmeticToken>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut (),
+    ) -> bool {
+        is_digit(grapheme)
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<ArithmeticToken>>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut (),
+    ) -> Result<ArithmeticToken, LexError> {
+        let mut text = grapheme.to_string();
+        while let Some(next) = incoming.try_peek()? {
+            if next != "." && !is_digit(&next) {
+                break;
+            }
+            text.push_str(&next);
+            incoming.next();
+        }
+        incoming.reset_peek();
+
+        text.parse()
+            .map(ArithmeticToken::Number)
+            .map_err(|_| LexError::other(format!("Invalid number {:?}", text)))
+    }
+}
+
+/// Creates a lexer for an arithmetic expression read from `reader`.
+pub fn lexer<'a, Reader: std::io::Read + 'a>(reader: Reader) -> Lexer<'a, ArithmeticToken> {
+    let operators =
+        LiteralSetTokenizer::new(["+", "-", "*", "/", "(", ")"], |literal| match literal {
+            "+" => ArithmeticToken::Plus,
+            "-" => ArithmeticToken::Minus,
+            "*" => ArithmeticToken::Star,
+            "/" => ArithmeticToken::Slash,
+            "(" => ArithmeticToken::LeftParen,
+            _ => ArithmeticToken::RightParen,
+        });
+
+    Lexer::new(reader, false, None)
+        .tokenizer(|| WhitespaceTokenizer)
+        .tokenizer(|| NumberTokenizer)
+        .tokenizer(move || operators.clone())
+}
+
+/// Parses and evaluates a sum or difference of terms.
+pub fn expression(cursor: &mut ParseCursor<ArithmeticToken>) -> Result<f64, ParseError> {
+    let mut value = term(cursor)?;
+    loop {
+        if cursor
+            .eat(|token| *token == ArithmeticToken::Plus)
+            .is_some()
+        {
+            value += term(cursor)?;
+        } else if cursor
+            .eat(|token| *token == ArithmeticToken::Minus)
+            .is_some()
+        {
+            value -= term(cursor)?;
+        } else {
+            return Ok(value);
+        }
+    }
+}
+
+/// Parses and evaluates a product or quotient of factors.
+fn term(cursor: &mut ParseCursor<ArithmeticToken>) -> Result<f64, ParseError> {
+    let mut value = factor(cursor)?;
+    loop {
+        if cursor
+            .eat(|token| *token == ArithmeticToken::Star)
+            .is_some()
+        {
+            value *= factor(cursor)?;
+        } else if cursor
+            .eat(|token| *token == ArithmeticToken::Slash)
+            .is_some()
+        {
+            let divisor = cursor.validate(factor, |divisor, _| match *divisor == 0.0 {
+                true => Err("Division by zero".to_string()),
+                false => Ok(()),
+            })?;
+            value /= divisor;
+        } else {
+            return Ok(value);
+        }
+    }
+}
+
+/// Parses and evaluates a number, a negated factor or a parenthesized expression.
+fn factor(cursor: &mut ParseCursor<ArithmeticToken>) -> Result<f64, ParseError> {
+    cursor.step("factor")?;
+    if cursor
+        .eat(|token| *token == ArithmeticToken::Minus)
+        .is_some()
+    {
+        return Ok(-cursor.nested("negation", factor)?);
+    }
+    if cursor
+        .eat(|token| *token == ArithmeticToken::LeftParen)
+        .is_some()
+    {
+        let value = cursor.nested("parentheses", expression)?;
+        cursor.expect(
+            |token| *token == ArithmeticToken::RightParen,
+            Label::literal(")"),
+        )?;
+        return Ok(value);
+    }
+
+    match cursor.peek().map(Token::token) {
+        Some(ArithmeticToken::Number(number)) => {
+            cursor.bump();
+            Ok(*number)
+        }
+        _ => Err(cursor.unexpected([
+            Label::from("a number"),
+            Label::literal("-"),
+            Label::literal("("),
+        ])),
+    }
+}
+
+/// The deepest nesting of negations and parentheses that [evaluate] accepts.
+pub const MAX_DEPTH: usize = 256;
+
+/// Lexes, parses and evaluates an arithmetic expression, such as `2 * (3 + 4)`.
+///
+/// Lexing errors are reported as parse errors with the lexing error's message, and negations
+/// and parentheses may be nested at most [MAX_DEPTH] deep.
+pub fn evaluate(input: &str) -> Result<f64, ParseError> {
+    let mut lexer = lexer(Cursor::new(input));
+    if let Err(error) = lexer.tokenize() {
+        let range = error
+            .location()
+            .map(|location| location.index..=location.index);
+        return Err(ParseError::custom(
+            lexer.tokens().len(),
+            range,
+            error.to_string(),
+        ));
+    }
+
+    let mut cursor = ParseCursor::new(lexer.tokens()).depth_limit(MAX_DEPTH);
+    let value = expression(&mut cursor)?;
+    cursor.expect_end()?;
+    Ok(value)
+}
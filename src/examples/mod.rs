@@ -0,0 +1,49 @@
+/// A JSON lexer and parser, see [crate::json].
+pub mod json {
+    pub use crate::json::*;
+}
+/// An evaluator for arithmetic expressions.
+pub mod arithmetic;
+
+#[cfg(test)]
+mod tests {
+    use super::arithmetic::{evaluate, MAX_DEPTH};
+
+    #[test]
+    fn test_arithmetic() {
+        assert_eq!(evaluate("1 + 2 * 3"), Ok(7.0));
+        assert_eq!(evaluate("2 * (3 + 4) - -1"), Ok(15.0));
+        assert_eq!(evaluate("7 / 2"), Ok(3.5));
+
+        let error = evaluate("1 / (2 - 2)").unwrap_err();
+        assert_eq!(error.message(), Some("Division by zero"));
+        assert_eq!(error.range(), Some(&(4..=10)));
+
+        let error = evaluate("1 +").unwrap_err();
+        assert_eq!(error.expected(), ["(", "-", "a number"]);
+        assert_eq!(
+            error.to_string(),
+            "Expected `(`, `-` or a number, found the end of the tokens at token 2"
+        );
+        assert_eq!(
+            evaluate("(1 +)").unwrap_err().to_string(),
+            "Expected `(`, `-` or a number, found `)` at token 3"
+        );
+        assert!(evaluate("1 $ 2").is_err());
+    }
+
+    #[test]
+    fn test_arithmetic_depth() {
+        let negations = format!("{}1", "-".repeat(MAX_DEPTH));
+        assert_eq!(evaluate(&negations), Ok(1.0));
+        let parentheses = format!("{}1{}", "(".repeat(MAX_DEPTH), ")".repeat(MAX_DEPTH));
+        assert_eq!(evaluate(&parentheses), Ok(1.0));
+
+        let error = evaluate(&format!("{}1", "-".repeat(1_000_000))).unwrap_err();
+        assert!(error.is_max_depth_exceeded());
+        assert_eq!(error.range(), Some(&(MAX_DEPTH + 1..=MAX_DEPTH + 1)));
+        assert!(evaluate(&"(".repeat(100_000))
+            .unwrap_err()
+            .is_max_depth_exceeded());
+    }
+}
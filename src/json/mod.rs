@@ -0,0 +1,557 @@
+use std::{collections::VecDeque, fmt::Display, io::Cursor, ops::RangeInclusive};
+
+use crate::{
+    lexical::{
+        error::LexError,
+        escape::{decode_escapes, EscapePolicy},
+        GraphemeLocation, Graphemes, LexContext, Lexer, LiteralSetTokenizer, SmallString, Token,
+        TokenValue, Tokenizer,
+    },
+    parsing::{
+        error::{Label, ParseError},
+        ParseCursor,
+    },
+};
+
+#[derive(Debug, Clone, PartialEq)]
+/// The tokens of a JSON document.
+pub enum JsonToken {
+    LeftBrace,
+    RightBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
+    Comma,
+    String(String),
+    Number(f64),
+    True,
+    False,
+    Null,
+    Whitespace,
+}
+
+impl TokenValue for JsonToken {
+    fn should_skip(&self) -> bool {
+        matches!(self, JsonToken::Whitespace)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// A parsed JSON value. The members of an object are kept in the order they were written.
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+#[derive(Debug)]
+/// An error that occurs while lexing or parsing a JSON document.
+pub enum JsonError {
+    Lex(LexError),
+    Parse(ParseError),
+}
+
+impl JsonError {
+    /// Returns the grapheme range that the error occurred at, if it is known.
+    pub fn range(&self) -> Option<RangeInclusive<usize>> {
+        match self {
+            JsonError::Lex(error) => error
+                .location()
+                .map(|location| location.index..=location.index),
+            JsonError::Parse(error) => error.range().cloned(),
+        }
+    }
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonError::Lex(error) => write!(f, "{}", error),
+            JsonError::Parse(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl From<LexError> for JsonError {
+    fn from(error: LexError) -> Self {
+        JsonError::Lex(error)
+    }
+}
+
+impl From<ParseError> for JsonError {
+    fn from(error: ParseError) -> Self {
+        JsonError::Parse(error)
+    }
+}
+
+/// Lexes the whitespace between JSON tokens.
+pub struct WhitespaceTokenizer;
+
+fn is_whitespace(grapheme: &str) -> bool {
+    matches!(grapheme, " " | "\t" | "\n" | "\r" | "\r\n")
+}
+
+impl Tokenizer<JsonToken> for WhitespaceTokenizer {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<JsonToken>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut (),
+    ) -> bool {
+        is_whitespace(grapheme)
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<JsonToken>>,
+        _: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut (),
+    ) -> Result<JsonToken, LexError> {
+        while let Some(next) = incoming.try_peek()? {
+            if !is_whitespace(&next) {
+                break;
+            }
+            incoming.next();
+        }
+        incoming.reset_peek();
+
+        Ok(JsonToken::Whitespace)
+    }
+}
+
+/// Reads the characters of graphemes one at a time.
+///
+/// A grapheme may join a quote or backslash with the combining marks that follow it,
+/// so strings are read character by character rather than grapheme by grapheme.
+struct StringChars<'i, 'a> {
+    incoming: &'i mut Graphemes<'a>,
+    pending: VecDeque<char>,
+}
+
+impl<'a> StringChars<'_, 'a> {
+    fn next(&mut self) -> Result<Option<char>, LexError> {
+        while self.pending.is_empty() {
+            match self.incoming.next() {
+                Some(Ok(grapheme)) => self.pending.extend(grapheme.chars()),
+                Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
+                None => return Ok(None),
+            }
+        }
+        Ok(self.pending.pop_front())
+    }
+}
+
+/// Lexes a JSON string, decoding its escape sequences with [EscapePolicy::json].
+pub struct StringTokenizer;
+
+impl Tokenizer<JsonToken> for StringTokenizer {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<JsonToken>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut (),
+    ) -> bool {
+        grapheme.starts_with('"')
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<JsonToken>>,
+        grapheme: &str,
+        location: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut (),
+    ) -> Result<JsonToken, LexError> {
+        let mut chars = StringChars {
+            incoming,
+            pending: grapheme.chars().skip(1).collect(),
+        };
+        let mut raw = String::new();
+        loop {
+            match chars.next()? {
+                Some('"') if chars.pending.is_empty() => break,
+                Some('"') => {
+                    return Err(LexError::other(format!(
+                        "Unexpected {:?} after string",
+                        chars.pending.iter().collect::<String>()
+                    )))
+                }
+                Some(character) if character < ' ' => {
+                    return Err(LexError::other(format!(
+                        "Unescaped control character {:?} in string",
+                        character
+                    )))
+                }
+                Some('\\') => {
+                    raw.push('\\');
+                    if let Some(escaped) = chars.next()? {
+                        raw.push(escaped);
+                    }
+                }
+                Some(character) => raw.push(character),
+                None => {
+                    return Err(LexError::unterminated(
+                        "string",
+                        location.index..=location.index,
+                    ))
+                }
+            }
+        }
+
+        // Strings cannot contain line breaks, so the escape lies on the same line as the opening quote.
+        decode_escapes(&raw, &EscapePolicy::json())
+            .map(JsonToken::String)
+            .map_err(|error| {
+                let start = location.index + 1 + error.grapheme_span(&raw).start;
+                let offset = location.offset + 1 + error.grapheme_span(&raw).start;
+                LexError::at(
+                    GraphemeLocation::new(start, location.line, offset),
+                    LexError::other(error),
+                )
+            })
+    }
+}
+
+/// Lexes a JSON number.
+pub struct NumberTokenizer;
+
+fn is_digit(grapheme: &str) -> bool {
+    grapheme.len() == 1 && grapheme.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+/// Returns whether `text` follows the JSON number grammar.
+fn is_json_number(text: &str) -> bool {
+    let text = text.strip_prefix('-').unwrap_or(text);
+    let (mantissa, exponent) = match text.find(['e', 'E']) {
+        Some(index) => (&text[..index], Some(&text[index + 1..])),
+        None => (text, None),
+    };
+    let (integer, fraction) = match mantissa.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let digits = |text: &str| !text.is_empty() && text.bytes().all(|byte| byte.is_ascii_digit());
+
+    digits(integer)
+        && (integer == "0" || !integer.starts_with('0'))
+        && fraction.is_none_or(digits)
+        && exponent
+            .is_none_or(|exponent| digits(exponent.strip_prefix(['+', '-']).unwrap_or(exponent)))
+}
+
+impl Tokenizer<JsonToken> for NumberTokenizer {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<JsonToken>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut (),
+    ) -> bool {
+        grapheme == "-" || is_digit(grapheme)
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<JsonToken>>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut (),
+    ) -> Result<JsonToken, LexError> {
+        let mut text = grapheme.to_string();
+        while let Some(next) = incoming.try_peek()? {
+            if !is_digit(&next) && !matches!(next.as_str(), "." | "e" | "E" | "+" | "-") {
+                break;
+            }
+            text.push_str(&next);
+            incoming.next();
+        }
+        incoming.reset_peek();
+
+        match text.parse() {
+            Ok(number) if is_json_number(&text) => Ok(JsonToken::Number(number)),
+            _ => Err(LexError::other(format!("Invalid number {:?}", text))),
+        }
+    }
+}
+
+/// Creates a tokenizer for the punctuation and keywords of JSON.
+pub fn literal_tokenizer() -> LiteralSetTokenizer<JsonToken> {
+    LiteralSetTokenizer::new(
+        ["{", "}", "[", "]", ":", ",", "true", "false", "null"],
+        |literal| match literal {
+            "{" => JsonToken::LeftBrace,
+            "}" => JsonToken::RightBrace,
+            "[" => JsonToken::LeftBracket,
+            "]" => JsonToken::RightBracket,
+            ":" => JsonToken::Colon,
+            "," => JsonToken::Comma,
+            "true" => JsonToken::True,
+            "false" => JsonToken::False,
+            _ => JsonToken::Null,
+        },
+    )
+}
+
+/// Creates a lexer for a JSON document read from `reader`.
+pub fn lexer<'a, Reader: std::io::Read + 'a>(reader: Reader) -> Lexer<'a, JsonToken> {
+    let literals = literal_tokenizer();
+    Lexer::new(reader, false, None)
+        .tokenizer(|| WhitespaceTokenizer)
+        .tokenizer(|| StringTokenizer)
+        .tokenizer(|| NumberTokenizer)
+        .tokenizer(move || literals.clone())
+}
+
+/// The deepest nesting of arrays and objects that [parse] accepts.
+pub const DEFAULT_MAX_DEPTH: usize = 128;
+
+/// Parses a single JSON value.
+///
+/// Arrays and objects are parsed through [ParseCursor::nested], so that hostile input cannot
+/// overflow the stack when the cursor is given a [ParseCursor::depth_limit].
+pub fn value(cursor: &mut ParseCursor<JsonToken>) -> Result<JsonValue, ParseError> {
+    let token = match cursor.peek() {
+        Some(token) => token,
+        None => return Err(cursor.unexpected(["a value"])),
+    };
+
+    let value = match token.token() {
+        JsonToken::LeftBrace => return cursor.nested("object", object),
+        JsonToken::LeftBracket => return cursor.nested("array", array),
+        JsonToken::String(string) => JsonValue::String(string.clone()),
+        JsonToken::Number(number) => JsonValue::Number(*number),
+        JsonToken::True => JsonValue::Bool(true),
+        JsonToken::False => JsonValue::Bool(false),
+        JsonToken::Null => JsonValue::Null,
+        _ => return Err(cursor.unexpected(["a value"])),
+    };
+    cursor.bump();
+
+    Ok(value)
+}
+
+fn array(cursor: &mut ParseCursor<JsonToken>) -> Result<JsonValue, ParseError> {
+    cursor.expect(
+        |token| *token == JsonToken::LeftBracket,
+        Label::literal("["),
+    )?;
+    let mut values = vec![];
+    if cursor
+        .eat(|token| *token == JsonToken::RightBracket)
+        .is_some()
+    {
+        return Ok(JsonValue::Array(values));
+    }
+
+    loop {
+        values.push(value(cursor)?);
+        if cursor.eat(|token| *token == JsonToken::Comma).is_none() {
+            cursor.expect(
+                |token| *token == JsonToken::RightBracket,
+                Label::literal("]"),
+            )?;
+            return Ok(JsonValue::Array(values));
+        }
+    }
+}
+
+fn object(cursor: &mut ParseCursor<JsonToken>) -> Result<JsonValue, ParseError> {
+    cursor.expect(|token| *token == JsonToken::LeftBrace, Label::literal("{"))?;
+    let mut members = vec![];
+    if cursor
+        .eat(|token| *token == JsonToken::RightBrace)
+        .is_some()
+    {
+        return Ok(JsonValue::Object(members));
+    }
+
+    loop {
+        let key = match cursor
+            .expect(|token| matches!(token, JsonToken::String(_)), "a string")?
+            .token()
+        {
+            JsonToken::String(key) => key.clone(),
+            _ => unreachable!(),
+        };
+        cursor.expect(|token| *token == JsonToken::Colon, Label::literal(":"))?;
+        members.push((key, value(cursor)?));
+
+        if cursor.eat(|token| *token == JsonToken::Comma).is_none() {
+            cursor.expect(|token| *token == JsonToken::RightBrace, Label::literal("}"))?;
+            return Ok(JsonValue::Object(members));
+        }
+    }
+}
+
+/// Lexes and parses a JSON document.
+pub fn parse(input: &str) -> Result<JsonValue, JsonError> {
+    parse_with_max_depth(input, DEFAULT_MAX_DEPTH)
+}
+
+/// Lexes and parses a JSON document, allowing arrays and objects to be nested `max_depth` deep.
+pub fn parse_with_max_depth(input: &str, max_depth: usize) -> Result<JsonValue, JsonError> {
+    parse_reader(Cursor::new(input), max_depth)
+}
+
+/// Lexes and parses a JSON document read from `reader`.
+fn parse_reader<Reader: std::io::Read>(
+    reader: Reader,
+    max_depth: usize,
+) -> Result<JsonValue, JsonError> {
+    let mut lexer = lexer(reader);
+    lexer.tokenize()?;
+
+    let mut cursor = ParseCursor::new(lexer.tokens()).depth_limit(max_depth);
+    let value = value(&mut cursor)?;
+    cursor.expect_end()?;
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json() {
+        let value = parse(
+            r#"{"name": "parsley", "tags": ["lexer", "parser"], "version": 0.17,
+                "stable": false, "license": null, "escaped": "a\"é😀"}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            value,
+            JsonValue::Object(vec![
+                ("name".into(), JsonValue::String("parsley".into())),
+                (
+                    "tags".into(),
+                    JsonValue::Array(vec![
+                        JsonValue::String("lexer".into()),
+                        JsonValue::String("parser".into()),
+                    ])
+                ),
+                ("version".into(), JsonValue::Number(0.17)),
+                ("stable".into(), JsonValue::Bool(false)),
+                ("license".into(), JsonValue::Null),
+                (
+                    "escaped".into(),
+                    JsonValue::String("a\"\u{e9}\u{1f600}".into())
+                ),
+            ])
+        );
+        assert_eq!(parse("[]").unwrap(), JsonValue::Array(vec![]));
+        assert_eq!(parse(" -1e3 ").unwrap(), JsonValue::Number(-1000.0));
+
+        for invalid in ["[1,]", "{\"a\" 1}", "01", "\"a", "[1] 2", "tru"] {
+            assert!(parse(invalid).is_err(), "{:?} should not parse", invalid);
+        }
+    }
+
+    /// Whether each implementation defined case of JSONTestSuite is accepted.
+    const IMPLEMENTATION_DEFINED: &[(&str, bool)] = &[
+        // Numbers beyond the range of f64 are rounded to zero or infinity.
+        ("i_number_double_huge_neg_exp", true),
+        ("i_number_huge_exp", true),
+        ("i_number_neg_int_huge_exp", true),
+        ("i_number_pos_double_huge_exp", true),
+        ("i_number_real_neg_overflow", true),
+        ("i_number_real_pos_overflow", true),
+        ("i_number_real_underflow", true),
+        ("i_number_too_big_neg_int", true),
+        ("i_number_too_big_pos_int", true),
+        ("i_number_very_big_negative_int", true),
+        // Escaped surrogates must form a pair, as strings hold Unicode scalar values.
+        ("i_object_key_lone_2nd_surrogate", false),
+        ("i_string_1st_surrogate_but_2nd_missing", false),
+        ("i_string_1st_valid_surrogate_2nd_invalid", false),
+        ("i_string_incomplete_surrogate_and_escape_valid", false),
+        ("i_string_incomplete_surrogate_pair", false),
+        ("i_string_incomplete_surrogates_escape_valid", false),
+        ("i_string_invalid_lonely_surrogate", false),
+        ("i_string_invalid_surrogate", false),
+        ("i_string_inverted_surrogates_U+1D11E", false),
+        ("i_string_lone_second_surrogate", false),
+        // The input must be UTF-8, so other encodings and invalid sequences are rejected.
+        ("i_string_UTF-16LE_with_BOM", false),
+        ("i_string_UTF-8_invalid_sequence", false),
+        ("i_string_UTF8_surrogate_U+D800", false),
+        ("i_string_invalid_utf-8", false),
+        ("i_string_iso_latin_1", false),
+        ("i_string_lone_utf8_continuation_byte", false),
+        ("i_string_not_in_unicode_range", false),
+        ("i_string_overlong_sequence_2_bytes", false),
+        ("i_string_overlong_sequence_6_bytes", false),
+        ("i_string_overlong_sequence_6_bytes_null", false),
+        ("i_string_truncated-utf-8", false),
+        ("i_string_utf16BE_no_BOM", false),
+        ("i_string_utf16LE_no_BOM", false),
+        // Nesting is limited to DEFAULT_MAX_DEPTH, and a byte order mark is not whitespace.
+        ("i_structure_500_nested_arrays", false),
+        ("i_structure_UTF-8_BOM_empty_object", false),
+    ];
+
+    #[test]
+    fn test_conformance() {
+        // The `test_parsing` directory of JSONTestSuite, where documents starting with `y_` must be
+        // accepted, those starting with `n_` must be rejected, and the rest are left to the parser.
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/json/test_parsing");
+        let mut names: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.ends_with(".json"))
+            .collect();
+        names.sort();
+
+        let mut failures = Vec::new();
+        for name in names {
+            let input = std::fs::read(dir.join(&name)).unwrap();
+            let accepted = parse_reader(input.as_slice(), DEFAULT_MAX_DEPTH).is_ok();
+            let expected = match name.split_once('_').unwrap().0 {
+                "y" => true,
+                "n" => false,
+                _ => match IMPLEMENTATION_DEFINED
+                    .iter()
+                    .find(|(case, _)| name.strip_suffix(".json") == Some(case))
+                {
+                    Some((_, expected)) => *expected,
+                    None => panic!("{} has no decision", name),
+                },
+            };
+            if accepted != expected {
+                failures.push(name);
+            }
+        }
+        assert!(failures.is_empty(), "{:#?}", failures);
+    }
+
+    #[test]
+    fn test_nesting() {
+        let nested = format!("{}{}", "[".repeat(10), "]".repeat(10));
+        assert!(parse_with_max_depth(&nested, 10).is_ok());
+
+        let error = parse_with_max_depth(&nested, 9).unwrap_err();
+        assert_eq!(error.range(), Some(9..=9));
+    }
+
+    #[test]
+    fn test_error_ranges() {
+        assert_eq!(parse("[1, ]").unwrap_err().range(), Some(4..=4));
+        assert_eq!(parse("[\"a\\q\"]").unwrap_err().range(), Some(3..=3));
+        assert_eq!(parse("[1, \"\\u12x4\"]").unwrap_err().range(), Some(5..=5));
+        assert_eq!(parse("[1 2]").unwrap_err().range(), Some(3..=3));
+    }
+}
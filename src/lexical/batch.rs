@@ -0,0 +1,126 @@
+//! Lexing of many sources at once, such as every file of a project, across a pool of threads.
+//!
+//! Lexers are not [Send], so each source is lexed entirely on one thread by a lexer created there,
+//! and only the tokens, or the error, are sent back.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    io::Read,
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
+
+use super::{error::LexError, Lexer, Token, TokenValue};
+
+#[derive(Debug)]
+/// A source that failed to lex in [tokenize_all].
+pub struct BatchError {
+    /// The index of the source within the sources passed to [tokenize_all].
+    pub source: usize,
+    /// The error that the source failed to lex with.
+    pub error: LexError,
+}
+
+impl Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Source {}: {}", self.source, self.error)
+    }
+}
+
+impl Error for BatchError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// The tokens lexed from a source by [tokenize_all], or why it failed to lex.
+pub type BatchResult<TokenType> = Result<Vec<Token<TokenType>>, BatchError>;
+
+/// Lexes every source in `sources` with a lexer created by `lexer`, returning the result of each
+/// source in the order they were given.
+///
+/// Sources are lexed in parallel by as many threads as [thread::available_parallelism] suggests,
+/// but never more threads than sources. If lexing a source panics, its result is an error.
+pub fn tokenize_all<'r, Reader, TokenType, Context, F>(
+    sources: Vec<Reader>,
+    lexer: F,
+) -> Vec<BatchResult<TokenType>>
+where
+    Reader: Read + Send + 'r,
+    TokenType: TokenValue + Send,
+    F: Fn(Reader) -> Lexer<'r, TokenType, Context> + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+    tokenize_all_with_threads(sources, lexer, threads)
+}
+
+/// Lexes every source in `sources` like [tokenize_all], using at most `threads` threads.
+pub fn tokenize_all_with_threads<'r, Reader, TokenType, Context, F>(
+    sources: Vec<Reader>,
+    lexer: F,
+    threads: usize,
+) -> Vec<BatchResult<TokenType>>
+where
+    Reader: Read + Send + 'r,
+    TokenType: TokenValue + Send,
+    F: Fn(Reader) -> Lexer<'r, TokenType, Context> + Sync,
+{
+    let count = sources.len();
+    let sources: Vec<_> = sources
+        .into_iter()
+        .map(|source| Mutex::new(Some(source)))
+        .collect();
+    let results: Vec<_> = (0..count).map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+
+    let lex = |index: usize, source: Reader| -> BatchResult<TokenType> {
+        let mut lexer = lexer(source);
+        match lexer.tokenize() {
+            Ok(()) => Ok(lexer.take()),
+            Err(error) => Err(BatchError {
+                source: index,
+                error,
+            }),
+        }
+    };
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, count.max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let source = match sources.get(index) {
+                    Some(source) => source.lock().ok().and_then(|mut source| source.take()),
+                    None => break,
+                };
+                if let Some(source) = source {
+                    // A panicking lexer is reported as an error of its source, rather than
+                    // stopping the whole batch.
+                    let result = panic::catch_unwind(AssertUnwindSafe(|| lex(index, source)));
+                    if let (Ok(result), Ok(mut slot)) = (result, results[index].lock()) {
+                        *slot = Some(result);
+                    }
+                }
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .enumerate()
+        .map(|(index, result)| {
+            let result = result.into_inner().ok().flatten();
+            result.unwrap_or_else(|| {
+                Err(BatchError {
+                    source: index,
+                    error: LexError::other("The thread lexing the source panicked"),
+                })
+            })
+        })
+        .collect()
+}
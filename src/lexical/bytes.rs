@@ -0,0 +1,316 @@
+use std::io::{BufReader, Bytes as IoBytes, Error, Read};
+
+use itertools::{Itertools, MultiPeek};
+
+use super::{error::LexError, lexer::token_start, GraphemeLocation, LexContext, Token, TokenValue};
+
+type ByteIter<'a> = MultiPeek<IoBytes<BufReader<Box<dyn Read + 'a>>>>;
+
+/// A stream of raw bytes, without any UTF-8 decoding, for lexing binary formats with a [ByteLexer].
+pub struct Bytes<'a> {
+    iter: ByteIter<'a>,
+    successful_reads: usize,
+}
+
+impl<'a> Bytes<'a> {
+    pub fn new<Reader: Read + 'a>(reader: Reader) -> Self {
+        let reader: Box<dyn Read + 'a> = Box::new(reader);
+        Self {
+            iter: BufReader::new(reader).bytes().multipeek(),
+            successful_reads: 0,
+        }
+    }
+
+    /// Peeks at the next byte, with each call peeking one byte further until [Bytes::reset_peek] is called.
+    ///
+    /// Returns `None` at the end of the stream, or if reading the byte failed, in which case
+    /// the error is returned when the byte is consumed.
+    pub fn peek(&mut self) -> Option<u8> {
+        match self.iter.peek() {
+            Some(Ok(byte)) => Some(*byte),
+            _ => None,
+        }
+    }
+
+    pub fn reset_peek(&mut self) {
+        self.iter.reset_peek()
+    }
+
+    /// Consumes `count` bytes and returns them.
+    ///
+    /// If the stream ends first, [LexError::UnexpectedEndOfStream] is returned.
+    pub fn take_bytes(&mut self, count: usize) -> Result<Vec<u8>, LexError> {
+        let mut taken = Vec::with_capacity(count);
+        for _ in 0..count {
+            match self.next() {
+                Some(Ok((_, byte))) => taken.push(byte),
+                Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
+                None => return Err(LexError::UnexpectedEndOfStream),
+            }
+        }
+        Ok(taken)
+    }
+
+    /// Consumes bytes until `delimiter` is read, returning everything that came before it.
+    /// The delimiter itself is consumed but not included.
+    ///
+    /// If the stream ends before `delimiter` is found, [LexError::UnexpectedEndOfStream] is returned.
+    pub fn read_until(&mut self, delimiter: &[u8]) -> Result<Vec<u8>, LexError> {
+        let mut read = vec![];
+        if delimiter.is_empty() {
+            return Ok(read);
+        }
+
+        loop {
+            match self.next() {
+                Some(Ok((_, byte))) => {
+                    read.push(byte);
+                    if read.ends_with(delimiter) {
+                        read.truncate(read.len() - delimiter.len());
+                        return Ok(read);
+                    }
+                }
+                Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
+                None => return Err(LexError::UnexpectedEndOfStream),
+            }
+        }
+    }
+
+    /// Returns the amount of bytes consumed.
+    pub fn successes(&self) -> usize {
+        self.successful_reads
+    }
+
+    /// Returns the index of the last consumed byte.
+    pub fn current_index(&self) -> usize {
+        self.successful_reads.saturating_sub(1)
+    }
+}
+
+impl Iterator for Bytes<'_> {
+    type Item = Result<(usize, u8), (usize, Error)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.iter.next()? {
+            Ok(byte) => {
+                self.successful_reads += 1;
+                Some(Ok((self.current_index(), byte)))
+            }
+            Err(error) => Some(Err((self.successful_reads, error))),
+        }
+    }
+}
+
+/// Represents a tokenizer of raw bytes, the counterpart of [Tokenizer](super::Tokenizer) for a [ByteLexer].
+pub trait ByteTokenizer<TokenType: TokenValue, Context = ()> {
+    /// Returns a name identifying the tokenizer in errors and diagnostics.
+    ///
+    /// Defaults to the name of the implementing type.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Determines whether `byte`, at the byte index `index`, and the potential next byte
+    /// constitute the start of a potentially valid token.
+    fn can_tokenize(
+        &mut self,
+        lex_context: &LexContext<TokenType>,
+        byte: u8,
+        index: usize,
+        next: Option<u8>,
+        context: &mut Context,
+    ) -> bool;
+
+    /// Given [can_tokenize](Self::can_tokenize) evaluates to `true`, this function is called.
+    ///
+    /// `byte` has already been consumed from `incoming`. Tokens pushed onto `tokens` are stored
+    /// before the returned token.
+    fn lex<'a, 'b>(
+        &'b mut self,
+        tokens: &'b mut Vec<Token<TokenType>>,
+        byte: u8,
+        index: usize,
+        incoming: &'b mut Bytes<'a>,
+        context: &mut Context,
+    ) -> Result<TokenType, LexError>;
+}
+
+type ByteTokenizerFn<'a, TokenType, Context> =
+    Box<dyn Fn() -> Box<dyn ByteTokenizer<TokenType, Context> + 'a> + 'a>;
+
+/// Accepts raw bytes from an input reader, and lexes them into tokens whose ranges are inclusive
+/// ranges of byte indexes, for binary formats such as container formats with embedded text.
+///
+/// Errors are located with a [GraphemeLocation] whose index and offset are the byte index of the
+/// byte that the failed token started at, and whose line is always zero.
+pub struct ByteLexer<'a, TokenType: TokenValue, Context = ()> {
+    tokens: Vec<Token<TokenType>>,
+    creation_funcs: Vec<ByteTokenizerFn<'a, TokenType, Context>>,
+    eof_token: Option<TokenType>,
+    incoming: Bytes<'a>,
+    context: Context,
+    finished: bool,
+}
+
+impl<'a, TokenType: TokenValue> ByteLexer<'a, TokenType> {
+    /// Create a byte lexer.
+    pub fn new<Reader: Read + 'a>(reader: Reader, eof_token: Option<TokenType>) -> Self {
+        Self::with_context(reader, eof_token, ())
+    }
+}
+
+impl<'a, TokenType: TokenValue, Context> ByteLexer<'a, TokenType, Context> {
+    /// Create a byte lexer whose tokenizers share `context`.
+    pub fn with_context<Reader: Read + 'a>(
+        reader: Reader,
+        eof_token: Option<TokenType>,
+        context: Context,
+    ) -> Self {
+        Self {
+            tokens: vec![],
+            creation_funcs: vec![],
+            eof_token,
+            incoming: Bytes::new(reader),
+            context,
+            finished: false,
+        }
+    }
+
+    /// Add a tokenizer function and return self.
+    pub fn tokenizer<F, T>(mut self, f: F) -> Self
+    where
+        F: Fn() -> T + 'a,
+        T: ByteTokenizer<TokenType, Context> + 'a,
+    {
+        self.add_tokenizer(f);
+        self
+    }
+
+    /// Add a tokenizer function.
+    pub fn add_tokenizer<F, T>(&mut self, f: F)
+    where
+        F: Fn() -> T + 'a,
+        T: ByteTokenizer<TokenType, Context> + 'a,
+    {
+        self.creation_funcs.push(Box::new(move || Box::new(f())));
+    }
+
+    /// Return a reference to the tokens.
+    pub fn tokens(&self) -> &Vec<Token<TokenType>> {
+        &self.tokens
+    }
+
+    /// Return the tokens and consume `self`.
+    pub fn take(self) -> Vec<Token<TokenType>> {
+        self.tokens
+    }
+
+    /// Return a reference to the context shared by the tokenizers.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Return a mutable reference to the context shared by the tokenizers.
+    pub fn context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    /// Return a mutable reference to the incoming byte stream.
+    ///
+    /// Bytes consumed through this are not passed to any tokenizer.
+    pub fn incoming_mut(&mut self) -> &mut Bytes<'a> {
+        &mut self.incoming
+    }
+
+    /// Tokenize tokens and store them in self.
+    pub fn tokenize(&mut self) -> Result<(), LexError> {
+        while self.next_token()?.is_some() {}
+
+        Ok(())
+    }
+
+    /// Tokenize until the next token that is not skipped, store it in self and return a reference to it.
+    ///
+    /// Once the input has been exhausted, the EOF token is stored and returned if there is one,
+    /// after which `None` is returned.
+    pub fn next_token(&mut self) -> Result<Option<&Token<TokenType>>, LexError> {
+        while let Some(result) = self.incoming.next() {
+            match result {
+                Ok((index, byte)) => {
+                    if self.lex_byte(index, byte)? {
+                        return Ok(self.tokens.last());
+                    }
+                }
+                Err((index, error)) => return Err(LexError::other_indexed(index, error)),
+            }
+        }
+
+        if !self.finished {
+            self.finished = true;
+            if let Some(eof_token) = &self.eof_token {
+                let end = self.incoming.successes();
+                self.tokens.push(Token::at(eof_token.clone(), end));
+                return Ok(self.tokens.last());
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Lex a token starting at `byte`, returning whether a token was stored.
+    fn lex_byte(&mut self, index: usize, byte: u8) -> Result<bool, LexError> {
+        let next = self.incoming.peek();
+        self.incoming.reset_peek();
+        let location = GraphemeLocation::new(index, 0, index);
+
+        for creation_func in &self.creation_funcs {
+            let mut tokenizer = creation_func();
+            if !tokenizer.can_tokenize(
+                &LexContext::new(&self.tokens),
+                byte,
+                index,
+                next,
+                &mut self.context,
+            ) {
+                continue;
+            }
+
+            let token_count = self.tokens.len();
+            let token = tokenizer
+                .lex(
+                    &mut self.tokens,
+                    byte,
+                    index,
+                    &mut self.incoming,
+                    &mut self.context,
+                )
+                .map_err(|error| error.in_tokenizer(tokenizer.name()).or_at(location))?;
+            self.incoming.reset_peek();
+            if token.should_skip() {
+                return Ok(false);
+            }
+
+            let end_index = self.incoming.current_index();
+            let start_index = token_start(&self.tokens, token_count, index, end_index);
+            self.tokens
+                .push(Token::new(token, Some(start_index..=end_index)));
+            return Ok(true);
+        }
+
+        Err(LexError::at(
+            location,
+            LexError::other(format!("Failed to find tokenizer for byte {:#04x}", byte)),
+        ))
+    }
+}
+
+/// Streams tokens as they are lexed.
+///
+/// Each token is also stored in the lexer, as tokenizers may rely on previous tokens.
+impl<'a, TokenType: TokenValue, Context> Iterator for ByteLexer<'a, TokenType, Context> {
+    type Item = Result<Token<TokenType>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().map(|token| token.cloned()).transpose()
+    }
+}
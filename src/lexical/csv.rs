@@ -0,0 +1,252 @@
+use std::io::Read;
+
+use super::{
+    error::LexError, GraphemeLocation, Graphemes, LexContext, Lexer, SmallString, Token,
+    TokenValue, Tokenizer,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The tokens of delimiter-separated values, as lexed by a [CsvTokenizer].
+pub enum CsvToken {
+    /// The unquoted and unescaped text of a field.
+    Field(String),
+    /// The end of a record, lexed from a line break outside of quotes.
+    RecordEnd,
+}
+
+impl TokenValue for CsvToken {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a quote character is written within a quoted field.
+pub enum CsvEscape {
+    /// A quote is written twice, such as `"a ""quoted"" word"`, as in RFC 4180.
+    Doubled,
+    /// Any character preceded by the given character is taken literally, such as `"a \"quoted\" word"`.
+    Character(char),
+    /// Quotes cannot be written within a quoted field.
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The rules of a delimiter-separated values format, such as CSV or TSV.
+///
+/// The defaults follow RFC 4180: fields are separated by `,`, may be quoted with `"`, quotes
+/// within quoted fields are doubled, and quoted fields may contain line breaks.
+pub struct CsvDialect {
+    delimiter: char,
+    quote: Option<char>,
+    escape: CsvEscape,
+    newlines_in_quotes: bool,
+}
+
+impl Default for CsvDialect {
+    fn default() -> Self {
+        Self::csv()
+    }
+}
+
+impl CsvDialect {
+    /// Creates the dialect of comma-separated values.
+    pub fn csv() -> Self {
+        Self {
+            delimiter: ',',
+            quote: Some('"'),
+            escape: CsvEscape::Doubled,
+            newlines_in_quotes: true,
+        }
+    }
+
+    /// Creates the dialect of tab-separated values, which are never quoted.
+    pub fn tsv() -> Self {
+        Self {
+            delimiter: '\t',
+            quote: None,
+            escape: CsvEscape::None,
+            newlines_in_quotes: false,
+        }
+    }
+
+    /// Sets the character that separates fields and returns self.
+    pub fn delimiter(mut self, delimiter: char) -> Self {
+        self.delimiter = delimiter;
+        self
+    }
+
+    /// Sets the character that fields may be quoted with, if any, and returns self.
+    pub fn quote(mut self, quote: Option<char>) -> Self {
+        self.quote = quote;
+        self
+    }
+
+    /// Sets how quotes are written within quoted fields and returns self.
+    pub fn escape(mut self, escape: CsvEscape) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Sets whether quoted fields may contain line breaks and returns self.
+    pub fn newlines_in_quotes(mut self, newlines_in_quotes: bool) -> Self {
+        self.newlines_in_quotes = newlines_in_quotes;
+        self
+    }
+
+    /// Creates a lexer for `reader` that lexes values of this dialect.
+    pub fn lexer<'a, Reader: Read + 'a>(self, reader: Reader) -> Lexer<'a, CsvToken> {
+        Lexer::new(reader, false, None).tokenizer(move || CsvTokenizer::new(self))
+    }
+
+    fn is_delimiter(&self, grapheme: &str) -> bool {
+        grapheme.chars().eq([self.delimiter])
+    }
+
+    fn is_quote(&self, grapheme: &str) -> bool {
+        self.quote.is_some_and(|quote| grapheme.chars().eq([quote]))
+    }
+
+    fn is_escape(&self, grapheme: &str) -> bool {
+        match self.escape {
+            CsvEscape::Character(escape) => grapheme.chars().eq([escape]),
+            _ => false,
+        }
+    }
+}
+
+fn is_newline(grapheme: &str) -> bool {
+    matches!(grapheme, "\n" | "\r\n" | "\r")
+}
+
+/// Lexes delimiter-separated values following a [CsvDialect] into [CsvToken]s.
+///
+/// Each field after the first in a record is lexed along with the delimiter before it, so a field's
+/// range starts at that delimiter. An empty field at the start of a record has no range.
+///
+/// A record is ended by a line break, so the last record has no [CsvToken::RecordEnd] if the input
+/// does not end with one, and an empty line is a record without any fields.
+pub struct CsvTokenizer {
+    dialect: CsvDialect,
+}
+
+impl CsvTokenizer {
+    pub fn new(dialect: CsvDialect) -> Self {
+        Self { dialect }
+    }
+
+    /// Lexes the rest of a quoted field, whose opening quote has been consumed.
+    fn lex_quoted<'a>(
+        &self,
+        location: &GraphemeLocation,
+        incoming: &mut Graphemes<'a>,
+    ) -> Result<String, LexError> {
+        let mut field = String::new();
+        loop {
+            let grapheme = match incoming.next() {
+                Some(Ok(grapheme)) => grapheme,
+                Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
+                None => {
+                    return Err(LexError::unterminated(
+                        "quoted field",
+                        location.index..=location.index,
+                    ))
+                }
+            };
+
+            if self.dialect.is_quote(&grapheme) {
+                let doubled = self.dialect.escape == CsvEscape::Doubled
+                    && matches!(incoming.peek(), Some(Ok(next))
+                        if self.dialect.is_quote(&next));
+                incoming.reset_peek();
+                if !doubled {
+                    return Ok(field);
+                }
+                incoming.next();
+            } else if self.dialect.is_escape(&grapheme) {
+                match incoming.next() {
+                    Some(Ok(escaped)) => {
+                        field.push_str(&escaped);
+                        continue;
+                    }
+                    Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
+                    None => return Err(LexError::UnexpectedEndOfStream),
+                }
+            } else if is_newline(&grapheme) && !self.dialect.newlines_in_quotes {
+                return Err(LexError::other("Line break within a quoted field"));
+            }
+
+            field.push_str(&grapheme);
+        }
+    }
+}
+
+impl Tokenizer<CsvToken> for CsvTokenizer {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<CsvToken>,
+        _: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut (),
+    ) -> bool {
+        true
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        tokens: &'b mut Vec<Token<CsvToken>>,
+        grapheme: &str,
+        location: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut (),
+    ) -> Result<CsvToken, LexError> {
+        if is_newline(grapheme) {
+            return Ok(CsvToken::RecordEnd);
+        }
+
+        let mut field = String::new();
+        let mut first = Some(grapheme);
+        if self.dialect.is_delimiter(grapheme) {
+            if matches!(
+                tokens.last().map(Token::token),
+                None | Some(CsvToken::RecordEnd)
+            ) {
+                tokens.push(Token::from(CsvToken::Field(String::new())));
+            }
+            first = None;
+        }
+
+        let quoted = match first {
+            Some(grapheme) => self.dialect.is_quote(grapheme),
+            None => {
+                let quoted =
+                    matches!(incoming.peek(), Some(Ok(next)) if self.dialect.is_quote(&next));
+                incoming.reset_peek();
+                if quoted {
+                    incoming.next();
+                }
+                quoted
+            }
+        };
+
+        if quoted {
+            field = self.lex_quoted(location, incoming)?;
+        } else if let Some(grapheme) = first {
+            field.push_str(grapheme);
+        }
+
+        while let Some(next) = incoming.try_peek()? {
+            if self.dialect.is_delimiter(&next) || is_newline(&next) {
+                break;
+            }
+            if quoted {
+                return Err(LexError::other(format!(
+                    "Unexpected {:?} after a quoted field",
+                    next.as_str()
+                )));
+            }
+            field.push_str(&next);
+            incoming.next();
+        }
+        incoming.reset_peek();
+
+        Ok(CsvToken::Field(field))
+    }
+}
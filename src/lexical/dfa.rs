@@ -0,0 +1,230 @@
+use std::rc::Rc;
+
+use super::{
+    error::LexError, GraphemeLocation, Graphemes, LexContext, SmallString, Token, TokenValue,
+    Tokenizer,
+};
+
+type ClassFn = Box<dyn Fn(char) -> bool>;
+type MapFn<TokenType> = Box<dyn Fn(&str) -> TokenType>;
+
+struct Dfa<TokenType> {
+    classes: Vec<ClassFn>,
+    ascii: [Option<usize>; 128],
+    transitions: Vec<Vec<Option<usize>>>,
+    accepting: Vec<bool>,
+    map: MapFn<TokenType>,
+}
+
+impl<TokenType> Dfa<TokenType> {
+    /// Returns the class of the first character of `grapheme`.
+    fn class(&self, grapheme: &str) -> Option<usize> {
+        let character = grapheme.chars().next()?;
+        match self.ascii.get(character as usize) {
+            Some(class) => *class,
+            None => self.classes.iter().position(|class| class(character)),
+        }
+    }
+
+    fn step(&self, state: usize, grapheme: &str) -> Option<usize> {
+        let class = self.class(grapheme)?;
+        *self.transitions.get(state)?.get(class)?
+    }
+}
+
+/// A tokenizer that runs a deterministic finite automaton over graphemes, for tokens such as
+/// identifiers, numbers and whitespace that are hot enough for per-grapheme dispatch to matter.
+///
+/// Graphemes are sorted into character classes by their first character, where the first class
+/// that matches is used, and ASCII characters are classified through a lookup table.
+/// State `0` is the start state, and the longest sequence of graphemes that ends in an
+/// accepting state is consumed and passed to the mapping function.
+///
+/// The automaton is shared between clones, so a lexer can create a tokenizer by cloning one:
+/// `lexer.tokenizer(move || dfa.clone())`.
+pub struct DfaTokenizer<TokenType> {
+    dfa: Rc<Dfa<TokenType>>,
+}
+
+impl<TokenType> Clone for DfaTokenizer<TokenType> {
+    fn clone(&self) -> Self {
+        Self {
+            dfa: self.dfa.clone(),
+        }
+    }
+}
+
+impl<TokenType: 'static> DfaTokenizer<TokenType> {
+    /// Create a tokenizer with only a start state, which maps the matched text with `map`.
+    pub fn new<F: Fn(&str) -> TokenType + 'static>(map: F) -> Self {
+        Self {
+            dfa: Rc::new(Dfa {
+                classes: vec![],
+                ascii: [None; 128],
+                transitions: vec![vec![]],
+                accepting: vec![false],
+                map: Box::new(map),
+            }),
+        }
+    }
+
+    /// Create a tokenizer that matches one or more graphemes in the class `class`.
+    pub fn one_or_more<C, F>(class: C, map: F) -> Self
+    where
+        C: Fn(char) -> bool + 'static,
+        F: Fn(&str) -> TokenType + 'static,
+    {
+        Self::new(map)
+            .class(class)
+            .transition(0, 0, 1)
+            .transition(1, 0, 1)
+            .accept(1)
+    }
+
+    /// Create a tokenizer that matches a grapheme in the class `first` followed by any amount
+    /// of graphemes in the class `rest`, such as an identifier.
+    pub fn leading<C, R, F>(first: C, rest: R, map: F) -> Self
+    where
+        C: Fn(char) -> bool + 'static,
+        R: Fn(char) -> bool + 'static,
+        F: Fn(&str) -> TokenType + 'static,
+    {
+        Self::new(map)
+            .class(first)
+            .class(rest)
+            .transition(0, 0, 1)
+            .transition(1, 0, 1)
+            .transition(1, 1, 1)
+            .accept(1)
+    }
+
+    fn dfa_mut(&mut self) -> &mut Dfa<TokenType> {
+        Rc::get_mut(&mut self.dfa).expect("DfaTokenizer must not be modified after being cloned")
+    }
+
+    /// Add a character class, whose id is the amount of classes added before it, and return self.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tokenizer has been cloned.
+    pub fn class<C: Fn(char) -> bool + 'static>(mut self, class: C) -> Self {
+        let dfa = self.dfa_mut();
+        let id = dfa.classes.len();
+        for (character, ascii_class) in dfa.ascii.iter_mut().enumerate() {
+            if ascii_class.is_none() && class(character as u8 as char) {
+                *ascii_class = Some(id);
+            }
+        }
+        dfa.classes.push(Box::new(class));
+        self
+    }
+
+    /// Add a transition from the state `from` to the state `to` on a grapheme in the class `class`,
+    /// creating the states if they do not exist, and return self.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tokenizer has been cloned.
+    pub fn transition(mut self, from: usize, class: usize, to: usize) -> Self {
+        let dfa = self.dfa_mut();
+        let states = from.max(to) + 1;
+        if dfa.transitions.len() < states {
+            dfa.transitions.resize(states, vec![]);
+            dfa.accepting.resize(states, false);
+        }
+
+        let row = &mut dfa.transitions[from];
+        if row.len() <= class {
+            row.resize(class + 1, None);
+        }
+        row[class] = Some(to);
+        self
+    }
+
+    /// Mark the state `state` as accepting and return self.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tokenizer has been cloned.
+    pub fn accept(mut self, state: usize) -> Self {
+        let dfa = self.dfa_mut();
+        if dfa.accepting.len() <= state {
+            dfa.transitions.resize(state + 1, vec![]);
+            dfa.accepting.resize(state + 1, false);
+        }
+        dfa.accepting[state] = true;
+        self
+    }
+}
+
+impl<TokenType: TokenValue, Context> Tokenizer<TokenType, Context> for DfaTokenizer<TokenType> {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<TokenType>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        self.dfa.step(0, grapheme).is_some()
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<TokenType>>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        let dfa = &*self.dfa;
+        let mut state = match dfa.step(0, grapheme) {
+            Some(state) => state,
+            None => return Err(LexError::other(format!("Unexpected {:?}", grapheme))),
+        };
+
+        let mut accepted = dfa.accepting[state].then_some(0);
+        let mut peeked = 0;
+        let mut ended = false;
+        loop {
+            state = match incoming.peek() {
+                Some(Ok(next)) => match dfa.step(state, &next) {
+                    Some(state) => state,
+                    None => break,
+                },
+                Some(Err(_)) => break,
+                None => {
+                    ended = true;
+                    break;
+                }
+            };
+            peeked += 1;
+            if dfa.accepting[state] {
+                accepted = Some(peeked);
+            }
+        }
+        incoming.reset_peek();
+
+        let accepted = match accepted {
+            Some(accepted) => accepted,
+            None if ended => return Err(LexError::UnexpectedEndOfStream),
+            None => {
+                return Err(LexError::other(format!(
+                    "Unexpected grapheme while lexing a token starting with {:?}",
+                    grapheme
+                )))
+            }
+        };
+
+        let mut text = String::from(grapheme);
+        for _ in 0..accepted {
+            match incoming.next() {
+                Some(Ok(grapheme)) => text.push_str(&grapheme),
+                Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
+                None => return Err(LexError::UnexpectedEndOfStream),
+            }
+        }
+
+        Ok((dfa.map)(&text))
+    }
+}
@@ -0,0 +1,175 @@
+//! Comparison of token streams, such as the tokens of a file before and after an edit.
+//!
+//! Tokens are compared by value only, so tokens that merely moved because of an edit elsewhere
+//! are not reported.
+
+use super::{Span, Token, TokenValue};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A difference between two token streams, as computed by [token_diff].
+///
+/// Indexes refer to the tokens within the old or new stream, and spans are those of the tokens.
+pub enum TokenEdit {
+    /// A token of the old stream that is not in the new stream.
+    Removed { old: usize, span: Option<Span> },
+    /// A token of the new stream that is not in the old stream.
+    Inserted { new: usize, span: Option<Span> },
+    /// A token of the old stream that was replaced by a token of the new stream.
+    Changed {
+        old: usize,
+        new: usize,
+        old_span: Option<Span>,
+        new_span: Option<Span>,
+    },
+}
+
+/// Computes the edits that turn the tokens of `old` into the tokens of `new`, in order of the streams.
+///
+/// The edits form a longest common subsequence of the two streams. Within each run of differing
+/// tokens, removed tokens are paired with inserted tokens as [TokenEdit::Changed], and the rest are
+/// reported as removed or inserted. Tokens shared by the start and end of both streams are skipped
+/// up front, so the cost is quadratic only in the size of the edited region. The subsequence is
+/// found with Hirschberg's algorithm, so memory is linear in the size of the streams.
+pub fn token_diff<TokenType: TokenValue + PartialEq>(
+    old: &[Token<TokenType>],
+    new: &[Token<TokenType>],
+) -> Vec<TokenEdit> {
+    let same = |old: &Token<TokenType>, new: &Token<TokenType>| old.token() == new.token();
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| same(old, new))
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| same(old, new))
+        .count();
+    let old_edited: Vec<_> = old[prefix..old.len() - suffix]
+        .iter()
+        .map(Token::token)
+        .collect();
+    let new_edited: Vec<_> = new[prefix..new.len() - suffix]
+        .iter()
+        .map(Token::token)
+        .collect();
+
+    let mut common = vec![];
+    common_subsequence(&old_edited, &new_edited, prefix, prefix, &mut common);
+    common.push((prefix + old_edited.len(), prefix + new_edited.len()));
+
+    let mut edits = vec![];
+    let mut removed = vec![];
+    let mut inserted = vec![];
+    let (mut i, mut j) = (prefix, prefix);
+    for (old_index, new_index) in common {
+        removed.extend(i..old_index);
+        inserted.extend(j..new_index);
+        flush_run(&mut edits, &mut removed, &mut inserted, old, new);
+        (i, j) = (old_index + 1, new_index + 1);
+    }
+
+    edits
+}
+
+/// Pushes the index pairs of a longest common subsequence of `old` and `new` onto `common`, offset
+/// by `old_offset` and `new_offset`, using Hirschberg's divide and conquer over the rows of `old`.
+fn common_subsequence<T: PartialEq>(
+    old: &[T],
+    new: &[T],
+    old_offset: usize,
+    new_offset: usize,
+    common: &mut Vec<(usize, usize)>,
+) {
+    if old.is_empty() || new.is_empty() {
+        return;
+    }
+    if old.len() == 1 {
+        if let Some(j) = new.iter().position(|new| *new == old[0]) {
+            common.push((old_offset, new_offset + j));
+        }
+        return;
+    }
+
+    // Split `new` where the subsequences of both halves of `old` add up to the longest.
+    let middle = old.len() / 2;
+    let forward = subsequence_lengths(&old[..middle], new, false);
+    let backward = subsequence_lengths(&old[middle..], new, true);
+    let split = (0..=new.len())
+        .max_by_key(|&j| (forward[j] + backward[new.len() - j], std::cmp::Reverse(j)))
+        .unwrap_or(0);
+
+    common_subsequence(
+        &old[..middle],
+        &new[..split],
+        old_offset,
+        new_offset,
+        common,
+    );
+    common_subsequence(
+        &old[middle..],
+        &new[split..],
+        old_offset + middle,
+        new_offset + split,
+        common,
+    );
+}
+
+/// Returns the lengths of the longest common subsequences of `old` and each prefix of `new`,
+/// or of the reversed `old` and each prefix of the reversed `new` if `reverse` is true.
+fn subsequence_lengths<T: PartialEq>(old: &[T], new: &[T], reverse: bool) -> Vec<usize> {
+    let at = |length: usize, index: usize| match reverse {
+        true => length - 1 - index,
+        false => index,
+    };
+
+    let mut lengths = vec![0; new.len() + 1];
+    for i in 0..old.len() {
+        let mut diagonal = 0;
+        for j in 0..new.len() {
+            let above = lengths[j + 1];
+            lengths[j + 1] = match old[at(old.len(), i)] == new[at(new.len(), j)] {
+                true => diagonal + 1,
+                false => above.max(lengths[j]),
+            };
+            diagonal = above;
+        }
+    }
+
+    lengths
+}
+
+/// Pushes the edits of a run of removed and inserted tokens, pairing them up as changes.
+fn flush_run<TokenType: TokenValue>(
+    edits: &mut Vec<TokenEdit>,
+    removed: &mut Vec<usize>,
+    inserted: &mut Vec<usize>,
+    old: &[Token<TokenType>],
+    new: &[Token<TokenType>],
+) {
+    let changed = removed.len().min(inserted.len());
+    for (&old_index, &new_index) in removed.iter().zip(inserted.iter()) {
+        edits.push(TokenEdit::Changed {
+            old: old_index,
+            new: new_index,
+            old_span: old[old_index].span(),
+            new_span: new[new_index].span(),
+        });
+    }
+    for &old_index in &removed[changed..] {
+        edits.push(TokenEdit::Removed {
+            old: old_index,
+            span: old[old_index].span(),
+        });
+    }
+    for &new_index in &inserted[changed..] {
+        edits.push(TokenEdit::Inserted {
+            new: new_index,
+            span: new[new_index].span(),
+        });
+    }
+
+    removed.clear();
+    inserted.clear();
+}
@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+use super::{Token, TokenValue};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+/// Associates doc comments within skipped tokens, such as those kept by
+/// [Lexer::keep_skipped](super::Lexer::keep_skipped), with the significant token that follows them.
+///
+/// A comment is a doc comment if its text starts with one of the line prefixes, such as `///`, or is
+/// enclosed by one of the block delimiters, such as `/**` and `*/`.
+pub struct DocComments {
+    line_prefixes: Vec<String>,
+    blocks: Vec<(String, String)>,
+}
+
+impl DocComments {
+    /// Creates an extractor that does not recognize any doc comments.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an extractor for the outer doc comments of Rust, `///` and `/** */`.
+    pub fn rust() -> Self {
+        Self::new().line("///").block("/**", "*/")
+    }
+
+    /// Recognizes comments starting with `prefix` as doc comments and returns self.
+    pub fn line<S: Into<String>>(mut self, prefix: S) -> Self {
+        self.line_prefixes.push(prefix.into());
+        self
+    }
+
+    /// Recognizes comments enclosed by `open` and `close` as doc comments and returns self.
+    pub fn block<O: Into<String>, C: Into<String>>(mut self, open: O, close: C) -> Self {
+        self.blocks.push((open.into(), close.into()));
+        self
+    }
+
+    /// Returns the documentation within `comment`, if it is a doc comment.
+    ///
+    /// The prefix of a line comment is removed along with a single space after it, and the
+    /// delimiters of a block comment are removed along with the whitespace around its text.
+    pub fn strip<'c>(&self, comment: &'c str) -> Option<&'c str> {
+        let comment = comment.trim_end_matches(['\n', '\r']);
+        let block = self.blocks.iter().find_map(|(open, close)| {
+            comment
+                .strip_prefix(open.as_str())?
+                .strip_suffix(close.as_str())
+                .map(str::trim)
+        });
+        block.or_else(|| {
+            self.line_prefixes.iter().find_map(|prefix| {
+                let text = comment.strip_prefix(prefix.as_str())?;
+                Some(text.strip_prefix(' ').unwrap_or(text))
+            })
+        })
+    }
+
+    /// Attaches the doc comments within `skipped` to the tokens of `tokens` that follow them, returning
+    /// the documentation of each documented token by its index within `tokens`.
+    ///
+    /// `comment` returns the text of a skipped token if it is a comment. Consecutive doc comments
+    /// are joined with line breaks, while tokens without a range, such as the EOF token, are never
+    /// documented. Both slices must be in the order the tokens were lexed.
+    pub fn extract<TokenType, F>(
+        &self,
+        tokens: &[Token<TokenType>],
+        skipped: &[Token<TokenType>],
+        comment: F,
+    ) -> BTreeMap<usize, String>
+    where
+        TokenType: TokenValue,
+        F: Fn(&TokenType) -> Option<&str>,
+    {
+        let mut docs = BTreeMap::<usize, String>::new();
+        let mut next = 0;
+        for token in skipped {
+            let doc = match comment(token.token()).and_then(|text| self.strip(text)) {
+                Some(doc) => doc,
+                None => continue,
+            };
+
+            let end = token.range_raw().end();
+            while next < tokens.len() && tokens[next].range_raw().start() <= end {
+                next += 1;
+            }
+            if next == tokens.len() {
+                break;
+            }
+
+            let text = docs.entry(next).or_default();
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str(doc);
+        }
+
+        docs
+    }
+}
@@ -0,0 +1,138 @@
+pub use itertools::Either;
+
+use super::{
+    error::LexError, Clusters, GraphemeLocation, Graphemes, LexContext, Lexer, SmallString, Token,
+    TokenValue, Tokenizer,
+};
+
+impl<Left: TokenValue, Right: TokenValue> TokenValue for Either<Left, Right> {
+    fn should_skip(&self) -> bool {
+        match self {
+            Either::Left(token) => token.should_skip(),
+            Either::Right(token) => token.should_skip(),
+        }
+    }
+
+    fn display_name(&self) -> String {
+        match self {
+            Either::Left(token) => token.display_name(),
+            Either::Right(token) => token.display_name(),
+        }
+    }
+}
+
+/// Creates the inner lexer of an [EmbeddedTokenizer] over the stream shared with the outer lexer.
+pub type InnerLexerFn<Inner> = for<'g> fn(Graphemes<'g>) -> Lexer<'g, Inner>;
+
+/// A tokenizer that composes two lexers with different token types over one input, by delegating
+/// regions between an opening and a closing delimiter to an inner lexer. For example, an outer template
+/// lexer can delegate `{{ ... }}` regions to an inner expression lexer.
+///
+/// The outer lexer's token type is [Either], with the outer tokens on the left and the inner tokens on
+/// the right. Both lexers share the same grapheme stream, so the ranges of all tokens are consistent.
+///
+/// When the opening delimiter is read, the opening token is stored, followed by every inner token,
+/// and then the closing token. Inner tokens are given no source, as the inner lexer is unaware of it.
+#[derive(Clone)]
+pub struct EmbeddedTokenizer<Outer: TokenValue, Inner: TokenValue> {
+    open: (Vec<SmallString>, Outer),
+    close: (String, Outer),
+    inner: InnerLexerFn<Inner>,
+}
+
+impl<Outer: TokenValue, Inner: TokenValue> EmbeddedTokenizer<Outer, Inner> {
+    /// Create a tokenizer that delegates regions starting with the text of `open` and ending with
+    /// the text of `close` to the lexer created by `inner`, storing the tokens of `open` and `close`
+    /// for the delimiters.
+    ///
+    /// # Panics
+    ///
+    /// Panics if either delimiter is empty.
+    pub fn new<O: AsRef<str>, C: Into<String>>(
+        open: (O, Outer),
+        close: (C, Outer),
+        inner: InnerLexerFn<Inner>,
+    ) -> Self {
+        let graphemes: Vec<_> = Clusters::new(open.0.as_ref().chars().map(Ok))
+            .flatten()
+            .collect();
+        let close_text = close.0.into();
+        assert!(
+            !graphemes.is_empty() && !close_text.is_empty(),
+            "Delimiters must not be empty"
+        );
+
+        Self {
+            open: (graphemes, open.1),
+            close: (close_text, close.1),
+            inner,
+        }
+    }
+}
+
+impl<Outer: TokenValue, Inner: TokenValue, Context> Tokenizer<Either<Outer, Inner>, Context>
+    for EmbeddedTokenizer<Outer, Inner>
+{
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<Either<Outer, Inner>>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        next: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        let open = &self.open.0;
+        open[0] == grapheme
+            && match (open.get(1), next) {
+                (None, _) => true,
+                (Some(second), Some(next)) => second == next,
+                (Some(_), None) => false,
+            }
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        tokens: &'b mut Vec<Token<Either<Outer, Inner>>>,
+        _: &str,
+        location: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<Either<Outer, Inner>, LexError> {
+        for expected in &self.open.0[1..] {
+            match incoming.next() {
+                Some(Ok(grapheme)) if grapheme == expected.as_str() => {}
+                Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
+                _ => {
+                    return Err(LexError::other(format!(
+                        "Expected {:?} while reading an opening delimiter",
+                        expected
+                    )))
+                }
+            }
+        }
+        tokens.push(Token::new(
+            Either::Left(self.open.1.clone()),
+            Some(location.index..=incoming.current_index()),
+        ));
+
+        let shared = std::mem::replace(incoming, Graphemes::from_chunks(std::iter::empty()));
+        let mut inner = (self.inner)(shared);
+        let closed = inner.lex_until(&self.close.0);
+        let inner_tokens = std::mem::take(inner.tokens_mut());
+        *incoming = inner.into_incoming();
+
+        if !closed? {
+            return Err(LexError::unterminated(
+                "embedded region",
+                location.index..=location.index,
+            ));
+        }
+
+        tokens.extend(
+            inner_tokens
+                .into_iter()
+                .map(|token| token.map(Either::Right)),
+        );
+        Ok(Either::Left(self.close.1.clone()))
+    }
+}
@@ -1,31 +1,145 @@
-use std::error::Error;
+use std::{error::Error, ops::Range};
+
+use super::{GraphemeLocation, Limit, Span};
 
 #[derive(Debug)]
 /// Represents an error that occurs when lexing.
-pub enum LexError<'a> {
+///
+/// Boxed errors must be [Send] and [Sync], so that a [LexError] can be sent between threads and
+/// returned through error handling crates such as anyhow.
+///
+/// With the `miette` feature, a [LexError] implements `miette::Diagnostic` with only a code and help,
+/// as it does not hold the source text. To report it with the source text and a label at its
+/// location, wrap it in a [SourceReport](crate::diagnostics::SourceReport).
+pub enum LexError {
     /// An error that you can throw when a token requires that within it's lexical logic,
     /// the stream must not cease to return graphemes.
     UnexpectedEndOfStream,
     /// An error that simply holds a boxed error.
-    Other(Box<dyn Error + 'a>),
+    Other(Box<dyn Error + Send + Sync>),
     /// Same as [Self::Other], except with an accompanying index
     /// representing the location of the failed grapheme.
-    OtherIndexed(usize, Box<dyn Error + 'a>),
+    OtherIndexed(usize, Box<dyn Error + Send + Sync>),
+    /// The input ended before a token that started at `start_span` was terminated,
+    /// such as a string missing its closing quote. `kind` describes the token.
+    UnterminatedToken { kind: String, start_span: Span },
+    /// Lexing was stopped because `limit` of the lexer's [LexLimits](super::LexLimits) exceeded `max`.
+    LimitExceeded { limit: Limit, max: usize },
+    /// Lexing was stopped because its [CancelFlag](super::CancelFlag) was cancelled.
+    Cancelled,
+    /// Wraps an error returned by the tokenizer named `name`. See [Tokenizer::name](super::Tokenizer::name).
+    Tokenizer { name: String, error: Box<LexError> },
+    /// Wraps another error with the location of the grapheme at which it occurred.
+    Located(GraphemeLocation, Box<LexError>),
 }
 
-impl<'a> LexError<'a> {
+impl LexError {
     /// Helper for creating a [LexError::Other].
-    pub fn other<T: Into<Box<dyn Error + 'a>>>(error: T) -> Self {
+    pub fn other<T: Into<Box<dyn Error + Send + Sync>>>(error: T) -> Self {
         Self::Other(error.into())
     }
 
     /// Helper for creating a [LexError::OtherIndexed].
-    pub fn other_indexed<T: Into<Box<dyn Error + 'a>>>(index: usize, error: T) -> Self {
+    pub fn other_indexed<T: Into<Box<dyn Error + Send + Sync>>>(index: usize, error: T) -> Self {
         Self::OtherIndexed(index, error.into())
     }
+
+    /// Helper for creating a [LexError::UnterminatedToken].
+    pub fn unterminated<K: Into<String>, S: Into<Span>>(kind: K, start_span: S) -> Self {
+        Self::UnterminatedToken {
+            kind: kind.into(),
+            start_span: start_span.into(),
+        }
+    }
+
+    /// Helper for creating a [LexError::Located].
+    pub fn at(location: GraphemeLocation, error: LexError) -> Self {
+        Self::Located(location, Box::new(error))
+    }
+
+    /// Attributes the error to the tokenizer named `name`, keeping any location outermost.
+    pub fn in_tokenizer(self, name: &str) -> Self {
+        match self {
+            LexError::Located(location, error) => Self::at(location, error.in_tokenizer(name)),
+            LexError::Tokenizer { .. } => self,
+            error => Self::Tokenizer {
+                name: name.to_string(),
+                error: Box::new(error),
+            },
+        }
+    }
+
+    /// Returns the name of the tokenizer that returned the error, if it is known.
+    pub fn tokenizer(&self) -> Option<&str> {
+        match self {
+            LexError::Tokenizer { name, .. } => Some(name),
+            LexError::Located(_, error) => error.tokenizer(),
+            _ => None,
+        }
+    }
+
+    /// Returns the underlying error, without the [LexError::Located] and [LexError::Tokenizer] wrappers.
+    pub fn inner(&self) -> &LexError {
+        match self {
+            LexError::Located(_, error) | LexError::Tokenizer { error, .. } => error.inner(),
+            error => error,
+        }
+    }
+
+    /// Returns the location of the error, if it has one.
+    pub fn location(&self) -> Option<&GraphemeLocation> {
+        match self {
+            LexError::Located(location, _) => Some(location),
+            _ => None,
+        }
+    }
+
+    /// Returns the exclusive range of grapheme indexes that the error occurred at, if it is known.
+    ///
+    /// This is the grapheme of its location, or for an error without one, the grapheme
+    /// that failed to be read or the start of the unterminated token.
+    pub fn grapheme_span(&self) -> Option<Range<usize>> {
+        match self {
+            LexError::Located(location, _) => Some(location.index..location.index + 1),
+            LexError::OtherIndexed(index, _) => Some(*index..index + 1),
+            LexError::UnterminatedToken { start_span, .. } => Some(start_span.grapheme_span()),
+            LexError::Tokenizer { error, .. } => error.grapheme_span(),
+            _ => None,
+        }
+    }
+
+    /// Returns whether the error was caused by the input ending early, being either a
+    /// [LexError::UnexpectedEndOfStream] or a [LexError::UnterminatedToken], meaning that more
+    /// input could allow lexing to succeed.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(
+            self.inner(),
+            LexError::UnexpectedEndOfStream | LexError::UnterminatedToken { .. }
+        )
+    }
+
+    /// Returns whether the error is a [LexError::UnexpectedEndOfStream].
+    ///
+    /// See [LexError::is_incomplete], which also covers [LexError::UnterminatedToken].
+    pub fn is_unexpected_end(&self) -> bool {
+        matches!(self.inner(), LexError::UnexpectedEndOfStream)
+    }
+
+    /// Returns whether lexing was stopped by a [CancelFlag](super::CancelFlag).
+    pub fn is_cancelled(&self) -> bool {
+        matches!(self.inner(), LexError::Cancelled)
+    }
+
+    /// Attaches `location` to the error, unless it already has a location.
+    pub fn or_at(self, location: GraphemeLocation) -> Self {
+        match self {
+            LexError::Located(..) => self,
+            error => Self::at(location, error),
+        }
+    }
 }
 
-impl std::fmt::Display for LexError<'_> {
+impl std::fmt::Display for LexError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             LexError::UnexpectedEndOfStream => write!(
@@ -38,8 +152,31 @@ impl std::fmt::Display for LexError<'_> {
                 "Error lexing the grapheme at index: {}. The error: {}",
                 index, error
             ),
+            LexError::UnterminatedToken { kind, start_span } => write!(
+                f,
+                "Encountered the end of the input before the {} starting at grapheme index {} was terminated.",
+                kind,
+                start_span.start()
+            ),
+            LexError::LimitExceeded { limit, max } => {
+                let limit = match limit {
+                    Limit::Tokens => "tokens",
+                    Limit::Bytes => "bytes of input",
+                    Limit::TokenLength => "bytes in a single token",
+                };
+                write!(f, "Exceeded the limit of {} {}.", max, limit)
+            }
+            LexError::Cancelled => write!(f, "Lexing was cancelled."),
+            LexError::Tokenizer { name, error } => {
+                write!(f, "Error in the tokenizer {}: {}", name, error)
+            }
+            LexError::Located(location, error) => write!(
+                f,
+                "Error lexing at line: {}, offset: {}. The error: {}",
+                location.line, location.offset, error
+            ),
         }
     }
 }
 
-impl Error for LexError<'_> {}
+impl Error for LexError {}
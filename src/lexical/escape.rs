@@ -0,0 +1,330 @@
+//! Decoding of escape sequences within the raw text of string-like tokens.
+//!
+//! A tokenizer collects the raw text between its delimiters and decodes it with [decode_escapes].
+//! Errors carry the byte offset of the offending sequence within the raw text, which
+//! [EscapeError::graphemes] converts into grapheme indexes so that the error can be located
+//! precisely within the token.
+
+use std::{
+    error::Error,
+    fmt::Display,
+    ops::{Range, RangeInclusive},
+};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// How a unicode escape such as `\u00e9` is written, if at all.
+pub enum UnicodeEscape {
+    /// Exactly four hex digits, such as `\u00e9`, where a surrogate pair is written as two
+    /// escapes, as in JSON and JavaScript.
+    FourDigits,
+    /// One to six hex digits within braces, such as `\u{1f600}`, as in Rust.
+    Braced,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// What to do with an escape sequence that the policy does not define.
+pub enum UnknownEscape {
+    /// Fail with [EscapeErrorKind::Unknown].
+    Error,
+    /// Keep the sequence as it was written, including the escape character.
+    Keep,
+    /// Drop the escape character and keep the character after it.
+    Literal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The escape sequences that [decode_escapes] understands.
+pub struct EscapePolicy {
+    escape: char,
+    mappings: Vec<(char, char)>,
+    unicode: Option<UnicodeEscape>,
+    hex: bool,
+    unknown: UnknownEscape,
+}
+
+impl Default for EscapePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EscapePolicy {
+    /// Creates a policy with the common escapes `\\`, `\"`, `\'`, `\n`, `\r`, `\t` and `\0`,
+    /// which fails on any other escape.
+    pub fn new() -> Self {
+        Self::empty('\\')
+            .map('\\', '\\')
+            .map('"', '"')
+            .map('\'', '\'')
+            .map('n', '\n')
+            .map('r', '\r')
+            .map('t', '\t')
+            .map('0', '\0')
+    }
+
+    /// Creates a policy without any escapes, where sequences start with `escape`.
+    pub fn empty(escape: char) -> Self {
+        Self {
+            escape,
+            mappings: vec![],
+            unicode: None,
+            hex: false,
+            unknown: UnknownEscape::Error,
+        }
+    }
+
+    /// Creates the policy of JSON strings.
+    pub fn json() -> Self {
+        Self::empty('\\')
+            .map('"', '"')
+            .map('\\', '\\')
+            .map('/', '/')
+            .map('b', '\u{8}')
+            .map('f', '\u{c}')
+            .map('n', '\n')
+            .map('r', '\r')
+            .map('t', '\t')
+            .unicode(Some(UnicodeEscape::FourDigits))
+    }
+
+    /// Creates the policy of Rust string literals, apart from line continuations.
+    pub fn rust() -> Self {
+        Self::new().unicode(Some(UnicodeEscape::Braced)).hex(true)
+    }
+
+    /// Adds an escape where `escaped` written after the escape character stands for `decoded`,
+    /// and returns self.
+    pub fn map(mut self, escaped: char, decoded: char) -> Self {
+        self.mappings.retain(|(existing, _)| *existing != escaped);
+        self.mappings.push((escaped, decoded));
+        self
+    }
+
+    /// Sets how unicode escapes are written, if at all, and returns self.
+    pub fn unicode(mut self, unicode: Option<UnicodeEscape>) -> Self {
+        self.unicode = unicode;
+        self
+    }
+
+    /// Sets whether ASCII characters can be written as `\x` followed by two hex digits, and returns self.
+    pub fn hex(mut self, hex: bool) -> Self {
+        self.hex = hex;
+        self
+    }
+
+    /// Sets what to do with undefined escapes and returns self.
+    pub fn unknown(mut self, unknown: UnknownEscape) -> Self {
+        self.unknown = unknown;
+        self
+    }
+
+    /// Returns the character that starts an escape sequence.
+    pub fn escape_char(&self) -> char {
+        self.escape
+    }
+
+    fn mapping(&self, escaped: char) -> Option<char> {
+        self.mappings
+            .iter()
+            .find(|(existing, _)| *existing == escaped)
+            .map(|(_, decoded)| *decoded)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Why an escape sequence could not be decoded.
+pub enum EscapeErrorKind {
+    /// The escape sequence is not defined by the policy.
+    Unknown(char),
+    /// The text ended within the escape sequence.
+    Incomplete,
+    /// The escape sequence is missing hex digits, or a brace around them.
+    InvalidHex,
+    /// The escape sequence encodes a value that is not a character, such as a lone surrogate.
+    InvalidCodePoint(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An escape sequence that could not be decoded by [decode_escapes].
+pub struct EscapeError {
+    /// The byte offset of the escape character within the raw text.
+    pub offset: usize,
+    /// The amount of bytes of the sequence that were read before the problem was found.
+    pub len: usize,
+    pub kind: EscapeErrorKind,
+}
+
+impl EscapeError {
+    /// Returns the range of bytes of the sequence within the raw text.
+    pub fn byte_range(&self) -> Range<usize> {
+        self.offset..self.offset + self.len
+    }
+
+    /// Returns the inclusive range of grapheme indexes of the sequence within `raw`, the text the
+    /// error was found in. Adding the index of the first grapheme of `raw` locates it within the input.
+    pub fn graphemes(&self, raw: &str) -> RangeInclusive<usize> {
+        let span = self.grapheme_span(raw);
+        span.start..=span.end - 1
+    }
+
+    /// Returns the exclusive range of grapheme indexes of the sequence within `raw`.
+    /// See [EscapeError::graphemes].
+    pub fn grapheme_span(&self, raw: &str) -> Range<usize> {
+        let start = raw
+            .get(..self.offset)
+            .map_or(0, |before| before.graphemes(true).count());
+        let len = raw
+            .get(self.byte_range())
+            .map_or(1, |sequence| sequence.graphemes(true).count());
+        start..start + len.max(1)
+    }
+}
+
+impl Display for EscapeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            EscapeErrorKind::Unknown(escaped) => {
+                write!(f, "Unknown escape sequence {:?}", escaped)?
+            }
+            EscapeErrorKind::Incomplete => write!(f, "Incomplete escape sequence")?,
+            EscapeErrorKind::InvalidHex => write!(f, "Invalid hex digits in escape sequence")?,
+            EscapeErrorKind::InvalidCodePoint(code) => write!(
+                f,
+                "Escape sequence encodes the invalid character {:#x}",
+                code
+            )?,
+        }
+        write!(f, " at byte {}", self.offset)
+    }
+}
+
+impl Error for EscapeError {}
+
+/// Reads escape sequences from raw text, keeping track of where the current sequence started.
+struct Decoder<'r> {
+    raw: &'r str,
+    chars: std::iter::Peekable<std::str::CharIndices<'r>>,
+    start: usize,
+}
+
+impl Decoder<'_> {
+    fn error(&mut self, kind: EscapeErrorKind) -> EscapeError {
+        let end = self
+            .chars
+            .peek()
+            .map_or(self.raw.len(), |(index, _)| *index);
+        EscapeError {
+            offset: self.start,
+            len: end - self.start,
+            kind,
+        }
+    }
+
+    fn next(&mut self) -> Result<char, EscapeError> {
+        match self.chars.next() {
+            Some((_, character)) => Ok(character),
+            None => Err(self.error(EscapeErrorKind::Incomplete)),
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.chars.next_if(|(_, next)| *next == expected).is_some()
+    }
+
+    fn hex_digits(&mut self, min: usize, max: usize) -> Result<u32, EscapeError> {
+        let mut value = 0;
+        let mut digits = 0;
+        while digits < max {
+            match self.chars.peek().and_then(|(_, next)| next.to_digit(16)) {
+                Some(digit) => value = value * 16 + digit,
+                None if self.chars.peek().is_none() && digits < min => {
+                    return Err(self.error(EscapeErrorKind::Incomplete))
+                }
+                None => break,
+            }
+            self.chars.next();
+            digits += 1;
+        }
+
+        match digits < min {
+            true => Err(self.error(EscapeErrorKind::InvalidHex)),
+            false => Ok(value),
+        }
+    }
+
+    fn character(&mut self, code: u32) -> Result<char, EscapeError> {
+        char::from_u32(code).ok_or_else(|| self.error(EscapeErrorKind::InvalidCodePoint(code)))
+    }
+
+    fn unicode(&mut self, unicode: UnicodeEscape, escape: char) -> Result<char, EscapeError> {
+        if unicode == UnicodeEscape::Braced {
+            if !self.eat('{') {
+                return Err(self.error(EscapeErrorKind::InvalidHex));
+            }
+            let code = self.hex_digits(1, 6)?;
+            if !self.eat('}') {
+                return Err(self.error(EscapeErrorKind::InvalidHex));
+            }
+            return self.character(code);
+        }
+
+        let code = self.hex_digits(4, 4)?;
+        if !(0xd800..0xdc00).contains(&code) {
+            return self.character(code);
+        }
+
+        // A high surrogate must be followed by an escaped low surrogate.
+        if !self.eat(escape) || !self.eat('u') {
+            return Err(self.error(EscapeErrorKind::InvalidCodePoint(code)));
+        }
+        let low = self.hex_digits(4, 4)?;
+        if !(0xdc00..0xe000).contains(&low) {
+            return Err(self.error(EscapeErrorKind::InvalidCodePoint(low)));
+        }
+        self.character(0x10000 + ((code - 0xd800) << 10) + (low - 0xdc00))
+    }
+}
+
+/// Decodes the escape sequences within `raw`, the text of a token without its delimiters,
+/// following `policy`.
+pub fn decode_escapes(raw: &str, policy: &EscapePolicy) -> Result<String, EscapeError> {
+    let mut decoded = String::with_capacity(raw.len());
+    let mut decoder = Decoder {
+        raw,
+        chars: raw.char_indices().peekable(),
+        start: 0,
+    };
+
+    while let Some((index, character)) = decoder.chars.next() {
+        if character != policy.escape {
+            decoded.push(character);
+            continue;
+        }
+
+        decoder.start = index;
+        let escaped = decoder.next()?;
+        let character = match (policy.mapping(escaped), escaped, policy.unicode) {
+            (Some(character), _, _) => character,
+            (None, 'u', Some(unicode)) => decoder.unicode(unicode, policy.escape)?,
+            (None, 'x', _) if policy.hex => match decoder.hex_digits(2, 2)? {
+                code @ 0..=0x7f => decoder.character(code)?,
+                code => return Err(decoder.error(EscapeErrorKind::InvalidCodePoint(code))),
+            },
+            (None, escaped, _) => match policy.unknown {
+                UnknownEscape::Error => {
+                    return Err(decoder.error(EscapeErrorKind::Unknown(escaped)))
+                }
+                UnknownEscape::Keep => {
+                    decoded.push(policy.escape);
+                    escaped
+                }
+                UnknownEscape::Literal => escaped,
+            },
+        };
+        decoded.push(character);
+    }
+
+    Ok(decoded)
+}
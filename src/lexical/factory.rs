@@ -0,0 +1,187 @@
+use std::{io::Read, rc::Rc};
+
+use super::{
+    CancelFlag, LexLimits, Lexer, Progress, TokenSink, TokenValue, Tokenizer, TokenizerFn,
+};
+
+type SharedShebangFn<'a, TokenType> = Rc<dyn Fn(&str) -> Option<TokenType> + 'a>;
+type SharedSkipFn<'a, TokenType> = Rc<dyn Fn(&TokenType) -> bool + 'a>;
+type SinkFn<'a, TokenType> = Rc<dyn Fn() -> Box<dyn TokenSink<TokenType> + 'a> + 'a>;
+type SharedProgressFn<'a> = Rc<dyn Fn(Progress) + 'a>;
+
+/// Registers tokenizers once and creates a [Lexer] for each input that shares them, for lexing many
+/// small inputs such as header or log lines without rebuilding the lexer's setup each time.
+///
+/// Each created lexer creates each tokenizer once, when the lexer is created, and
+/// [resets](Tokenizer::reset) it before each token where a [Lexer] would otherwise create a new
+/// one. Tokenizers are not shared between lexers, so lexers of the same factory may be used at the
+/// same time, such as to lex an embedded region.
+///
+/// Each created lexer starts with the factory's tokenizers, EOF token and options, and may have
+/// further tokenizers or options added without affecting the factory.
+pub struct LexerFactory<'a, TokenType: TokenValue, Context = ()> {
+    creation_funcs: Vec<Box<dyn TokenizerFn<'a, TokenType, Context>>>,
+    eof_token: Option<TokenType>,
+    is_lossy: bool,
+    skip_bom: bool,
+    shebang: Option<SharedShebangFn<'a, TokenType>>,
+    skip_if: Option<SharedSkipFn<'a, TokenType>>,
+    keep_skipped: bool,
+    sink: Option<(SinkFn<'a, TokenType>, usize)>,
+    store_source: bool,
+    tab_width: Option<usize>,
+    progress: Option<(usize, SharedProgressFn<'a>)>,
+    cancel_flag: Option<CancelFlag>,
+    limits: LexLimits,
+}
+
+impl<'a, TokenType: TokenValue + 'a, Context: 'a> LexerFactory<'a, TokenType, Context> {
+    /// Create a lexer factory.
+    pub fn new(is_lossy: bool, eof_token: Option<TokenType>) -> Self {
+        Self {
+            creation_funcs: vec![],
+            eof_token,
+            is_lossy,
+            skip_bom: false,
+            shebang: None,
+            skip_if: None,
+            keep_skipped: false,
+            sink: None,
+            store_source: false,
+            tab_width: None,
+            progress: None,
+            cancel_flag: None,
+            limits: LexLimits::new(),
+        }
+    }
+
+    /// Add a tokenizer, created once for each lexer by calling `f`, and return self.
+    pub fn tokenizer<F, T>(mut self, f: F) -> Self
+    where
+        F: Fn() -> T + 'a,
+        T: Tokenizer<TokenType, Context> + 'a,
+    {
+        self.add_tokenizer(f);
+        self
+    }
+
+    /// Add a tokenizer, created once for each lexer by calling `f`.
+    pub fn add_tokenizer<F, T>(&mut self, f: F)
+    where
+        F: Fn() -> T + 'a,
+        T: Tokenizer<TokenType, Context> + 'a,
+    {
+        self.creation_funcs.push(Box::new(move || Box::new(f())));
+    }
+
+    /// Skip a byte order mark at the start of each input and return self. See [Lexer::skip_bom].
+    pub fn skip_bom(mut self) -> Self {
+        self.skip_bom = true;
+        self
+    }
+
+    /// Recognize a shebang line at the start of each input and return self. See [Lexer::shebang].
+    pub fn shebang<F: Fn(&str) -> Option<TokenType> + 'a>(mut self, shebang: F) -> Self {
+        self.shebang = Some(Rc::new(shebang));
+        self
+    }
+
+    /// Decide which tokens are skipped with `skip_if` and return self. See [Lexer::skip_if].
+    pub fn skip_if<F: Fn(&TokenType) -> bool + 'a>(mut self, skip_if: F) -> Self {
+        self.skip_if = Some(Rc::new(skip_if));
+        self
+    }
+
+    /// Set whether skipped tokens are kept and return self. See [Lexer::keep_skipped].
+    pub fn keep_skipped(mut self, keep_skipped: bool) -> Self {
+        self.keep_skipped = keep_skipped;
+        self
+    }
+
+    /// Stream the tokens of each lexer into a clone of `sink` and return self. See [Lexer::sink].
+    pub fn sink<S: TokenSink<TokenType> + Clone + 'a>(mut self, sink: S, history: usize) -> Self {
+        self.sink = Some((Rc::new(move || Box::new(sink.clone())), history));
+        self
+    }
+
+    /// Set whether each lexer stores the text it consumes and return self.
+    /// See [Lexer::store_source].
+    pub fn store_source(mut self, store_source: bool) -> Self {
+        self.store_source = store_source;
+        self
+    }
+
+    /// Set the amount of columns between tab stops and return self. See [Lexer::tab_width].
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.tab_width = Some(tab_width);
+        self
+    }
+
+    /// Call `callback` with the progress of each lexer and return self. See [Lexer::on_progress].
+    pub fn on_progress<F: Fn(Progress) + 'a>(mut self, interval_bytes: usize, callback: F) -> Self {
+        self.progress = Some((interval_bytes, Rc::new(callback)));
+        self
+    }
+
+    /// Set the flag that each lexer checks before lexing each token and return self.
+    /// See [Lexer::cancel_flag].
+    pub fn cancel_flag(mut self, cancel_flag: CancelFlag) -> Self {
+        self.cancel_flag = Some(cancel_flag);
+        self
+    }
+
+    /// Set the limits of each created lexer and return self. See [Lexer::limits].
+    pub fn limits(mut self, limits: LexLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Create a lexer for `reader` whose tokenizers share `context`.
+    pub fn lexer_with_context<Reader: Read + 'a>(
+        &self,
+        reader: Reader,
+        context: Context,
+    ) -> Lexer<'a, TokenType, Context> {
+        let mut lexer = Lexer::with_context(reader, self.is_lossy, self.eof_token.clone(), context)
+            .limits(self.limits)
+            .keep_skipped(self.keep_skipped)
+            .store_source(self.store_source);
+        if self.skip_bom {
+            lexer = lexer.skip_bom();
+        }
+        if let Some(shebang) = &self.shebang {
+            let shebang = Rc::clone(shebang);
+            lexer = lexer.shebang(move |line| shebang(line));
+        }
+        if let Some(skip_if) = &self.skip_if {
+            let skip_if = Rc::clone(skip_if);
+            lexer = lexer.skip_if(move |token| skip_if(token));
+        }
+        if let Some((sink, history)) = &self.sink {
+            let mut sink = sink();
+            lexer = lexer.sink(move |token| sink.push(token), *history);
+        }
+        if let Some(tab_width) = self.tab_width {
+            lexer = lexer.tab_width(tab_width);
+        }
+        if let Some((interval_bytes, callback)) = &self.progress {
+            let callback = Rc::clone(callback);
+            lexer = lexer.on_progress(*interval_bytes, move |progress| callback(progress));
+        }
+        if let Some(cancel_flag) = &self.cancel_flag {
+            lexer = lexer.cancel_flag(cancel_flag.clone());
+        }
+        for creation_func in &self.creation_funcs {
+            lexer.reuse_tokenizer(creation_func());
+        }
+        lexer
+    }
+
+    /// Create a lexer for `reader` with a default context.
+    pub fn lexer<Reader: Read + 'a>(&self, reader: Reader) -> Lexer<'a, TokenType, Context>
+    where
+        Context: Default,
+    {
+        self.lexer_with_context(reader, Context::default())
+    }
+}
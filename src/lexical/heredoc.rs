@@ -0,0 +1,300 @@
+use std::rc::Rc;
+
+use super::{
+    error::LexError, Clusters, GraphemeLocation, Graphemes, LexContext, SmallString, Token,
+    TokenValue, Tokenizer,
+};
+
+enum Style {
+    /// `<<TAG`, followed by lines up to a line consisting of `TAG`.
+    Heredoc { opener: Vec<SmallString> },
+    /// A prefix, any amount of `#` and a `"`, followed by text up to a `"` and the same amount of `#`.
+    Raw { prefix: SmallString },
+}
+
+type MapFn<TokenType> = Box<dyn Fn(&str, String) -> TokenType>;
+
+struct Heredoc<TokenType> {
+    style: Style,
+    strip_indentation: bool,
+    map: MapFn<TokenType>,
+}
+
+/// A tokenizer for text that runs until a terminator chosen where the text starts, such as shell
+/// style heredocs or Rust style raw strings, mapping the text into a token with a closure.
+///
+/// The closure is given the tag of the token, which is the heredoc's tag or the `#`s of a raw string,
+/// along with the text between the opener and the terminator.
+///
+/// Like [LiteralSetTokenizer](super::LiteralSetTokenizer), the configuration is shared between
+/// clones, so a lexer can create a tokenizer by cloning one.
+pub struct HeredocTokenizer<TokenType> {
+    heredoc: Rc<Heredoc<TokenType>>,
+}
+
+impl<TokenType> Clone for HeredocTokenizer<TokenType> {
+    fn clone(&self) -> Self {
+        Self {
+            heredoc: self.heredoc.clone(),
+        }
+    }
+}
+
+impl<TokenType> HeredocTokenizer<TokenType> {
+    fn with_style<F>(style: Style, map: F) -> Self
+    where
+        F: Fn(&str, String) -> TokenType + 'static,
+    {
+        Self {
+            heredoc: Rc::new(Heredoc {
+                style,
+                strip_indentation: false,
+                map: Box::new(map),
+            }),
+        }
+    }
+
+    /// Create a tokenizer for heredocs that start with `opener`, such as `<<`, followed by a tag made
+    /// of letters, digits and underscores. The text starts on the next line, and ends before
+    /// the first line that consists of the tag alone.
+    ///
+    /// The first two graphemes of `opener` decide whether a heredoc starts, so they should not
+    /// start any other token.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `opener` is empty.
+    pub fn heredoc<F>(opener: &str, map: F) -> Self
+    where
+        F: Fn(&str, String) -> TokenType + 'static,
+    {
+        let opener: Vec<_> = Clusters::new(opener.chars().map(Ok)).flatten().collect();
+        assert!(!opener.is_empty(), "A heredoc opener must not be empty");
+        Self::with_style(Style::Heredoc { opener }, map)
+    }
+
+    /// Create a tokenizer for raw strings such as `r"text"` or `r##"text with "#"##`, which start with
+    /// `prefix`, any amount of `#` and a `"`, and end at the next `"` followed by the same amount of `#`.
+    pub fn raw_string<F>(prefix: char, map: F) -> Self
+    where
+        F: Fn(&str, String) -> TokenType + 'static,
+    {
+        let mut prefix_grapheme = SmallString::new();
+        prefix_grapheme.push(prefix);
+        Self::with_style(
+            Style::Raw {
+                prefix: prefix_grapheme,
+            },
+            map,
+        )
+    }
+
+    /// Remove the indentation shared by every line that is not blank from the text, and return self.
+    /// This also allows the terminating line of a heredoc to be indented.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tokenizer has been cloned.
+    pub fn strip_indentation(mut self) -> Self {
+        Rc::get_mut(&mut self.heredoc)
+            .expect("HeredocTokenizer must not be modified after being cloned")
+            .strip_indentation = true;
+        self
+    }
+}
+
+/// Removes the indentation shared by every line of `text` that is not blank.
+fn strip_indentation(text: &str) -> String {
+    let indentation = |line: &str| line.len() - line.trim_start_matches([' ', '\t']).len();
+    let shared = text
+        .split('\n')
+        .filter(|line| !line.trim().is_empty())
+        .map(indentation)
+        .min()
+        .unwrap_or(0);
+
+    text.split('\n')
+        .map(|line| {
+            line.get(shared.min(indentation(line))..)
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn is_newline(grapheme: &str) -> bool {
+    matches!(grapheme, "\n" | "\r\n")
+}
+
+fn next_grapheme<'a>(incoming: &mut Graphemes<'a>) -> Result<Option<SmallString>, LexError> {
+    match incoming.next() {
+        Some(Ok(grapheme)) => Ok(Some(grapheme.into_text())),
+        Some(Err((index, error))) => Err(LexError::other_indexed(index, error)),
+        None => Ok(None),
+    }
+}
+
+impl<TokenType> Heredoc<TokenType> {
+    fn lex_heredoc<'a>(
+        &self,
+        opener: &[SmallString],
+        location: &GraphemeLocation,
+        incoming: &mut Graphemes<'a>,
+    ) -> Result<TokenType, LexError> {
+        let rest: String = opener[1..].iter().map(SmallString::as_str).collect();
+        if !rest.is_empty() && !incoming.eat(&rest) {
+            return Err(LexError::other(format!(
+                "Expected the heredoc opener {:?}",
+                opener.concat()
+            )));
+        }
+
+        let mut tag = String::new();
+        while let Some(next) = incoming.try_peek()? {
+            if !next
+                .chars()
+                .all(|character| character.is_alphanumeric() || character == '_')
+            {
+                break;
+            }
+            tag.push_str(&next);
+            incoming.next();
+        }
+        incoming.reset_peek();
+        if tag.is_empty() {
+            return Err(LexError::other("Expected a heredoc tag"));
+        }
+
+        let unterminated = || LexError::unterminated("heredoc", location.index..=location.index);
+        loop {
+            match next_grapheme(incoming)? {
+                Some(grapheme) if is_newline(&grapheme) => break,
+                Some(grapheme) if grapheme == " " || grapheme == "\t" => {}
+                Some(grapheme) => {
+                    return Err(LexError::other(format!(
+                        "Unexpected {:?} after the heredoc tag",
+                        grapheme.as_str()
+                    )))
+                }
+                None => return Err(unterminated()),
+            }
+        }
+
+        // Each line is peeked before being consumed, so that the line break after the terminator
+        // is left for other tokenizers.
+        let mut text = String::new();
+        loop {
+            let mut line = String::new();
+            let mut length = 0;
+            while let Some(next) = incoming.try_peek()? {
+                if is_newline(&next) {
+                    break;
+                }
+                line.push_str(&next);
+                length += 1;
+            }
+            incoming.reset_peek();
+
+            let terminator = match self.strip_indentation {
+                true => line.trim_start_matches([' ', '\t']),
+                false => &line,
+            };
+            for _ in 0..length {
+                next_grapheme(incoming)?;
+            }
+            if terminator == tag {
+                break;
+            }
+
+            match next_grapheme(incoming)? {
+                Some(newline) => {
+                    text.push_str(&line);
+                    text.push_str(&newline);
+                }
+                None => return Err(unterminated()),
+            }
+        }
+
+        for newline in ["\r\n", "\n"] {
+            if let Some(stripped) = text.strip_suffix(newline) {
+                text.truncate(stripped.len());
+                break;
+            }
+        }
+        if self.strip_indentation {
+            text = strip_indentation(&text);
+        }
+
+        Ok((self.map)(&tag, text))
+    }
+
+    fn lex_raw<'a>(
+        &self,
+        location: &GraphemeLocation,
+        incoming: &mut Graphemes<'a>,
+    ) -> Result<TokenType, LexError> {
+        let mut hashes = String::new();
+        while incoming.eat("#") {
+            hashes.push('#');
+        }
+        if !incoming.eat("\"") {
+            return Err(LexError::other(format!(
+                "Expected a '\"' after {:?}",
+                hashes
+            )));
+        }
+
+        let mut text = match incoming.raw_read_until(&format!("\"{}", hashes)) {
+            Err(LexError::UnexpectedEndOfStream) => {
+                return Err(LexError::unterminated(
+                    "raw string",
+                    location.index..=location.index,
+                ))
+            }
+            result => result?,
+        };
+        if self.strip_indentation {
+            text = strip_indentation(&text);
+        }
+
+        Ok((self.map)(&hashes, text))
+    }
+}
+
+impl<TokenType: TokenValue, Context> Tokenizer<TokenType, Context> for HeredocTokenizer<TokenType> {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<TokenType>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        next: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        match &self.heredoc.style {
+            Style::Heredoc { opener } => {
+                opener[0] == grapheme
+                    && opener
+                        .get(1)
+                        .is_none_or(|second| next.as_ref() == Some(second))
+            }
+            Style::Raw { prefix } => {
+                prefix == grapheme && matches!(next.as_deref(), Some("#" | "\""))
+            }
+        }
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<TokenType>>,
+        _: &str,
+        location: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        let heredoc = &*self.heredoc;
+        match &heredoc.style {
+            Style::Heredoc { opener } => heredoc.lex_heredoc(opener, location, incoming),
+            Style::Raw { .. } => heredoc.lex_raw(location, incoming),
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use std::fmt::Display;
+
+use super::Span;
+
+/// Renders a window of raw bytes around a [Span] of byte indexes as a hex dump, with the bytes
+/// within the span marked underneath, such as for errors reported by a [ByteLexer](super::ByteLexer).
+///
+/// Each row shows the offset of its first byte, the bytes in hex, and the bytes as ASCII,
+/// with non-printable bytes shown as `.`:
+///
+/// ```text
+/// 00000000  89 50 4e 47 0d 0a 1a 0a  |.PNG....|
+///                    ^^ ^^               ^^
+/// ```
+pub struct HexDump<'d> {
+    bytes: &'d [u8],
+    span: Span,
+    row_width: usize,
+    context_rows: usize,
+}
+
+impl<'d> HexDump<'d> {
+    /// Creates a hex dump of `bytes` around `span`, with rows of 16 bytes and one row of context
+    /// before and after the span.
+    pub fn new(bytes: &'d [u8], span: Span) -> Self {
+        Self {
+            bytes,
+            span,
+            row_width: 16,
+            context_rows: 1,
+        }
+    }
+
+    /// Sets the amount of bytes shown per row and returns self. A width of zero is treated as one.
+    pub fn row_width(mut self, row_width: usize) -> Self {
+        self.row_width = row_width.max(1);
+        self
+    }
+
+    /// Sets the amount of rows shown before and after the rows the span lies within, and returns self.
+    pub fn context_rows(mut self, context_rows: usize) -> Self {
+        self.context_rows = context_rows;
+        self
+    }
+
+    fn write_row(&self, f: &mut std::fmt::Formatter<'_>, row: usize) -> std::fmt::Result {
+        let start = row * self.row_width;
+        let end = (start + self.row_width).min(self.bytes.len());
+        let row_bytes = &self.bytes[start..end];
+        let padding = "   ".repeat(self.row_width - row_bytes.len());
+
+        write!(f, "{:08x}  ", start)?;
+        for byte in row_bytes {
+            write!(f, "{:02x} ", byte)?;
+        }
+        write!(f, "{} |", padding)?;
+        for byte in row_bytes {
+            let shown = if byte.is_ascii_graphic() || *byte == b' ' {
+                *byte as char
+            } else {
+                '.'
+            };
+            write!(f, "{}", shown)?;
+        }
+        write!(f, "|")?;
+
+        if !(start..end).any(|index| self.span.contains(index)) {
+            return Ok(());
+        }
+
+        let mut markers = format!("\n{:10}", "");
+        for index in start..end {
+            markers += if self.span.contains(index) {
+                "^^ "
+            } else {
+                "   "
+            };
+        }
+        markers += &padding;
+        markers += "  ";
+        for index in start..end {
+            markers.push(if self.span.contains(index) { '^' } else { ' ' });
+        }
+        write!(f, "{}", markers.trim_end())
+    }
+}
+
+impl Display for HexDump<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.bytes.is_empty() {
+            return Ok(());
+        }
+
+        let last_row = (self.bytes.len() - 1) / self.row_width;
+        let first = (self.span.start() / self.row_width).saturating_sub(self.context_rows);
+        let last = (self.span.end() / self.row_width + self.context_rows).min(last_row);
+
+        for row in first..=last {
+            if row != first {
+                writeln!(f)?;
+            }
+            self.write_row(f, row)?;
+        }
+        Ok(())
+    }
+}
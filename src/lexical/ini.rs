@@ -0,0 +1,403 @@
+use std::io::Read;
+
+use super::{
+    error::LexError,
+    escape::{decode_escapes, EscapePolicy},
+    GraphemeLocation, Graphemes, LexContext, Lexer, SmallString, Token, TokenValue, Tokenizer,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The tokens of an INI-like configuration file.
+pub enum IniToken {
+    /// A section header such as `[server]`, holding the trimmed section name.
+    Section(String),
+    /// The key of an entry, such as `port` in `port = 8080`.
+    Key(String),
+    /// The `=` or `:` between a key and its value.
+    Assign,
+    /// An unquoted value, with surrounding whitespace removed.
+    Value(String),
+    /// A quoted value, with escape sequences decoded if it was double quoted.
+    String(String),
+    /// A comment starting with `;` or `#`, holding the text after it.
+    Comment(String),
+    /// The end of a line.
+    Newline,
+    /// Spaces and tabs, which are skipped.
+    Whitespace,
+}
+
+impl TokenValue for IniToken {
+    fn should_skip(&self) -> bool {
+        matches!(self, IniToken::Whitespace)
+    }
+}
+
+/// A token type that the INI tokenizers can produce, so that they can be combined with other
+/// tokenizers in a lexer of a larger token type.
+///
+/// The tokenizers decide whether they apply by looking at the last token, through [IniTokenType::as_ini].
+pub trait IniTokenType: TokenValue + From<IniToken> {
+    /// Returns the token as an [IniToken], if it is one.
+    fn as_ini(&self) -> Option<&IniToken>;
+}
+
+impl IniTokenType for IniToken {
+    fn as_ini(&self) -> Option<&IniToken> {
+        Some(self)
+    }
+}
+
+fn last_ini<'l, TokenType: IniTokenType>(
+    lex_context: &LexContext<'l, TokenType>,
+) -> Option<Option<&'l IniToken>> {
+    lex_context.last().map(|token| token.token().as_ini())
+}
+
+/// Returns whether the next token starts a line.
+fn at_line_start<TokenType: IniTokenType>(lex_context: &LexContext<TokenType>) -> bool {
+    matches!(last_ini(lex_context), None | Some(Some(IniToken::Newline)))
+}
+
+/// Returns whether the next token is the value of an entry.
+fn at_value<TokenType: IniTokenType>(lex_context: &LexContext<TokenType>) -> bool {
+    matches!(last_ini(lex_context), Some(Some(IniToken::Assign)))
+}
+
+fn is_newline(grapheme: &str) -> bool {
+    matches!(grapheme, "\n" | "\r\n" | "\r")
+}
+
+fn is_blank(grapheme: &str) -> bool {
+    matches!(grapheme, " " | "\t")
+}
+
+fn is_comment_start(grapheme: &str) -> bool {
+    matches!(grapheme, ";" | "#")
+}
+
+/// Consumes graphemes while `predicate` holds for the next one, returning them.
+fn read_while<F: Fn(&str) -> bool>(incoming: &mut Graphemes, predicate: F) -> String {
+    let mut read = String::new();
+    while let Some(Ok(next)) = incoming.peek() {
+        if !predicate(&next) {
+            break;
+        }
+        read.push_str(&next);
+        incoming.next();
+    }
+    incoming.reset_peek();
+    read
+}
+
+/// Lexes a section header, such as `[server]`, at the start of a line.
+pub struct IniSectionTokenizer;
+
+impl<TokenType: IniTokenType, Context> Tokenizer<TokenType, Context> for IniSectionTokenizer {
+    fn can_tokenize(
+        &mut self,
+        lex_context: &LexContext<TokenType>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        grapheme == "[" && at_line_start(lex_context)
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<TokenType>>,
+        _: &str,
+        location: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        let name = read_while(incoming, |next| next != "]" && !is_newline(next));
+        if !incoming.eat("]") {
+            return Err(LexError::unterminated(
+                "section header",
+                location.index..=location.index,
+            ));
+        }
+
+        Ok(IniToken::Section(name.trim().to_string()).into())
+    }
+}
+
+/// Lexes the key of an entry at the start of a line.
+pub struct IniKeyTokenizer;
+
+fn is_key_end(grapheme: &str) -> bool {
+    matches!(grapheme, "=" | ":") || is_blank(grapheme) || is_newline(grapheme)
+}
+
+impl<TokenType: IniTokenType, Context> Tokenizer<TokenType, Context> for IniKeyTokenizer {
+    fn can_tokenize(
+        &mut self,
+        lex_context: &LexContext<TokenType>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        at_line_start(lex_context)
+            && !is_key_end(grapheme)
+            && !is_comment_start(grapheme)
+            && !matches!(grapheme, "[" | "\"" | "'")
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<TokenType>>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        let key = grapheme.to_string() + &read_while(incoming, |next| !is_key_end(next));
+        Ok(IniToken::Key(key).into())
+    }
+}
+
+/// Lexes the `=` or `:` after a key.
+pub struct IniAssignTokenizer;
+
+impl<TokenType: IniTokenType, Context> Tokenizer<TokenType, Context> for IniAssignTokenizer {
+    fn can_tokenize(
+        &mut self,
+        lex_context: &LexContext<TokenType>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        matches!(grapheme, "=" | ":")
+            && matches!(last_ini(lex_context), Some(Some(IniToken::Key(_))))
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<TokenType>>,
+        _: &str,
+        _: &GraphemeLocation,
+        _: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        Ok(IniToken::Assign.into())
+    }
+}
+
+/// Lexes an unquoted value, which runs until the end of the line or a comment preceded by whitespace.
+pub struct IniValueTokenizer;
+
+impl<TokenType: IniTokenType, Context> Tokenizer<TokenType, Context> for IniValueTokenizer {
+    fn can_tokenize(
+        &mut self,
+        lex_context: &LexContext<TokenType>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        at_value(lex_context)
+            && !is_blank(grapheme)
+            && !is_newline(grapheme)
+            && !is_comment_start(grapheme)
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<TokenType>>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        let mut value = grapheme.to_string();
+        loop {
+            value += &read_while(incoming, |next| !is_blank(next) && !is_newline(next));
+
+            // Whitespace is only part of the value if the value continues after it.
+            let mut blank = 0;
+            let mut continues = false;
+            while let Some(next) = incoming.try_peek()? {
+                if is_blank(&next) {
+                    blank += 1;
+                    continue;
+                }
+                continues = !is_newline(&next) && !is_comment_start(&next);
+                break;
+            }
+            incoming.reset_peek();
+            if !continues {
+                return Ok(IniToken::Value(value).into());
+            }
+
+            for _ in 0..blank {
+                if let Some(Ok(next)) = incoming.next() {
+                    value.push_str(&next);
+                }
+            }
+        }
+    }
+}
+
+/// Lexes a quoted value. Double quoted values may contain the escape sequences `\"`, `\\`, `\n`
+/// and `\t`, while single quoted values are taken literally.
+pub struct IniStringTokenizer;
+
+impl<TokenType: IniTokenType, Context> Tokenizer<TokenType, Context> for IniStringTokenizer {
+    fn can_tokenize(
+        &mut self,
+        lex_context: &LexContext<TokenType>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        matches!(grapheme, "\"" | "'") && at_value(lex_context)
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<TokenType>>,
+        grapheme: &str,
+        location: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        let mut raw = String::new();
+        // Strings end at the closing quote, and are unterminated if a line break or the end of the
+        // input comes first.
+        let next_in_line = |incoming: &mut Graphemes<'a>| match incoming.next() {
+            Some(Ok(next)) if !is_newline(&next) => Ok(next),
+            Some(Err((index, error))) => Err(LexError::other_indexed(index, error)),
+            _ => Err(LexError::unterminated(
+                "string",
+                location.index..=location.index,
+            )),
+        };
+        loop {
+            let next = next_in_line(incoming)?;
+            if next == grapheme {
+                break;
+            }
+            raw.push_str(&next);
+            if next == "\\" && grapheme == "\"" {
+                raw.push_str(&next_in_line(incoming)?);
+            }
+        }
+
+        if grapheme == "'" {
+            return Ok(IniToken::String(raw).into());
+        }
+
+        let policy = EscapePolicy::empty('\\')
+            .map('"', '"')
+            .map('\\', '\\')
+            .map('n', '\n')
+            .map('t', '\t');
+        match decode_escapes(&raw, &policy) {
+            Ok(string) => Ok(IniToken::String(string).into()),
+            Err(error) => {
+                // Strings cannot contain line breaks, so the escape lies on the same line as the opening quote.
+                let start = error.grapheme_span(&raw).start + 1;
+                Err(LexError::at(
+                    GraphemeLocation::new(
+                        location.index + start,
+                        location.line,
+                        location.offset + start,
+                    ),
+                    LexError::other(error),
+                ))
+            }
+        }
+    }
+}
+
+/// Lexes a comment starting with `;` or `#`, up to the end of the line.
+pub struct IniCommentTokenizer;
+
+impl<TokenType: IniTokenType, Context> Tokenizer<TokenType, Context> for IniCommentTokenizer {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<TokenType>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        is_comment_start(grapheme)
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<TokenType>>,
+        _: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        let comment = read_while(incoming, |next| !is_newline(next));
+        Ok(IniToken::Comment(comment.trim().to_string()).into())
+    }
+}
+
+/// Lexes line breaks, and the spaces and tabs between tokens.
+pub struct IniLayoutTokenizer;
+
+impl<TokenType: IniTokenType, Context> Tokenizer<TokenType, Context> for IniLayoutTokenizer {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<TokenType>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        is_newline(grapheme) || is_blank(grapheme)
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<TokenType>>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        if is_newline(grapheme) {
+            return Ok(IniToken::Newline.into());
+        }
+
+        read_while(incoming, is_blank);
+        Ok(IniToken::Whitespace.into())
+    }
+}
+
+/// The complete set of tokenizers for INI-like configuration files.
+///
+/// The tokenizers can also be added to a lexer individually, in order to replace or extend some of them.
+pub struct IniTokenizers;
+
+impl IniTokenizers {
+    /// Adds every INI tokenizer to `lexer` and returns it.
+    pub fn register<'a, TokenType: IniTokenType, Context>(
+        lexer: Lexer<'a, TokenType, Context>,
+    ) -> Lexer<'a, TokenType, Context> {
+        lexer
+            .tokenizer(|| IniLayoutTokenizer)
+            .tokenizer(|| IniCommentTokenizer)
+            .tokenizer(|| IniSectionTokenizer)
+            .tokenizer(|| IniKeyTokenizer)
+            .tokenizer(|| IniAssignTokenizer)
+            .tokenizer(|| IniStringTokenizer)
+            .tokenizer(|| IniValueTokenizer)
+    }
+
+    /// Creates a lexer for an INI-like configuration file read from `reader`.
+    pub fn lexer<'a, Reader: Read + 'a>(reader: Reader) -> Lexer<'a, IniToken> {
+        Self::register(Lexer::new(reader, false, None))
+    }
+}
@@ -0,0 +1,69 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A cheap, copyable handle to a string stored in an [Interner].
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// Returns the index of the symbol within its interner.
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+#[derive(Debug, Default)]
+struct InternerStorage {
+    symbols: HashMap<Rc<str>, Symbol>,
+    strings: Vec<Rc<str>>,
+}
+
+#[derive(Debug, Default, Clone)]
+/// Deduplicates repeated token text, such as identifiers, into [Symbol]s.
+///
+/// Cloning an [Interner] is cheap, and the clone shares its storage with the original.
+/// This allows a tokenizer to hold onto the lexer's interner, which can be retrieved via
+/// [Lexer::interner](super::Lexer::interner).
+pub struct Interner {
+    storage: Rc<RefCell<InternerStorage>>,
+}
+
+impl Interner {
+    /// Create an empty interner.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the [Symbol] for `text`, storing it if it has not been seen before.
+    pub fn intern(&self, text: &str) -> Symbol {
+        let mut storage = self.storage.borrow_mut();
+        if let Some(symbol) = storage.symbols.get(text) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(storage.strings.len() as u32);
+        let text: Rc<str> = Rc::from(text);
+        storage.strings.push(text.clone());
+        storage.symbols.insert(text, symbol);
+        symbol
+    }
+
+    /// Returns the [Symbol] for `text` without storing it.
+    pub fn get(&self, text: &str) -> Option<Symbol> {
+        self.storage.borrow().symbols.get(text).copied()
+    }
+
+    /// Returns the text that `symbol` represents.
+    pub fn resolve(&self, symbol: Symbol) -> Option<Rc<str>> {
+        self.storage.borrow().strings.get(symbol.index()).cloned()
+    }
+
+    /// Returns the amount of unique strings that have been interned.
+    pub fn len(&self) -> usize {
+        self.storage.borrow().strings.len()
+    }
+
+    /// Returns whether nothing has been interned.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
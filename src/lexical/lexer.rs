@@ -1,25 +1,217 @@
-use std::io::Read;
+use std::{io::Read, ops::RangeInclusive, rc::Rc, time::Instant};
 
-use super::{error::LexError, stream::Graphemes, Token, TokenValue, Tokenizer};
+use super::{
+    error::LexError,
+    stream::{Grapheme, GraphemeLocation, Graphemes},
+    CancelFlag, Interner, LexContext, LexStats, SourceBuffer, SourceId, Symbol, Token, TokenSink,
+    TokenValue, Tokenizer,
+};
 
 /// Represents a function that creates an empty token. This assumes that each token is represented by a single type,
 /// such as an enum, however for each enumeration that will be used in the lexer, there is a corresponding `TokenizerFn`.
-pub trait TokenizerFn<'a, TokenType: TokenValue>:
-    Fn() -> Box<dyn Tokenizer<TokenType> + 'a> + 'a
+pub trait TokenizerFn<'a, TokenType: TokenValue, Context = ()>:
+    Fn() -> Box<dyn Tokenizer<TokenType, Context> + 'a> + 'a
 {
 }
 
-impl<'a, TokenType: TokenValue, T: Fn() -> Box<dyn Tokenizer<TokenType> + 'a> + 'a>
-    TokenizerFn<'a, TokenType> for T
+impl<'a, TokenType, Context, T> TokenizerFn<'a, TokenType, Context> for T
+where
+    TokenType: TokenValue,
+    T: Fn() -> Box<dyn Tokenizer<TokenType, Context> + 'a> + 'a,
 {
 }
 
+/// A tokenizer of a [Lexer].
+enum Registered<'a, TokenType: TokenValue, Context> {
+    /// A tokenizer that is created anew by its function whenever it is consulted, along with the
+    /// one created last.
+    Created(
+        Box<dyn TokenizerFn<'a, TokenType, Context>>,
+        Option<Box<dyn Tokenizer<TokenType, Context> + 'a>>,
+    ),
+    /// A tokenizer that is created once and [reset](Tokenizer::reset) whenever it is consulted.
+    Reused(Box<dyn Tokenizer<TokenType, Context> + 'a>),
+}
+
+impl<'a, TokenType: TokenValue, Context> Registered<'a, TokenType, Context> {
+    /// Returns the tokenizer, ready to be consulted about a new token.
+    fn prepare(&mut self) -> &mut (dyn Tokenizer<TokenType, Context> + 'a) {
+        match self {
+            Registered::Created(create, tokenizer) => tokenizer.insert(create()).as_mut(),
+            Registered::Reused(tokenizer) => {
+                tokenizer.reset();
+                tokenizer.as_mut()
+            }
+        }
+    }
+}
+
+/// Returns the index that a token ending at `end` starts at, which is `start` unless its tokenizer
+/// pushed tokens after the first `token_count` of `tokens`, in which case it follows the last of them.
+///
+/// A tokenizer may also remove tokens, leaving fewer than `token_count`.
+pub(crate) fn token_start<TokenType: TokenValue>(
+    tokens: &[Token<TokenType>],
+    token_count: usize,
+    start: usize,
+    end: usize,
+) -> usize {
+    match tokens
+        .get(token_count..)
+        .and_then(<[_]>::last)
+        .and_then(Token::grapheme_span)
+    {
+        Some(pushed) if pushed.end <= end => pushed.end,
+        _ => start,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Describes a problem with the ranges of the lexed tokens, as found by [Lexer::validate_spans].
+pub enum SpanViolation {
+    /// The range of the token at `index` ends before it starts.
+    Reversed { index: usize },
+    /// The range of the token at `index` starts at or before the end of the previous token's range.
+    Overlapping { index: usize, previous_end: usize },
+    /// No token covers the graphemes within `range`.
+    Gap { range: RangeInclusive<usize> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A snapshot of how far a [Lexer] has got through its input, as passed to the callback
+/// registered with [Lexer::on_progress].
+pub struct Progress {
+    /// The amount of input bytes consumed, including those of included streams. See
+    /// [Graphemes::input_bytes].
+    pub bytes: usize,
+    /// The amount of tokens stored so far.
+    pub tokens: usize,
+    /// The line of the stream currently being lexed, starting at zero.
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// Identifies one of the limits of [LexLimits].
+pub enum Limit {
+    /// The amount of stored tokens.
+    Tokens,
+    /// The amount of bytes consumed.
+    Bytes,
+    /// The amount of bytes a single token was lexed from.
+    TokenLength,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Limits that stop a [Lexer] with [LexError::LimitExceeded] once exceeded, so that lexing untrusted
+/// input cannot exhaust memory. Every limit is disabled by default.
+///
+/// Limits are checked after each token is lexed, so a single tokenizer may read past
+/// the byte limit before it is noticed.
+pub struct LexLimits {
+    max_tokens: Option<usize>,
+    max_bytes: Option<usize>,
+    max_token_length: Option<usize>,
+}
+
+impl LexLimits {
+    /// Create limits with every limit disabled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum amount of stored tokens, not including the EOF token, and return self.
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// Set the maximum amount of bytes consumed, including those of included streams, and return self.
+    ///
+    /// Bytes are counted as they were in the input, so an invalid UTF-8 byte that is replaced with
+    /// a U+FFFD counts as one byte. See [Graphemes::input_bytes].
+    pub fn max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Set the maximum amount of bytes that a single token, including skipped tokens,
+    /// can be lexed from and return self.
+    pub fn max_token_length(mut self, max_token_length: usize) -> Self {
+        self.max_token_length = Some(max_token_length);
+        self
+    }
+
+    /// Returns the first limit that the values exceed, along with its maximum.
+    fn check(&self, tokens: usize, bytes: usize, token_length: usize) -> Option<(Limit, usize)> {
+        [
+            (Limit::Tokens, self.max_tokens, tokens),
+            (Limit::Bytes, self.max_bytes, bytes),
+            (Limit::TokenLength, self.max_token_length, token_length),
+        ]
+        .into_iter()
+        .find_map(|(limit, max, value)| match max {
+            Some(max) if value > max => Some((limit, max)),
+            _ => None,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Describes how a single token was lexed, as produced by [Lexer::explain].
+pub struct TokenExplanation {
+    /// The location of the grapheme the token started at.
+    pub location: GraphemeLocation,
+    /// The names of the tokenizers that were asked whether they could lex the token, in order.
+    pub consulted: Vec<String>,
+    /// The name of the tokenizer that lexed the token, if any tokenizer accepted it.
+    pub matched: Option<String>,
+    /// The graphemes that were consumed for the token.
+    pub range: RangeInclusive<usize>,
+    /// Whether the token was skipped.
+    pub skipped: bool,
+    /// Whether lexing the token failed.
+    pub failed: bool,
+}
+
+/// The name that the shebang line is attributed to in [LexStats] and [TokenExplanation]s.
+const SHEBANG: &str = "Shebang";
+
+type ShebangFn<'a, TokenType> = Box<dyn Fn(&str) -> Option<TokenType> + 'a>;
+type SkipFn<'a, TokenType> = Box<dyn Fn(&TokenType) -> bool + 'a>;
+
+struct ProgressReporter<'a> {
+    interval: usize,
+    next_report: usize,
+    callback: Box<dyn FnMut(Progress) + 'a>,
+}
+
 /// Accepts graphemes from an input reader, and lexes them into tokens.
-pub struct Lexer<'a, TokenType: TokenValue> {
+///
+/// `Context` is a user supplied value that is passed to every tokenizer, allowing them to share
+/// state such as nesting depth or a symbol table.
+pub struct Lexer<'a, TokenType: TokenValue, Context = ()> {
     tokens: Vec<Token<TokenType>>,
-    creation_funcs: Vec<Box<dyn TokenizerFn<'a, TokenType>>>,
+    tokenizers: Vec<Registered<'a, TokenType, Context>>,
     eof_token: Option<TokenType>,
     incoming: Graphemes<'a>,
+    interner: Interner,
+    context: Context,
+    source: Option<SourceId>,
+    suspended: Vec<(Graphemes<'a>, Option<SourceId>)>,
+    progress: Option<ProgressReporter<'a>>,
+    cancel_flag: Option<CancelFlag>,
+    stats: LexStats<TokenType>,
+    sink: Option<(Box<dyn TokenSink<TokenType> + 'a>, usize)>,
+    limits: LexLimits,
+    explanations: Option<Vec<TokenExplanation>>,
+    skip_bom: bool,
+    shebang: Option<ShebangFn<'a, TokenType>>,
+    skip_if: Option<SkipFn<'a, TokenType>>,
+    skipped_tokens: Option<Vec<Token<TokenType>>>,
+    started: bool,
+    finished: bool,
+    #[cfg(feature = "profiling")]
+    profile: super::Profile,
 }
 
 impl<'a, TokenType: TokenValue> Lexer<'a, TokenType> {
@@ -28,12 +220,83 @@ impl<'a, TokenType: TokenValue> Lexer<'a, TokenType> {
         reader: Reader,
         is_lossy: bool,
         eof_token: Option<TokenType>,
+    ) -> Self {
+        Self::with_context(reader, is_lossy, eof_token, ())
+    }
+
+    /// Create a lexer that replaces invalid UTF-8 in `reader` with U+FFFD.
+    ///
+    /// The lexer stores the text it consumes, see [Lexer::store_source]. It pushes the end of file
+    /// token given by [TokenValue::eof], so none is pushed unless `TokenType` overrides it.
+    pub fn lossy<Reader: Read + 'a>(reader: Reader) -> Self {
+        Self::new(reader, true, TokenType::eof()).store_source(true)
+    }
+
+    /// Create a lexer that fails on invalid UTF-8 in `reader`.
+    ///
+    /// The lexer stores the text it consumes, see [Lexer::store_source]. It pushes the end of file
+    /// token given by [TokenValue::eof], so none is pushed unless `TokenType` overrides it.
+    pub fn strict<Reader: Read + 'a>(reader: Reader) -> Self {
+        Self::new(reader, false, TokenType::eof()).store_source(true)
+    }
+
+    /// Create a lexer over `text`.
+    ///
+    /// The lexer stores the text it consumes, see [Lexer::store_source]. It pushes the end of file
+    /// token given by [TokenValue::eof], so none is pushed unless `TokenType` overrides it.
+    pub fn of_string(text: &'a str) -> Self {
+        Self::from_chunks(std::iter::once(text), TokenType::eof()).store_source(true)
+    }
+
+    /// Create a lexer from chunks of already decoded text. See [Graphemes::from_chunks].
+    pub fn from_chunks<I: IntoIterator<Item = &'a str> + 'a>(
+        chunks: I,
+        eof_token: Option<TokenType>,
+    ) -> Self {
+        Self::with_graphemes(Graphemes::from_chunks(chunks), eof_token, ())
+    }
+}
+
+impl<'a, TokenType: TokenValue, Context> Lexer<'a, TokenType, Context> {
+    /// Create a lexer whose tokenizers share `context`.
+    pub fn with_context<Reader: Read + 'a>(
+        reader: Reader,
+        is_lossy: bool,
+        eof_token: Option<TokenType>,
+        context: Context,
+    ) -> Self {
+        Self::with_graphemes(Graphemes::new(reader, is_lossy), eof_token, context)
+    }
+
+    /// Create a lexer over an existing grapheme stream whose tokenizers share `context`.
+    pub fn with_graphemes(
+        incoming: Graphemes<'a>,
+        eof_token: Option<TokenType>,
+        context: Context,
     ) -> Self {
         Self {
             tokens: vec![],
-            creation_funcs: vec![],
-            incoming: Graphemes::new(reader, is_lossy),
+            tokenizers: vec![],
+            incoming,
             eof_token,
+            interner: Interner::new(),
+            context,
+            source: None,
+            suspended: vec![],
+            progress: None,
+            cancel_flag: None,
+            stats: LexStats::new(),
+            sink: None,
+            limits: LexLimits::new(),
+            explanations: None,
+            skip_bom: false,
+            shebang: None,
+            skip_if: None,
+            skipped_tokens: None,
+            started: false,
+            finished: false,
+            #[cfg(feature = "profiling")]
+            profile: super::Profile::default(),
         }
     }
 
@@ -41,7 +304,7 @@ impl<'a, TokenType: TokenValue> Lexer<'a, TokenType> {
     pub fn tokenizer<F, T>(mut self, f: F) -> Self
     where
         F: Fn() -> T + 'a,
-        T: Tokenizer<TokenType> + 'a,
+        T: Tokenizer<TokenType, Context> + 'a,
     {
         self.add_tokenizer(f);
         self
@@ -51,9 +314,162 @@ impl<'a, TokenType: TokenValue> Lexer<'a, TokenType> {
     pub fn add_tokenizer<F, T>(&mut self, f: F)
     where
         F: Fn() -> T + 'a,
-        T: Tokenizer<TokenType> + 'a,
+        T: Tokenizer<TokenType, Context> + 'a,
     {
-        self.creation_funcs.push(Box::new(move || Box::new(f())));
+        self.tokenizers
+            .push(Registered::Created(Box::new(move || Box::new(f())), None));
+    }
+
+    /// Add a tokenizer that is created once, such as by a [LexerFactory](super::LexerFactory),
+    /// and [reset](Tokenizer::reset) before each token rather than created anew.
+    pub(super) fn reuse_tokenizer(
+        &mut self,
+        tokenizer: Box<dyn Tokenizer<TokenType, Context> + 'a>,
+    ) {
+        self.tokenizers.push(Registered::Reused(tokenizer));
+    }
+
+    /// Skip a byte order mark at the start of the input, before any tokenizer runs, and return self.
+    pub fn skip_bom(mut self) -> Self {
+        self.skip_bom = true;
+        self
+    }
+
+    /// Recognize a shebang line, such as `#!/usr/bin/env script`, at the start of the input before
+    /// any tokenizer runs, and return self.
+    ///
+    /// The text of the line, without its line ending, is passed to `shebang`. If it returns a token,
+    /// the token is stored, otherwise the line is skipped. The line ending is left for the tokenizers.
+    pub fn shebang<F: Fn(&str) -> Option<TokenType> + 'a>(mut self, shebang: F) -> Self {
+        self.shebang = Some(Box::new(shebang));
+        self
+    }
+
+    /// Decide which tokens are skipped with `skip_if` instead of [TokenValue::should_skip], and return self.
+    ///
+    /// This lets the same token type be lexed with trivia by one lexer and without it by another.
+    /// To skip tokens in addition to those the token type skips, call `should_skip` within `skip_if`.
+    pub fn skip_if<F: Fn(&TokenType) -> bool + 'a>(mut self, skip_if: F) -> Self {
+        self.skip_if = Some(Box::new(skip_if));
+        self
+    }
+
+    /// Set whether skipped tokens are kept apart from the other tokens, and return self.
+    ///
+    /// Kept tokens have their ranges like any other token, and are returned by [Lexer::skipped_tokens],
+    /// which lets tools such as formatters see trivia that the parser ignores.
+    pub fn keep_skipped(mut self, keep_skipped: bool) -> Self {
+        self.skipped_tokens = keep_skipped.then(Vec::new);
+        self
+    }
+
+    /// Returns the skipped tokens that were kept, which is empty unless [Lexer::keep_skipped] was set.
+    pub fn skipped_tokens(&self) -> &[Token<TokenType>] {
+        self.skipped_tokens.as_deref().unwrap_or_default()
+    }
+
+    /// Returns whether `token` is skipped, following [Lexer::skip_if] if it was set.
+    fn should_skip(&self, token: &TokenType) -> bool {
+        match &self.skip_if {
+            Some(skip_if) => skip_if(token),
+            None => token.should_skip(),
+        }
+    }
+
+    /// Handle the byte order mark and shebang line at the start of the input, returning whether a token was stored.
+    fn lex_prelude(&mut self) -> Result<bool, LexError> {
+        if self.skip_bom {
+            let is_bom =
+                matches!(self.incoming.peek(), Some(Ok(grapheme)) if grapheme == "\u{FEFF}");
+            self.incoming.reset_peek();
+            if is_bom {
+                self.incoming.next();
+            }
+        }
+
+        let shebang = match &self.shebang {
+            Some(shebang) => shebang,
+            None => return Ok(false),
+        };
+
+        let location = match self.incoming.peek() {
+            Some(Ok(grapheme)) if grapheme == "#" => Some(grapheme.location().clone()),
+            _ => None,
+        };
+        let is_shebang = location.is_some()
+            && matches!(self.incoming.peek(), Some(Ok(grapheme)) if grapheme == "!");
+        self.incoming.reset_peek();
+        let location = match location {
+            Some(location) if is_shebang => location,
+            _ => return Ok(false),
+        };
+
+        let start_index = self.incoming.successes();
+        let start_bytes = self.incoming.bytes_read();
+        let mut line = String::new();
+        loop {
+            match self.incoming.peek() {
+                Some(Ok(grapheme)) if !matches!(grapheme.as_str(), "\n" | "\r\n" | "\r") => {
+                    line.push_str(&grapheme)
+                }
+                _ => break,
+            }
+            self.incoming.reset_peek();
+            if let Some(Err((index, error))) = self.incoming.next() {
+                return Err(LexError::other_indexed(index, error));
+            }
+        }
+        self.incoming.reset_peek();
+
+        let token = match shebang(&line) {
+            Some(token) => token,
+            None => return Ok(false),
+        };
+        let skipped = self.should_skip(&token);
+        let end_index = self.incoming.current_index();
+        if let Some(explanations) = &mut self.explanations {
+            explanations.push(TokenExplanation {
+                location: location.clone(),
+                consulted: vec![],
+                matched: Some(SHEBANG.to_string()),
+                range: start_index..=end_index,
+                skipped,
+                failed: false,
+            });
+        }
+
+        let length = self.incoming.bytes_read() - start_bytes;
+        self.store(
+            token,
+            start_index..=end_index,
+            location,
+            SHEBANG,
+            length,
+            skipped,
+        )
+    }
+
+    /// Stream tokens into `sink` instead of retaining them and return self.
+    ///
+    /// Only the last `history` tokens, and never fewer than one, are kept by the lexer for
+    /// tokenizers to look back on through [LexContext] and their `tokens` argument. Each token is
+    /// passed to `sink` once it falls out of that history, and the rest are passed once the input
+    /// has been exhausted, meaning [Lexer::tokens] is empty after [Lexer::tokenize].
+    ///
+    /// Without a sink, every token is retained, as if the sink were a [Vec] with unlimited history.
+    pub fn sink<S: TokenSink<TokenType> + 'a>(mut self, sink: S, history: usize) -> Self {
+        self.sink = Some((Box::new(sink), history.max(1)));
+        self
+    }
+
+    /// Pass every token but the last `keep` to the sink, if there is one.
+    fn flush_sink(&mut self, keep: usize) {
+        if let Some((sink, _)) = &mut self.sink {
+            let flushed = self.tokens.len().saturating_sub(keep);
+            for token in self.tokens.drain(..flushed) {
+                sink.push(token);
+            }
+        }
     }
 
     /// Return a reference to the tokens.
@@ -71,69 +487,558 @@ impl<'a, TokenType: TokenValue> Lexer<'a, TokenType> {
         self.tokens
     }
 
+    /// Return the incoming grapheme stream and consume `self`.
+    pub fn into_incoming(self) -> Graphemes<'a> {
+        self.incoming
+    }
+
+    /// Set whether the lexer stores the text it consumes and return self.
+    ///
+    /// See [Lexer::set_store_source].
+    pub fn store_source(mut self, store_source: bool) -> Self {
+        self.set_store_source(store_source);
+        self
+    }
+
+    /// Set whether the lexer stores the text it consumes, which can be retrieved
+    /// alongside the tokens with [Lexer::into_parts].
+    ///
+    /// This must be set before tokenizing.
+    pub fn set_store_source(&mut self, store_source: bool) {
+        self.incoming.set_store_source(store_source)
+    }
+
+    /// Set the amount of columns between tab stops and return self.
+    ///
+    /// See [Lexer::set_tab_width].
+    pub fn tab_width(mut self, tab_width: usize) -> Self {
+        self.set_tab_width(tab_width);
+        self
+    }
+
+    /// Set the amount of columns between tab stops, which decides the offset within its line
+    /// of every [GraphemeLocation]. Tabs are one column wide by default.
+    ///
+    /// This must be set before tokenizing.
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.incoming.set_tab_width(tab_width)
+    }
+
+    /// Return the tokens and the consumed text, consuming `self`.
+    ///
+    /// Both can outlive the lexer, and the range of each token can be resolved into
+    /// its text with [SourceBuffer::slice].
+    ///
+    /// If the lexer was not storing its source, the [SourceBuffer] will be empty.
+    pub fn into_parts(mut self) -> (Vec<Token<TokenType>>, SourceBuffer) {
+        let source = self.incoming.take_source().unwrap_or_default();
+        (self.tokens, source)
+    }
+
+    /// Set the id of the source being lexed and return self. See [Lexer::set_source].
+    pub fn source(mut self, source: SourceId) -> Self {
+        self.set_source(Some(source));
+        self
+    }
+
+    /// Set the id of the source being lexed, which every token lexed afterwards is given.
+    pub fn set_source(&mut self, source: Option<SourceId>) {
+        self.source = source
+    }
+
+    /// Return the id of the source being lexed.
+    pub fn source_id(&self) -> Option<SourceId> {
+        self.source
+    }
+
+    /// Call `callback` every time at least `interval_bytes` more bytes of input have been
+    /// consumed, and once more when the input has been exhausted, and return self.
+    ///
+    /// This is meant for rendering progress bars while lexing large inputs.
+    /// An `interval_bytes` of zero reports after every token.
+    pub fn on_progress<F: FnMut(Progress) + 'a>(
+        mut self,
+        interval_bytes: usize,
+        callback: F,
+    ) -> Self {
+        self.progress = Some(ProgressReporter {
+            interval: interval_bytes,
+            next_report: interval_bytes,
+            callback: Box::new(callback),
+        });
+        self
+    }
+
+    /// Set the flag that is checked before lexing each token and return self. See [Lexer::set_cancel_flag].
+    pub fn cancel_flag(mut self, cancel_flag: CancelFlag) -> Self {
+        self.set_cancel_flag(Some(cancel_flag));
+        self
+    }
+
+    /// Set the flag that is checked before lexing each token, including skipped tokens.
+    /// Once it is cancelled, lexing stops with [LexError::Cancelled].
+    pub fn set_cancel_flag(&mut self, cancel_flag: Option<CancelFlag>) {
+        self.cancel_flag = cancel_flag
+    }
+
+    /// Return how far the lexer has got through its input.
+    pub fn progress(&self) -> Progress {
+        let bytes = std::iter::once(&self.incoming)
+            .chain(self.suspended.iter().map(|(incoming, _)| incoming))
+            .map(Graphemes::input_bytes)
+            .sum();
+
+        Progress {
+            bytes,
+            tokens: self.tokens.len(),
+            line: self.incoming.lines() - 1,
+        }
+    }
+
+    /// Report progress to the callback registered with [Lexer::on_progress], if it is due.
+    fn report_progress(&mut self, force: bool) {
+        let progress = self.progress();
+        if let Some(reporter) = &mut self.progress {
+            if force || progress.bytes >= reporter.next_report {
+                reporter.next_report = progress.bytes.saturating_add(reporter.interval);
+                (reporter.callback)(progress);
+            }
+        }
+    }
+
+    /// Set the limits that stop lexing once exceeded and return self.
+    pub fn limits(mut self, limits: LexLimits) -> Self {
+        self.set_limits(limits);
+        self
+    }
+
+    /// Set the limits that stop lexing with [LexError::LimitExceeded] once exceeded.
+    pub fn set_limits(&mut self, limits: LexLimits) {
+        self.limits = limits
+    }
+
+    /// Return statistics about the tokens lexed so far.
+    pub fn stats(&self) -> &LexStats<TokenType> {
+        &self.stats
+    }
+
+    /// Returns the counters of the work done while lexing so far, including any included streams.
+    #[cfg(feature = "profiling")]
+    pub fn profile_report(&self) -> super::Profile {
+        let mut profile = self.profile;
+        profile.add(&self.incoming.profile());
+        for (incoming, _) in &self.suspended {
+            profile.add(&incoming.profile());
+        }
+        profile
+    }
+
+    /// Return a reference to the context shared by the tokenizers.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    /// Return a mutable reference to the context shared by the tokenizers.
+    pub fn context_mut(&mut self) -> &mut Context {
+        &mut self.context
+    }
+
+    /// Return a handle to the lexer's [Interner].
+    ///
+    /// The handle shares storage with the lexer, so it can be moved into a tokenizer
+    /// function to intern token text while lexing.
+    pub fn interner(&self) -> Interner {
+        self.interner.clone()
+    }
+
+    /// Intern `text` in the lexer's [Interner].
+    pub fn intern(&self, text: &str) -> Symbol {
+        self.interner.intern(text)
+    }
+
+    /// Return the text that `symbol` represents in the lexer's [Interner].
+    pub fn resolve(&self, symbol: Symbol) -> Option<Rc<str>> {
+        self.interner.resolve(symbol)
+    }
+
+    /// Return a reference to the incoming grapheme stream.
+    pub fn incoming(&self) -> &Graphemes<'a> {
+        &self.incoming
+    }
+
+    /// Return a mutable reference to the incoming grapheme stream.
+    ///
+    /// Graphemes consumed through this are not passed to any tokenizer.
+    pub fn incoming_mut(&mut self) -> &mut Graphemes<'a> {
+        &mut self.incoming
+    }
+
+    /// Consume graphemes until `delimiter` is read. See [Graphemes::raw_read_until].
+    pub fn raw_read_until(&mut self, delimiter: &str) -> Result<String, LexError> {
+        self.incoming.raw_read_until(delimiter)
+    }
+
     /// Tokenize tokens and store them in self.
-    pub fn tokenize(&mut self) -> Result<(), LexError<'a>> {
-        while let Some(result) = self.incoming.next() {
-            match result {
-                Ok((location, grapheme)) => {
-                    let next = match self.incoming.peek() {
-                        None => None,
-                        Some(result) => match result {
-                            Err(_) => None,
-                            Ok((_, grapheme)) => Some(grapheme.clone()),
-                        },
-                    };
-                    self.incoming.reset_peek();
-
-                    let mut found = false;
-
-                    match self
-                        .creation_funcs
-                        .iter()
-                        .filter_map(|creation_func: &Box<dyn TokenizerFn<'a, TokenType>>| {
-                            if !found {
-                                let mut tokenizer = creation_func();
-                                if tokenizer.can_tokenize(&self.tokens, &grapheme, &location, &next)
-                                {
-                                    let start_index = self.incoming.current_index();
-                                    let token = tokenizer.lex(&mut self.tokens, &mut self.incoming);
-                                    self.incoming.reset_peek();
-                                    found = true;
-                                    return Some((start_index, token));
-                                }
-                            }
-
-                            None
-                        })
-                        .last()
-                    {
-                        Some((start_index, token)) => {
-                            let token = token?;
-                            if !token.should_skip() {
-                                let end_index = self.incoming.current_index();
-                                let bounded_token =
-                                    Token::new(token, Some(start_index..=end_index));
-
-                                self.tokens.push(bounded_token)
-                            }
+    ///
+    /// Errors returned by a tokenizer are attributed to it with [LexError::Tokenizer], and those without
+    /// a location are given the location of the grapheme that the failed token started at.
+    pub fn tokenize(&mut self) -> Result<(), LexError> {
+        while self.next_token()?.is_some() {}
+
+        Ok(())
+    }
+
+    /// Tokenize tokens and store them in self, while recording how each token, including
+    /// skipped tokens, was lexed. This is meant for debugging the order of tokenizers.
+    ///
+    /// The explanations are returned alongside the result of tokenizing, so that they are
+    /// available even when lexing fails, in which case the last explanation is of the failed token.
+    pub fn explain(&mut self) -> (Vec<TokenExplanation>, Result<(), LexError>) {
+        self.explanations = Some(vec![]);
+        let result = self.tokenize();
+        (self.explanations.take().unwrap_or_default(), result)
+    }
+
+    /// Tokenize tokens and store them in self, stopping with [LexError::LimitExceeded]
+    /// once any of `limits` is exceeded.
+    ///
+    /// Only `limits` are enforced while tokenizing, after which the previous limits are restored.
+    pub fn tokenize_with_limit(&mut self, limits: LexLimits) -> Result<(), LexError> {
+        let previous = std::mem::replace(&mut self.limits, limits);
+        let result = self.tokenize();
+        self.limits = previous;
+        result
+    }
+
+    /// Tokenize tokens and store them in self, stopping with [LexError::Cancelled]
+    /// once `cancel_flag` is cancelled.
+    ///
+    /// Tokens lexed before cancellation remain stored.
+    pub fn tokenize_cancellable(&mut self, cancel_flag: &CancelFlag) -> Result<(), LexError> {
+        let previous = self.cancel_flag.replace(cancel_flag.clone());
+        let result = self.tokenize();
+        self.cancel_flag = previous;
+        result
+    }
+
+    /// Tokenize tokens and store them in self until the upcoming graphemes spell out `delimiter`,
+    /// which is consumed but not lexed, returning whether the delimiter was found before the input ended.
+    ///
+    /// This allows a tokenizer to lex a delimited region of its stream with another lexer, as done by
+    /// [EmbeddedTokenizer](super::EmbeddedTokenizer). Includes, the shebang line and the EOF token are not handled.
+    pub fn lex_until(&mut self, delimiter: &str) -> Result<bool, LexError> {
+        loop {
+            if self.incoming.eat(delimiter) {
+                return Ok(true);
+            }
+
+            match self.incoming.next() {
+                Some(Ok(grapheme)) => {
+                    self.lex_grapheme(grapheme)?;
+                }
+                Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
+                None => return Ok(false),
+            }
+        }
+    }
+
+    /// Tokenize until the next token that is not skipped, store it in self and return a reference to it.
+    ///
+    /// If a tokenizer requests an include through [Graphemes::include], the included stream is lexed
+    /// before the rest of the current stream.
+    ///
+    /// Once the input has been exhausted, the EOF token is stored and returned if there is one,
+    /// after which `None` is returned.
+    pub fn next_token(&mut self) -> Result<Option<&Token<TokenType>>, LexError> {
+        let started = Instant::now();
+        let stored = self.lex_next();
+        self.stats.add_elapsed(started.elapsed());
+
+        match stored? {
+            true => {
+                if let Some((_, history)) = self.sink {
+                    self.flush_sink(history);
+                }
+                Ok(self.tokens.last())
+            }
+            false => {
+                self.flush_sink(0);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Lex until the next token that is not skipped or the EOF token is stored,
+    /// returning whether a token was stored.
+    fn lex_next(&mut self) -> Result<bool, LexError> {
+        if !self.started {
+            self.started = true;
+            if self.lex_prelude()? {
+                return Ok(true);
+            }
+        }
+
+        loop {
+            loop {
+                if let Some(cancel_flag) = &self.cancel_flag {
+                    if cancel_flag.is_cancelled() {
+                        return Err(LexError::Cancelled);
+                    }
+                }
+
+                let result = match self.incoming.next() {
+                    Some(result) => result,
+                    None => break,
+                };
+
+                match result {
+                    Ok(grapheme) => {
+                        let stored = self.lex_grapheme(grapheme)?;
+                        self.report_progress(false);
+                        if let Some((incoming, source)) = self.incoming.take_include() {
+                            let outer = std::mem::replace(&mut self.incoming, incoming);
+                            self.suspended.push((outer, self.source));
+                            self.source = source;
                         }
-                        None => {
-                            return Err(LexError::other(format!(
-                                "Failed to find tokenizer for {:?}",
-                                grapheme
-                            )))
+                        if stored {
+                            return Ok(true);
                         }
                     }
+                    Err((index, error)) => return Err(LexError::other_indexed(index, error)),
                 }
-                Err((index, error)) => return Err(LexError::other_indexed(index, error)),
+            }
+
+            match self.suspended.pop() {
+                Some((incoming, source)) => {
+                    #[cfg(feature = "profiling")]
+                    self.profile.add(&self.incoming.profile());
+                    self.incoming = incoming;
+                    self.source = source;
+                }
+                None => break,
             }
         }
 
-        if let Some(eof_token) = &self.eof_token {
-            self.tokens.push(Token::from(eof_token.clone()));
+        if !self.finished {
+            self.finished = true;
+            self.report_progress(true);
+            if let Some(eof_token) = &self.eof_token {
+                // The end of file token lies at the end of the stream that was lexed last.
+                let mut token = Token::at(eof_token.clone(), self.incoming.successes());
+                token.set_source(self.source);
+                self.tokens.push(token);
+                return Ok(true);
+            }
         }
 
-        Ok(())
+        Ok(false)
+    }
+
+    /// Lex a token starting at `grapheme`, returning whether a token was stored.
+    fn lex_grapheme(&mut self, grapheme: Grapheme) -> Result<bool, LexError> {
+        // The tokenizers are taken out while lexing, so that they can be borrowed alongside the lexer.
+        let mut tokenizers = std::mem::take(&mut self.tokenizers);
+        let stored = self.dispatch(&mut tokenizers, grapheme);
+        self.tokenizers = tokenizers;
+        stored
+    }
+
+    /// Lex a token starting at `grapheme` with the first of `tokenizers` that can tokenize it,
+    /// returning whether a token was stored.
+    fn dispatch(
+        &mut self,
+        tokenizers: &mut [Registered<'a, TokenType, Context>],
+        grapheme: Grapheme,
+    ) -> Result<bool, LexError> {
+        let (location, grapheme) = grapheme.into_parts();
+        let next = match self.incoming.peek() {
+            None => None,
+            Some(result) => match result {
+                Err(_) => None,
+                Ok(grapheme) => Some(grapheme.into_text()),
+            },
+        };
+        self.incoming.reset_peek();
+
+        let token_count = self.tokens.len();
+        let explaining = self.explanations.is_some();
+        let mut consulted = vec![];
+
+        let dispatched = tokenizers.iter_mut().find_map(|registered| {
+            let tokenizer = registered.prepare();
+            if explaining {
+                consulted.push(tokenizer.name().to_string());
+            }
+            #[cfg(feature = "profiling")]
+            {
+                self.profile.tokenizer_checks += 1;
+            }
+            if !tokenizer.can_tokenize(
+                &LexContext::new(&self.tokens),
+                &grapheme,
+                &location,
+                &next,
+                &mut self.context,
+            ) {
+                return None;
+            }
+
+            let start_index = self.incoming.current_index();
+            let start_bytes = self.incoming.bytes_read() - grapheme.len();
+            #[cfg(feature = "profiling")]
+            {
+                self.profile.tokenizer_lexes += 1;
+            }
+            let token = tokenizer.lex(
+                &mut self.tokens,
+                &grapheme,
+                &location,
+                &mut self.incoming,
+                &mut self.context,
+            );
+            self.incoming.reset_peek();
+            Some((start_index, start_bytes, tokenizer, token))
+        });
+
+        let skipped = matches!(&dispatched, Some((_, _, _, Ok(token))) if self.should_skip(token));
+        if let Some(explanations) = &mut self.explanations {
+            let (matched, start_index, token) = match &dispatched {
+                Some((start_index, _, tokenizer, token)) => (
+                    Some(tokenizer.name().to_string()),
+                    *start_index,
+                    Some(token),
+                ),
+                None => (None, location.index, None),
+            };
+            explanations.push(TokenExplanation {
+                location: location.clone(),
+                consulted,
+                matched,
+                range: start_index..=self.incoming.current_index(),
+                skipped,
+                failed: !matches!(token, Some(Ok(_))),
+            });
+        }
+
+        match dispatched {
+            Some((start_index, start_bytes, tokenizer, token)) => {
+                let token = token.map_err(|error| {
+                    error.in_tokenizer(tokenizer.name()).or_at(location.clone())
+                })?;
+                let length = self.incoming.bytes_read() - start_bytes;
+
+                let end_index = self.incoming.current_index();
+                let start_index = token_start(&self.tokens, token_count, start_index, end_index);
+                self.store(
+                    token,
+                    start_index..=end_index,
+                    location,
+                    tokenizer.name(),
+                    length,
+                    skipped,
+                )
+            }
+            None => Err(LexError::at(
+                location,
+                LexError::other(format!("Failed to find tokenizer for {:?}", grapheme)),
+            )),
+        }
+    }
+
+    /// Record a token of `length` bytes lexed by `tokenizer` in the statistics, check the limits,
+    /// and store it unless it is `skipped`, returning whether it was stored.
+    fn store(
+        &mut self,
+        token: TokenType,
+        range: RangeInclusive<usize>,
+        location: GraphemeLocation,
+        tokenizer: &str,
+        length: usize,
+        skipped: bool,
+    ) -> Result<bool, LexError> {
+        self.stats.record(&token, length, tokenizer, skipped);
+        let bytes = self.progress().bytes;
+        if let Some((limit, max)) = self.limits.check(self.stats.significant(), bytes, length) {
+            return Err(LexError::at(
+                location,
+                LexError::LimitExceeded { limit, max },
+            ));
+        }
+
+        let mut bounded_token = Token::new(token, Some(range));
+        bounded_token.set_source(self.source);
+
+        if skipped {
+            if let Some(skipped_tokens) = &mut self.skipped_tokens {
+                skipped_tokens.push(bounded_token);
+            }
+            return Ok(false);
+        }
+
+        self.tokens.push(bounded_token);
+        Ok(true)
+    }
+
+    /// Check that the ranges of the lexed tokens are in increasing order and do not overlap,
+    /// returning every violation that was found. This is meant for catching buggy tokenizers
+    /// while testing.
+    ///
+    /// If `cover_input` is true, every consumed grapheme must also be covered by a token.
+    /// Skipped tokens are not stored, so this is only meaningful for lexers that skip nothing.
+    /// Ranges are only comparable within a source, so this is not meaningful for lexers
+    /// that include other streams.
+    ///
+    /// Token ranges are grapheme indexes rather than byte offsets, so the ranges are checked in
+    /// graphemes. As each grapheme is made of whole bytes, ranges that partition the graphemes of
+    /// the input also partition its bytes.
+    ///
+    /// [Zero width](Token::is_zero_width) tokens, such as the EOF token, are ignored.
+    pub fn validate_spans(&self, cover_input: bool) -> Result<(), Vec<SpanViolation>> {
+        let mut violations = vec![];
+        let mut next_start = 0;
+
+        for (index, token) in self.tokens.iter().enumerate() {
+            if token.is_zero_width() {
+                continue;
+            }
+            let range = token.range_raw();
+
+            if range.start() > range.end() {
+                violations.push(SpanViolation::Reversed { index });
+                continue;
+            }
+
+            if *range.start() < next_start {
+                violations.push(SpanViolation::Overlapping {
+                    index,
+                    previous_end: next_start - 1,
+                });
+            } else if cover_input && *range.start() > next_start {
+                violations.push(SpanViolation::Gap {
+                    range: next_start..=range.start() - 1,
+                });
+            }
+
+            next_start = next_start.max(range.end().saturating_add(1));
+        }
+
+        let graphemes = self.incoming.successes();
+        if cover_input && next_start < graphemes {
+            violations.push(SpanViolation::Gap {
+                range: next_start..=graphemes - 1,
+            });
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+
+    /// Return an iterator over references to the tokens.
+    pub fn iter(&self) -> std::slice::Iter<'_, Token<TokenType>> {
+        self.tokens.iter()
     }
 
     pub fn lines(&self) -> usize {
@@ -148,3 +1053,23 @@ impl<'a, TokenType: TokenValue> Lexer<'a, TokenType> {
         self.incoming.invalid_bytes()
     }
 }
+
+/// Streams tokens as they are lexed.
+///
+/// Each token is also stored in the lexer, as tokenizers may rely on previous tokens.
+impl<'a, TokenType: TokenValue, Context> Iterator for Lexer<'a, TokenType, Context> {
+    type Item = Result<Token<TokenType>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_token().map(|token| token.cloned()).transpose()
+    }
+}
+
+impl<'l, TokenType: TokenValue, Context> IntoIterator for &'l Lexer<'_, TokenType, Context> {
+    type Item = &'l Token<TokenType>;
+    type IntoIter = std::slice::Iter<'l, Token<TokenType>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
@@ -0,0 +1,346 @@
+use std::rc::Rc;
+
+use super::{
+    error::LexError, GraphemeLocation, Graphemes, LexContext, SmallString, Token, TokenValue,
+    Tokenizer,
+};
+
+#[derive(Default, Clone)]
+struct TrieNode {
+    children: Vec<(char, usize)>,
+    literal: Option<usize>,
+}
+
+struct LiteralSet<TokenType> {
+    nodes: Vec<TrieNode>,
+    literals: Vec<String>,
+    case_insensitive: bool,
+    map: Rc<dyn Fn(&str) -> TokenType>,
+}
+
+impl<TokenType> Clone for LiteralSet<TokenType> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            literals: self.literals.clone(),
+            case_insensitive: self.case_insensitive,
+            map: self.map.clone(),
+        }
+    }
+}
+
+impl<TokenType> LiteralSet<TokenType> {
+    /// Walks from `node` through the characters of `grapheme`, returning the node reached.
+    ///
+    /// The trie is keyed by characters rather than graphemes, so that a folded grapheme can match
+    /// several graphemes of a literal, such as `ß` matching `SS`. Literals are only matched at the
+    /// end of a grapheme, so one never ends partway through a grapheme of the input.
+    fn child(&self, node: usize, grapheme: &str) -> Option<usize> {
+        let folded;
+        let grapheme = if self.case_insensitive {
+            folded = fold(grapheme);
+            folded.as_str()
+        } else {
+            grapheme
+        };
+
+        grapheme.chars().try_fold(node, |node, character| {
+            self.nodes[node]
+                .children
+                .iter()
+                .find(|(child, _)| *child == character)
+                .map(|(_, child)| *child)
+        })
+    }
+
+    /// Rebuilds the trie from the literals.
+    fn build(&mut self) {
+        self.nodes = vec![TrieNode::default()];
+        for (index, literal) in self.literals.iter().enumerate() {
+            let folded;
+            let literal = match self.case_insensitive {
+                true => {
+                    folded = fold(literal);
+                    folded.as_str()
+                }
+                false => literal.as_str(),
+            };
+
+            let mut node = 0;
+            for character in literal.chars() {
+                node = match self.nodes[node]
+                    .children
+                    .iter()
+                    .find(|(child, _)| *child == character)
+                {
+                    Some((_, child)) => *child,
+                    None => {
+                        self.nodes.push(TrieNode::default());
+                        let child = self.nodes.len() - 1;
+                        self.nodes[node].children.push((character, child));
+                        child
+                    }
+                };
+            }
+
+            if self.nodes[node].literal.is_none() {
+                self.nodes[node].literal = Some(index);
+            }
+        }
+    }
+}
+
+/// Folds the case of `text` using the full case folding of Unicode, so that `ß` folds to `ss`
+/// and `ς` folds to `σ`.
+///
+/// Each character is lowercased, and then folded with [fold_lowercase] where folding differs.
+fn fold(text: &str) -> SmallString {
+    let mut folded = SmallString::new();
+    for character in text.chars().flat_map(char::to_lowercase) {
+        match fold_lowercase(character) {
+            Some(folding) => folded.push_str(folding),
+            None => folded.push(character),
+        }
+    }
+    folded
+}
+
+/// Returns the case folding of the lowercase `character`, if it differs from the character.
+///
+/// This covers every character of CaseFolding.txt with a `C` or `F` folding that is not reached
+/// by lowercasing alone. Lowercase Cherokee, which folds to uppercase, is left as it is, since
+/// its uppercase form lowercases to it.
+fn fold_lowercase(character: char) -> Option<&'static str> {
+    Some(match character {
+        '\u{b5}' => "\u{3bc}",
+        '\u{df}' => "ss",
+        '\u{149}' => "\u{2bc}n",
+        '\u{17f}' => "s",
+        '\u{1f0}' => "j\u{30c}",
+        '\u{345}' => "\u{3b9}",
+        '\u{390}' => "\u{3b9}\u{308}\u{301}",
+        '\u{3b0}' => "\u{3c5}\u{308}\u{301}",
+        '\u{3c2}' => "\u{3c3}",
+        '\u{3d0}' => "\u{3b2}",
+        '\u{3d1}' => "\u{3b8}",
+        '\u{3d5}' => "\u{3c6}",
+        '\u{3d6}' => "\u{3c0}",
+        '\u{3f0}' => "\u{3ba}",
+        '\u{3f1}' => "\u{3c1}",
+        '\u{3f5}' => "\u{3b5}",
+        '\u{587}' => "\u{565}\u{582}",
+        '\u{1c80}' => "\u{432}",
+        '\u{1c81}' => "\u{434}",
+        '\u{1c82}' => "\u{43e}",
+        '\u{1c83}' => "\u{441}",
+        '\u{1c84}' | '\u{1c85}' => "\u{442}",
+        '\u{1c86}' => "\u{44a}",
+        '\u{1c87}' => "\u{463}",
+        '\u{1c88}' => "\u{a64b}",
+        '\u{1e96}' => "h\u{331}",
+        '\u{1e97}' => "t\u{308}",
+        '\u{1e98}' => "w\u{30a}",
+        '\u{1e99}' => "y\u{30a}",
+        '\u{1e9a}' => "a\u{2be}",
+        '\u{1e9b}' => "\u{1e61}",
+        '\u{1f50}' => "\u{3c5}\u{313}",
+        '\u{1f52}' => "\u{3c5}\u{313}\u{300}",
+        '\u{1f54}' => "\u{3c5}\u{313}\u{301}",
+        '\u{1f56}' => "\u{3c5}\u{313}\u{342}",
+        '\u{1f80}' => "\u{1f00}\u{3b9}",
+        '\u{1f81}' => "\u{1f01}\u{3b9}",
+        '\u{1f82}' => "\u{1f02}\u{3b9}",
+        '\u{1f83}' => "\u{1f03}\u{3b9}",
+        '\u{1f84}' => "\u{1f04}\u{3b9}",
+        '\u{1f85}' => "\u{1f05}\u{3b9}",
+        '\u{1f86}' => "\u{1f06}\u{3b9}",
+        '\u{1f87}' => "\u{1f07}\u{3b9}",
+        '\u{1f90}' => "\u{1f20}\u{3b9}",
+        '\u{1f91}' => "\u{1f21}\u{3b9}",
+        '\u{1f92}' => "\u{1f22}\u{3b9}",
+        '\u{1f93}' => "\u{1f23}\u{3b9}",
+        '\u{1f94}' => "\u{1f24}\u{3b9}",
+        '\u{1f95}' => "\u{1f25}\u{3b9}",
+        '\u{1f96}' => "\u{1f26}\u{3b9}",
+        '\u{1f97}' => "\u{1f27}\u{3b9}",
+        '\u{1fa0}' => "\u{1f60}\u{3b9}",
+        '\u{1fa1}' => "\u{1f61}\u{3b9}",
+        '\u{1fa2}' => "\u{1f62}\u{3b9}",
+        '\u{1fa3}' => "\u{1f63}\u{3b9}",
+        '\u{1fa4}' => "\u{1f64}\u{3b9}",
+        '\u{1fa5}' => "\u{1f65}\u{3b9}",
+        '\u{1fa6}' => "\u{1f66}\u{3b9}",
+        '\u{1fa7}' => "\u{1f67}\u{3b9}",
+        '\u{1fb2}' => "\u{1f70}\u{3b9}",
+        '\u{1fb3}' => "\u{3b1}\u{3b9}",
+        '\u{1fb4}' => "\u{3ac}\u{3b9}",
+        '\u{1fb6}' => "\u{3b1}\u{342}",
+        '\u{1fb7}' => "\u{3b1}\u{342}\u{3b9}",
+        '\u{1fbe}' => "\u{3b9}",
+        '\u{1fc2}' => "\u{1f74}\u{3b9}",
+        '\u{1fc3}' => "\u{3b7}\u{3b9}",
+        '\u{1fc4}' => "\u{3ae}\u{3b9}",
+        '\u{1fc6}' => "\u{3b7}\u{342}",
+        '\u{1fc7}' => "\u{3b7}\u{342}\u{3b9}",
+        '\u{1fd2}' => "\u{3b9}\u{308}\u{300}",
+        '\u{1fd3}' => "\u{3b9}\u{308}\u{301}",
+        '\u{1fd6}' => "\u{3b9}\u{342}",
+        '\u{1fd7}' => "\u{3b9}\u{308}\u{342}",
+        '\u{1fe2}' => "\u{3c5}\u{308}\u{300}",
+        '\u{1fe3}' => "\u{3c5}\u{308}\u{301}",
+        '\u{1fe4}' => "\u{3c1}\u{313}",
+        '\u{1fe6}' => "\u{3c5}\u{342}",
+        '\u{1fe7}' => "\u{3c5}\u{308}\u{342}",
+        '\u{1ff2}' => "\u{1f7c}\u{3b9}",
+        '\u{1ff3}' => "\u{3c9}\u{3b9}",
+        '\u{1ff4}' => "\u{3ce}\u{3b9}",
+        '\u{1ff6}' => "\u{3c9}\u{342}",
+        '\u{1ff7}' => "\u{3c9}\u{342}\u{3b9}",
+        '\u{fb00}' => "ff",
+        '\u{fb01}' => "fi",
+        '\u{fb02}' => "fl",
+        '\u{fb03}' => "ffi",
+        '\u{fb04}' => "ffl",
+        '\u{fb05}' | '\u{fb06}' => "st",
+        '\u{fb13}' => "\u{574}\u{576}",
+        '\u{fb14}' => "\u{574}\u{565}",
+        '\u{fb15}' => "\u{574}\u{56b}",
+        '\u{fb16}' => "\u{57e}\u{576}",
+        '\u{fb17}' => "\u{574}\u{56d}",
+        _ => return None,
+    })
+}
+
+/// A tokenizer that matches the longest of a set of literals, such as operators and punctuation,
+/// and maps the matched literal into a token with a closure.
+///
+/// The literals are compiled into a prefix trie of graphemes, so `"=="`, `"="` and `"=>"` can be
+/// handled by a single tokenizer rather than one per literal.
+///
+/// The trie is shared between clones, so a lexer can create a tokenizer by cloning one:
+/// `lexer.tokenizer(move || literals.clone())`.
+pub struct LiteralSetTokenizer<TokenType> {
+    set: Rc<LiteralSet<TokenType>>,
+}
+
+impl<TokenType> Clone for LiteralSetTokenizer<TokenType> {
+    fn clone(&self) -> Self {
+        Self {
+            set: self.set.clone(),
+        }
+    }
+}
+
+impl<TokenType> LiteralSetTokenizer<TokenType> {
+    /// Create a tokenizer that matches any of `literals`, mapping the matched literal with `map`.
+    ///
+    /// Empty literals are ignored.
+    pub fn new<I, S, F>(literals: I, map: F) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+        F: Fn(&str) -> TokenType + 'static,
+    {
+        let mut set = LiteralSet {
+            nodes: vec![],
+            literals: vec![],
+            case_insensitive: false,
+            map: Rc::new(map),
+        };
+        for literal in literals {
+            let literal = literal.as_ref();
+            if !literal.is_empty() && !set.literals.iter().any(|existing| existing == literal) {
+                set.literals.push(literal.to_string());
+            }
+        }
+        set.build();
+
+        Self { set: Rc::new(set) }
+    }
+
+    /// Match literals regardless of case and return self, which is useful for languages such as SQL.
+    ///
+    /// Case is folded using the full case folding of Unicode, so `STRASSE` matches `straße`.
+    /// The literal passed to the mapping function is the literal as it was given, rather than the
+    /// matched text. If several literals only differ in case, the first is used.
+    ///
+    /// If the tokenizer has been cloned, the clones keep matching case sensitively.
+    pub fn case_insensitive(mut self) -> Self {
+        let set = Rc::make_mut(&mut self.set);
+        set.case_insensitive = true;
+        set.build();
+        self
+    }
+
+    /// Returns whether literals are matched regardless of case.
+    pub fn is_case_insensitive(&self) -> bool {
+        self.set.case_insensitive
+    }
+
+    /// Returns the literals that the tokenizer matches.
+    pub fn literals(&self) -> &[String] {
+        &self.set.literals
+    }
+}
+
+impl<TokenType: TokenValue, Context> Tokenizer<TokenType, Context>
+    for LiteralSetTokenizer<TokenType>
+{
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<TokenType>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut Context,
+    ) -> bool {
+        self.set.child(0, grapheme).is_some()
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<TokenType>>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        let set = &*self.set;
+        let mut node = match set.child(0, grapheme) {
+            Some(node) => node,
+            None => return Err(LexError::other(format!("Unexpected {:?}", grapheme))),
+        };
+
+        let mut longest = set.nodes[node].literal.map(|literal| (literal, 0));
+        let mut peeked = 0;
+        while let Some(Ok(next)) = incoming.peek() {
+            node = match set.child(node, &next) {
+                Some(node) => node,
+                None => break,
+            };
+            peeked += 1;
+            if let Some(literal) = set.nodes[node].literal {
+                longest = Some((literal, peeked));
+            }
+        }
+        incoming.reset_peek();
+
+        let (literal, length) = match longest {
+            Some(longest) => longest,
+            None => {
+                return Err(LexError::other(format!(
+                    "No literal matches the text starting with {:?}",
+                    grapheme
+                )))
+            }
+        };
+
+        for _ in 0..length {
+            if let Some(Err((index, error))) = incoming.next() {
+                return Err(LexError::other_indexed(index, error));
+            }
+        }
+
+        Ok((set.map)(&set.literals[literal]))
+    }
+}
@@ -0,0 +1,102 @@
+use std::{fs, io::Result as IoResult, path::Path};
+
+use super::{Graphemes, Lexer, SourceBuffer, SourceId, SourceRegistry, Span, TokenValue};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A [Span] resolved against the source it lies within.
+pub struct ResolvedSpan<'m> {
+    /// The id of the source.
+    pub source: SourceId,
+    /// The description of the source, such as its file path.
+    pub name: &'m str,
+    /// The line the span starts on, starting at zero.
+    pub line: usize,
+    /// The column the span starts at in graphemes, starting at zero.
+    pub column: usize,
+    /// The text covered by the span.
+    pub snippet: &'m str,
+    /// The full text of the line the span starts on, including its line ending.
+    pub line_text: &'m str,
+}
+
+#[derive(Debug, Default)]
+/// Owns the text of multiple sources, such as files or strings, and creates lexers for them.
+///
+/// Each source is assigned a [SourceId], which the tokens lexed from it carry, allowing any
+/// [Span] to be resolved back into its source, line, column and text.
+pub struct SourceManager {
+    registry: SourceRegistry,
+    buffers: Vec<SourceBuffer>,
+}
+
+impl SourceManager {
+    /// Create an empty manager.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a source described by `name` with the text `text`, returning its id.
+    pub fn add_string<N: Into<String>>(&mut self, name: N, text: &str) -> SourceId {
+        self.buffers.push(SourceBuffer::from_text(text));
+        self.registry.register(name)
+    }
+
+    /// Read the file at `path` and add it as a source described by its path, returning its id.
+    ///
+    /// Invalid UTF-8 sequences are replaced with U+FFFD.
+    pub fn add_file<P: AsRef<Path>>(&mut self, path: P) -> IoResult<SourceId> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)?;
+        let text = String::from_utf8_lossy(&bytes);
+        Ok(self.add_string(path.display().to_string(), &text))
+    }
+
+    /// Returns the registry mapping ids to source descriptions.
+    pub fn registry(&self) -> &SourceRegistry {
+        &self.registry
+    }
+
+    /// Returns the text of the source with the id `id`.
+    pub fn source(&self, id: SourceId) -> Option<&SourceBuffer> {
+        self.buffers.get(id.index())
+    }
+
+    /// Create a lexer over the source with the id `id`, whose tokens carry `id`.
+    pub fn lexer<TokenType: TokenValue>(
+        &self,
+        id: SourceId,
+        eof_token: Option<TokenType>,
+    ) -> Option<Lexer<'_, TokenType>> {
+        self.lexer_with_context(id, eof_token, ())
+    }
+
+    /// Create a lexer over the source with the id `id` whose tokenizers share `context`.
+    pub fn lexer_with_context<TokenType: TokenValue, Context>(
+        &self,
+        id: SourceId,
+        eof_token: Option<TokenType>,
+        context: Context,
+    ) -> Option<Lexer<'_, TokenType, Context>> {
+        let text = self.source(id)?.text();
+        let incoming = Graphemes::from_chunks(std::iter::once(text));
+        Some(Lexer::with_graphemes(incoming, eof_token, context).source(id))
+    }
+
+    /// Resolve `span` into its source, line, column and text.
+    ///
+    /// Returns `None` if the span has no source, or lies outside of its source.
+    pub fn resolve(&self, span: &Span) -> Option<ResolvedSpan<'_>> {
+        let id = span.source()?;
+        let buffer = self.source(id)?;
+        let (line, column) = buffer.location_of(span.start())?;
+
+        Some(ResolvedSpan {
+            source: id,
+            name: self.registry.name(id)?,
+            line,
+            column,
+            snippet: buffer.slice_span(span.grapheme_span())?,
+            line_text: buffer.line(line)?,
+        })
+    }
+}
@@ -0,0 +1,78 @@
+use std::marker::PhantomData;
+
+use super::{Token, TokenValue};
+
+impl<TokenType: TokenValue> Token<TokenType> {
+    /// Converts the token's value into `U`, keeping its range, source and metadata.
+    pub fn convert<U: TokenValue + From<TokenType>>(self) -> Token<U> {
+        self.map(U::from)
+    }
+}
+
+/// An item of a token stream whose token can be converted into a `Token<U>`, being either a [Token]
+/// or a [Result] containing one, such as the items of a [Lexer](super::Lexer).
+pub trait ConvertToken<U: TokenValue> {
+    /// The item with its token converted.
+    type Output;
+
+    /// Converts the token of the item, keeping its range, source and metadata.
+    fn convert_token(self) -> Self::Output;
+}
+
+impl<TokenType: TokenValue, U: TokenValue + From<TokenType>> ConvertToken<U> for Token<TokenType> {
+    type Output = Token<U>;
+
+    fn convert_token(self) -> Self::Output {
+        self.convert()
+    }
+}
+
+impl<TokenType: TokenValue, U: TokenValue + From<TokenType>, E> ConvertToken<U>
+    for Result<Token<TokenType>, E>
+{
+    type Output = Result<Token<U>, E>;
+
+    fn convert_token(self) -> Self::Output {
+        self.map(Token::convert)
+    }
+}
+
+/// An iterator adapter that converts the tokens of a token stream into `Token<U>`, created by
+/// [MapTokensExt::map_tokens]. This allows tokens of a sub-lexer with its own token type to be
+/// embedded into a host grammar.
+pub struct MapTokens<I, U> {
+    iter: I,
+    target: PhantomData<fn() -> U>,
+}
+
+impl<I: Iterator, U: TokenValue> Iterator for MapTokens<I, U>
+where
+    I::Item: ConvertToken<U>,
+{
+    type Item = <I::Item as ConvertToken<U>>::Output;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.iter.next().map(ConvertToken::convert_token)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// Adds [MapTokensExt::map_tokens] to every iterator.
+pub trait MapTokensExt: Iterator + Sized {
+    /// Converts every token of the stream into `Token<U>`, where `U` implements [From] for the token
+    /// type, keeping ranges, sources and metadata.
+    fn map_tokens<U: TokenValue>(self) -> MapTokens<Self, U>
+    where
+        Self::Item: ConvertToken<U>,
+    {
+        MapTokens {
+            iter: self,
+            target: PhantomData,
+        }
+    }
+}
+
+impl<I: Iterator> MapTokensExt for I {}
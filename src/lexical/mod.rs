@@ -1,19 +1,72 @@
 //mod lexer;
 //mod token;
+mod bytes;
+mod csv;
+mod dfa;
+mod doc;
+mod embed;
+mod factory;
+mod heredoc;
+mod hexdump;
+mod ini;
+mod intern;
 mod lexer;
+mod literal;
+mod manager;
+mod map;
+mod position;
+#[cfg(feature = "profiling")]
+mod profile;
+mod repl;
+mod sink;
+mod small_string;
+mod source;
+mod span;
+mod stats;
 mod stream;
-mod token;
+mod text;
+mod tokenizer;
 
+pub use crate::{cancel::*, token::*};
+
+pub use bytes::*;
+pub use csv::*;
+pub use dfa::*;
+pub use doc::*;
+pub use embed::*;
+pub use factory::*;
+pub use heredoc::*;
+pub use hexdump::*;
+pub use ini::*;
+pub use intern::*;
 pub use lexer::*;
+pub use literal::*;
+pub use manager::*;
+pub use map::*;
+pub use position::*;
+#[cfg(feature = "profiling")]
+pub use profile::*;
+pub use repl::*;
+pub use sink::*;
+pub use small_string::*;
+pub use source::*;
+pub use span::*;
+pub use stats::*;
 pub use stream::*;
-pub use token::*;
+pub use text::*;
+pub use tokenizer::*;
 
+/// Lexes many sources at once across a pool of threads.
+pub mod batch;
+/// Compares token streams.
+pub mod diff;
 /// Stores error types.
 pub mod error;
+pub mod escape;
 
 #[cfg(test)]
 mod tests {
-    use std::{fmt::Display, io::Cursor};
+    use std::{fmt::Display, io::Cursor, ops::RangeInclusive};
 
     use super::{error::LexError, *};
 
@@ -34,6 +87,10 @@ mod tests {
         fn should_skip(&self) -> bool {
             matches!(self, Self::Whitespace)
         }
+
+        fn eof() -> Option<Self> {
+            Some(Self::Eof)
+        }
     }
 
     impl Display for Token {
@@ -64,10 +121,11 @@ mod tests {
     impl Tokenizer<Token> for DoubleQuotedStringLexer {
         fn can_tokenize(
             &mut self,
-            _: &[super::Token<Token>],
+            _: &LexContext<Token>,
             grapheme: &str,
             _: &super::stream::GraphemeLocation,
-            next_grapheme: &Option<String>,
+            next_grapheme: &Option<SmallString>,
+            _: &mut (),
         ) -> bool {
             if let ("\"", Some(next_g)) = (grapheme, next_grapheme) {
                 if !matches!(next_g.as_str(), "\n" | "\r") {
@@ -80,19 +138,22 @@ mod tests {
         fn lex<'a, 'b>(
             &'b mut self,
             _: &'b mut Vec<super::Token<Token>>,
+            _: &str,
+            _: &super::stream::GraphemeLocation,
             incoming_characters: &'b mut super::stream::Graphemes<'a>,
-        ) -> Result<Token, LexError<'a>> {
+            _: &mut (),
+        ) -> Result<Token, LexError> {
             if let Some('"') = self.internal_value.chars().last() {
                 return Ok(Token::double_quoted_string(""));
             }
             match incoming_characters.peek() {
-                Some(Ok((_location, _grapheme))) => {},
+                Some(Ok(_grapheme)) => {},
                 Some(Err((_index, _error))) => {},
                 None => {},
             }
             loop {
                 let mut character = match incoming_characters.next() {
-                    Some(Ok((_location, grapheme))) => grapheme,
+                    Some(Ok(grapheme)) => grapheme.into_text(),
                     Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
                     None => return Err(LexError::UnexpectedEndOfStream),
                 };
@@ -110,7 +171,7 @@ mod tests {
                             )))
                         }
                     }
-                    .to_string();
+                    .into();
                     self.internal_value.pop();
                 } else {
                     if character == "\"" {
@@ -121,6 +182,10 @@ mod tests {
                 self.internal_value.push_str(&character)
             }
         }
+
+        fn reset(&mut self) {
+            self.internal_value.clear()
+        }
     }
 
     struct Whitespace;
@@ -134,10 +199,11 @@ mod tests {
     impl Tokenizer<Token> for Whitespace {
         fn can_tokenize(
             &mut self,
-            _: &[super::Token<Token>],
+            _: &LexContext<Token>,
             grapheme: &str,
             _: &super::stream::GraphemeLocation,
-            _next: &Option<String>,
+            _next: &Option<SmallString>,
+            _: &mut (),
         ) -> bool {
             grapheme.chars().fold(true, Whitespace::is)
         }
@@ -145,9 +211,12 @@ mod tests {
         fn lex<'a, 'b>(
             &'b mut self,
             _: &'b mut Vec<super::Token<Token>>,
+            _: &str,
+            _: &super::stream::GraphemeLocation,
             incoming: &'b mut super::stream::Graphemes<'a>,
-        ) -> Result<Token, LexError<'a>> {
-            if let Some(Ok((_, first_grapheme))) = incoming.peek() {
+            _: &mut (),
+        ) -> Result<Token, LexError> {
+            if let Some(Ok(first_grapheme)) = incoming.peek() {
                 if !first_grapheme.chars().fold(true, Whitespace::is) {
                     return Ok(Token::Whitespace);
                 }
@@ -155,9 +224,9 @@ mod tests {
             }
             loop {
                 match incoming.next() {
-                    Some(Ok((_, grapheme))) if grapheme.chars().fold(true, Whitespace::is) => {
+                    Some(Ok(grapheme)) if grapheme.chars().fold(true, Whitespace::is) => {
                         match incoming.peek() {
-                            Some(Ok((_, next_grapheme)))
+                            Some(Ok(next_grapheme))
                                 if !next_grapheme.chars().fold(true, Whitespace::is) =>
                             {
                                 break
@@ -166,7 +235,7 @@ mod tests {
                             None => break,
                         }
                     }
-                    Some(Ok((_, _))) => unreachable!(),
+                    Some(Ok(_)) => unreachable!(),
                     Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
                     None => break,
                 }
@@ -181,13 +250,11 @@ mod tests {
         
                         "PPPps\n\n""#
             .to_vec();
-        for _ in 0..100 {
-            input.push(0xAD);
-        }
+        input.extend([0xAD; 100]);
         let input = Cursor::new(input);
 
         let mut lexer = Lexer::new(input, true, Some(Token::Eof))
-            .tokenizer(|| DoubleQuotedStringLexer::new())
+            .tokenizer(DoubleQuotedStringLexer::new)
             .tokenizer(|| Whitespace);
 
         if let Err(error) = lexer.tokenize() {
@@ -202,4 +269,2113 @@ mod tests {
 
         println!("Invalid byte count: {}", lexer.dropped_bytes());
     }
+
+    #[test]
+    fn test_raw_read_until() {
+        let mut incoming = Graphemes::new(Cursor::new("raw\ntext\nEOF rest"), true);
+
+        assert_eq!(incoming.raw_read_until("EOF").unwrap(), "raw\ntext\n");
+        assert_eq!(incoming.lines(), 3);
+        assert!(matches!(
+            incoming.raw_read_until("EOF"),
+            Err(LexError::UnexpectedEndOfStream)
+        ));
+    }
+
+    #[test]
+    fn test_consume_balanced() {
+        let mut incoming = Graphemes::new(Cursor::new("x{a {b} c} {d {e}"), true);
+        assert!(incoming.eat("x{"));
+        let (inner, span) = incoming.consume_balanced("{", "}", true).unwrap();
+        assert_eq!(inner, "a {b} c");
+        assert_eq!(span.range(), 1..=9);
+
+        assert!(incoming.eat(" {"));
+        let (inner, span) = incoming.consume_balanced("{", "}", false).unwrap();
+        assert_eq!(inner, "d {e");
+        assert_eq!(span.range(), 11..=16);
+
+        let mut incoming = Graphemes::new(Cursor::new("#{ #{a}} #{"), true);
+        assert!(incoming.eat("#{"));
+        assert_eq!(
+            incoming.consume_balanced("#{", "}", true).unwrap().0,
+            " #{a}"
+        );
+        assert!(incoming.eat(" #{"));
+        let error = incoming.consume_balanced("#{", "}", true).unwrap_err();
+        assert!(error.is_incomplete());
+        assert!(matches!(
+            error,
+            LexError::UnterminatedToken { start_span, .. } if start_span.range() == (9..=10)
+        ));
+    }
+
+    #[test]
+    fn test_lex_fixture() {
+        let lexer = |input: &'static str| {
+            Lexer::new(Cursor::new(input), true, Some(Token::Eof))
+                .tokenizer(DoubleQuotedStringLexer::new)
+                .tokenizer(|| Whitespace)
+        };
+        let fixture = "\
+=== strings
+\"a\" \"bc\"
+---
+0..=2 DoubleQuotedString(\"a\")
+4..=7 DoubleQuotedString
+- Eof
+
+=== empty
+---
+- Eof
+";
+        crate::testing::lex_fixture(fixture, lexer);
+
+        let failure = std::panic::catch_unwind(|| {
+            crate::testing::lex_fixture("=== wrong\n\"a\"\n---\n0..=2 Whitespace\n- Eof", lexer)
+        })
+        .unwrap_err();
+        let message = failure.downcast_ref::<String>().unwrap();
+        assert!(message.contains("=== wrong\nexpected:\n0..=2 Whitespace"));
+    }
+
+    #[test]
+    fn test_interner() {
+        let lexer: Lexer<Token> = Lexer::new(Cursor::new(""), true, None);
+        let interner = lexer.interner();
+
+        let first = interner.intern("identifier");
+        let second = lexer.intern("other");
+
+        assert_eq!(lexer.intern("identifier"), first);
+        assert_ne!(first, second);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(lexer.resolve(second).as_deref(), Some("other"));
+    }
+
+    #[test]
+    fn test_small_string_graphemes() {
+        let graphemes: Vec<SmallString> = Graphemes::new(Cursor::new("e\u{301}a"), true)
+            .map(|result| result.unwrap().into_text())
+            .collect();
+
+        assert_eq!(graphemes, ["e\u{301}", "a"]);
+        assert!(graphemes.iter().all(|grapheme| !grapheme.spilled()));
+    }
+
+    #[test]
+    fn test_into_parts() {
+        let mut lexer = Lexer::new(Cursor::new("\"one\"\n  \"two\""), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace)
+            .store_source(true);
+        lexer.tokenize().unwrap();
+
+        let (tokens, source) = lexer.into_parts();
+
+        let texts: Vec<&str> = tokens
+            .iter()
+            .filter_map(|token| source.slice(token.range_raw()))
+            .collect();
+
+        assert_eq!(texts, ["\"one\"", "\"two\""]);
+        assert_eq!(source.lines(), 2);
+        assert_eq!(source.line(1), Some("  \"two\""));
+    }
+
+    #[test]
+    fn test_error_location() {
+        let mut lexer = Lexer::new(Cursor::new("\"ok\"\n  \"bad\\q\""), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+
+        let error = lexer.tokenize().unwrap_err();
+        let location = error.location().unwrap();
+
+        assert_eq!((location.index, location.line), (7, 1));
+        assert!(error
+            .tokenizer()
+            .unwrap()
+            .ends_with("DoubleQuotedStringLexer"));
+        assert!(matches!(error.inner(), LexError::Other(_)));
+
+        fn assert_shareable<T: Send + Sync + 'static>(_: &T) {}
+        assert_shareable(&error);
+        let boxed: Box<dyn std::error::Error + Send + Sync> = error.into();
+        assert!(boxed.to_string().contains("line: 1"));
+    }
+
+    #[test]
+    fn test_token_iterators() {
+        let lexer = Lexer::new(Cursor::new("\"a\" \"b\""), true, Some(Token::Eof))
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+
+        let streamed: Vec<String> = lexer
+            .map(|token| token.unwrap().token().to_string())
+            .filter(|token| token != "EOF")
+            .collect();
+        assert_eq!(
+            streamed,
+            ["Quoted String := \"a\"", "Quoted String := \"b\""]
+        );
+    }
+
+    #[test]
+    fn test_fuzz_inputs() {
+        let inputs: [&[u8]; 5] = [
+            b"",
+            b"\"unterminated",
+            b"\xF0\x9F\x91\xA8\xE2\x80\x8D\xF0\x9F",
+            b"\r\n\xFF\xFE\"\xC3\"",
+            "\u{1F1FA}\u{1F1F8}\u{1F1EC}".as_bytes(),
+        ];
+
+        for input in inputs {
+            crate::testing::fuzz_lexer(input);
+        }
+    }
+
+    #[test]
+    fn test_validate_spans() {
+        let mut lexer = Lexer::new(Cursor::new("\"a\" \"b\""), true, Some(Token::Eof))
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+        lexer.tokenize().unwrap();
+
+        assert_eq!(lexer.validate_spans(false), Ok(()));
+        assert_eq!(
+            lexer.validate_spans(true),
+            Err(vec![SpanViolation::Gap { range: 3..=3 }])
+        );
+
+        *lexer.tokens_mut()[1].range_raw_mut() = 2..=6;
+        assert_eq!(
+            lexer.validate_spans(false),
+            Err(vec![SpanViolation::Overlapping {
+                index: 1,
+                previous_end: 2
+            }])
+        );
+
+        let mut lexer = CsvDialect::csv().lexer(Cursor::new("a,bc"));
+        lexer.tokenize().unwrap();
+        assert_eq!(lexer.tokens()[0].range_raw(), &(0..=0));
+        assert_eq!(lexer.validate_spans(true), Ok(()));
+    }
+
+    /// Reads a single byte at a time, so that multi-byte sequences are split across reads.
+    struct ByteByByte<'a>(&'a [u8]);
+
+    impl std::io::Read for ByteByByte<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            match (self.0.split_first(), buf.first_mut()) {
+                (Some((byte, rest)), Some(slot)) => {
+                    *slot = *byte;
+                    self.0 = rest;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            }
+        }
+    }
+
+    #[test]
+    fn test_grapheme() {
+        let mut graphemes = Graphemes::new(Cursor::new("a\n \u{3000}e\u{301}"), true);
+
+        let peeked = graphemes.peek().unwrap().unwrap();
+        assert_eq!(peeked, "a");
+        assert_eq!(peeked.index(), 0);
+        graphemes.reset_peek();
+
+        let read: Vec<Grapheme> = graphemes.by_ref().map(Result::unwrap).collect();
+        assert_eq!(read, ["a", "\n", " ", "\u{3000}", "e\u{301}"]);
+        assert_eq!(
+            read.iter().map(Grapheme::index).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4]
+        );
+        assert_eq!(read[2].location().line, 1);
+        assert!(read[1].is_newline() && !read[2].is_newline());
+        assert!(read[1].is_whitespace() && read[3].is_whitespace() && !read[4].is_whitespace());
+        assert_eq!(read[4].chars().collect::<Vec<_>>(), ['e', '\u{301}']);
+        assert_eq!(read[4].to_string(), "e\u{301}");
+    }
+
+    #[test]
+    fn test_position_tracking() {
+        fn locations(input: &str, tab_width: usize) -> Vec<(usize, usize)> {
+            let mut graphemes = Graphemes::new(Cursor::new(input.to_string()), true);
+            graphemes.set_tab_width(tab_width);
+            graphemes
+                .map(|grapheme| {
+                    let location = grapheme.unwrap().location().clone();
+                    (location.line, location.offset)
+                })
+                .collect()
+        }
+
+        assert_eq!(locations("ab", 1), [(0, 0), (0, 1)]);
+        assert_eq!(locations("a\nb", 1), [(0, 0), (0, 1), (1, 0)]);
+        assert_eq!(locations("a\r\nb", 1), [(0, 0), (0, 1), (1, 0)]);
+        assert_eq!(locations("a\rb", 1), [(0, 0), (0, 1), (1, 0)]);
+        assert_eq!(locations("\n\n", 1), [(0, 0), (1, 0)]);
+        assert_eq!(
+            locations("\r\r\n\n\rx", 1),
+            [(0, 0), (1, 0), (2, 0), (3, 0), (4, 0)]
+        );
+        assert_eq!(locations("\n\r", 1), [(0, 0), (1, 0)]);
+        assert_eq!(locations("\ta\tb", 1), [(0, 0), (0, 1), (0, 2), (0, 3)]);
+        assert_eq!(locations("\ta\tb", 4), [(0, 0), (0, 4), (0, 5), (0, 8)]);
+        assert_eq!(
+            locations("abcd\t\t", 4),
+            [(0, 0), (0, 1), (0, 2), (0, 3), (0, 4), (0, 8)]
+        );
+        assert_eq!(
+            locations("a\t\r\n\tb", 8),
+            [(0, 0), (0, 1), (0, 8), (1, 0), (1, 8)]
+        );
+
+        let mut graphemes = Graphemes::new(Cursor::new("x\r\ny\tz"), true);
+        graphemes.set_tab_width(4);
+        let peeked: Vec<_> = std::iter::from_fn(|| graphemes.peek().map(Result::unwrap))
+            .map(|grapheme| grapheme.location().clone())
+            .collect();
+        graphemes.reset_peek();
+        let read: Vec<_> = graphemes
+            .by_ref()
+            .map(|grapheme| grapheme.unwrap().location().clone())
+            .collect();
+        assert_eq!(peeked, read);
+        assert_eq!(read[3], GraphemeLocation::new(3, 1, 1));
+        assert_eq!(read[4], GraphemeLocation::new(4, 1, 4));
+        assert_eq!(graphemes.position(), GraphemeLocation::new(5, 1, 5));
+        assert_eq!(graphemes.lines(), 2);
+
+        let mut graphemes = Graphemes::new(Cursor::new("ab"), true);
+        graphemes.next();
+        assert_eq!(graphemes.peek().unwrap().unwrap().location().offset, 1);
+        graphemes.reset_peek();
+        assert_eq!(graphemes.next().unwrap().unwrap().location().offset, 1);
+
+        let source = SourceBuffer::from_text("a\rb\r\nc");
+        assert_eq!(source.lines(), 3);
+        assert_eq!(source.location_of(2), Some((1, 0)));
+    }
+
+    fn graphemes_of(input: &[u8], is_lossy: bool) -> Vec<Result<String, usize>> {
+        Graphemes::new(ByteByByte(input), is_lossy)
+            .map(|result| {
+                result
+                    .map(|grapheme| grapheme.into_text().into_string())
+                    .map_err(|(index, _)| index)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_grapheme_clusters() {
+        let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+        let flags = "\u{1F1FA}\u{1F1F8}\u{1F1EC}\u{1F1E7}\u{1F1EB}";
+        let input = format!("{}{}e\u{301}\u{302}\r\nx", family, flags);
+
+        let graphemes: Vec<String> = graphemes_of(input.as_bytes(), false)
+            .into_iter()
+            .map(Result::unwrap)
+            .collect();
+
+        assert_eq!(
+            graphemes,
+            [
+                family,
+                "\u{1F1FA}\u{1F1F8}",
+                "\u{1F1EC}\u{1F1E7}",
+                "\u{1F1EB}",
+                "e\u{301}\u{302}",
+                "\r\n",
+                "x"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_grapheme_clusters_with_invalid_bytes() {
+        let mut input = "\u{1F468}\u{200D}\u{1F469}".as_bytes().to_vec();
+        input.push(0xFF);
+        input.extend_from_slice("\u{1F1FA}".as_bytes());
+
+        assert_eq!(
+            graphemes_of(&input, false),
+            [
+                Ok("\u{1F468}\u{200D}\u{1F469}".to_string()),
+                Err(1),
+                Ok("\u{1F1FA}".to_string())
+            ]
+        );
+        assert_eq!(
+            graphemes_of(&input, true),
+            [
+                Ok("\u{1F468}\u{200D}\u{1F469}".to_string()),
+                Ok("\u{FFFD}".to_string()),
+                Ok("\u{1F1FA}".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_try_peek() {
+        let mut graphemes = Graphemes::new(ByteByByte(b"a\xFFb"), false);
+
+        assert_eq!(graphemes.try_peek().unwrap().unwrap(), "a");
+        graphemes.next();
+        let error = graphemes.try_peek().unwrap_err();
+        assert!(matches!(error, LexError::OtherIndexed(1, _)));
+
+        // The failed read is still returned by next, as the peek cursor was reset.
+        assert!(matches!(graphemes.next(), Some(Err((1, _)))));
+        assert_eq!(graphemes.try_peek().unwrap().unwrap(), "b");
+        graphemes.next();
+        assert!(graphemes.try_peek().unwrap().is_none());
+    }
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Depth(usize);
+
+    impl super::TokenValue for Depth {}
+
+    struct Parenthesis;
+
+    impl Tokenizer<Depth, usize> for Parenthesis {
+        fn can_tokenize(
+            &mut self,
+            _: &LexContext<Depth>,
+            grapheme: &str,
+            _: &super::stream::GraphemeLocation,
+            _: &Option<SmallString>,
+            _: &mut usize,
+        ) -> bool {
+            matches!(grapheme, "(" | ")")
+        }
+
+        fn lex<'a, 'b>(
+            &'b mut self,
+            _: &'b mut Vec<super::Token<Depth>>,
+            grapheme: &str,
+            _: &super::stream::GraphemeLocation,
+            _: &'b mut super::stream::Graphemes<'a>,
+            depth: &mut usize,
+        ) -> Result<Depth, LexError> {
+            if grapheme == "(" {
+                *depth += 1;
+                Ok(Depth(*depth))
+            } else {
+                let token = Depth(*depth);
+                *depth = depth
+                    .checked_sub(1)
+                    .ok_or_else(|| LexError::other("Unbalanced parenthesis"))?;
+                Ok(token)
+            }
+        }
+    }
+
+    #[test]
+    fn test_context() {
+        let mut lexer =
+            Lexer::with_context(Cursor::new("(()())"), true, None, 0).tokenizer(|| Parenthesis);
+        lexer.tokenize().unwrap();
+
+        crate::assert_tokens!(
+            &lexer,
+            [Depth(1), Depth(2), Depth(2), Depth(2), Depth(2), Depth(1)]
+        );
+        assert_eq!(*lexer.context(), 0);
+    }
+
+    #[test]
+    fn test_from_chunks() {
+        let chunks = ["\"spl", "it\" \"e", "\u{301}\""];
+        let mut lexer = Lexer::from_chunks(chunks, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+        lexer.tokenize().unwrap();
+
+        let values: Vec<&str> = lexer
+            .iter()
+            .filter_map(|token| match token.token() {
+                Token::DoubleQuotedString(string) => Some(string.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(values, ["split", "e\u{301}"]);
+    }
+
+    #[test]
+    fn test_token_metadata() {
+        let mut token = super::Token::from(Token::Eof).with_metadata("origin");
+
+        assert_eq!(token.metadata::<&str>(), Some(&"origin"));
+        assert_eq!(token.metadata::<usize>(), None);
+
+        let shared = token.clone();
+        assert!(token.metadata_mut::<&str>().is_none());
+        drop(shared);
+
+        *token.metadata_mut::<&str>().unwrap() = "resolved";
+        assert_eq!(token.metadata::<&str>(), Some(&"resolved"));
+    }
+
+    #[test]
+    fn test_span_helpers() {
+        let first = Span::new(2, 4);
+        let second = Span::from(7..=9);
+
+        assert_eq!(first.merge(&second), Span::new(2, 9));
+        assert!(!first.intersects(&second));
+        assert!(first.merge(&second).intersects(&second));
+        assert!(first.contains(4) && !first.contains(5));
+        assert_eq!(second.len_graphemes(), 3);
+
+        let empty = Span::empty(5);
+        assert!(empty.is_empty() && !second.is_empty());
+        assert_eq!(
+            (empty.start(), empty.end(), empty.len_graphemes()),
+            (5, 5, 0)
+        );
+        assert_eq!(empty.grapheme_span(), 5..5);
+        assert!(!empty.contains(5) && !empty.intersects(&Span::new(4, 6)));
+        assert_eq!(first.merge(&empty), Span::new(2, 4));
+        assert_eq!(empty.merge(&second), Span::from(5..10));
+        assert_eq!(Span::from(3..3), Span::empty(3));
+        assert_eq!(empty.to_string(), "5..5");
+
+        let last = Span::new(usize::MAX - 1, usize::MAX);
+        assert_eq!(last.grapheme_span(), usize::MAX - 1..usize::MAX);
+        assert!(last.contains(usize::MAX - 1) && !last.contains(usize::MAX));
+
+        let mut lexer = Lexer::new(Cursor::new("\"\u{e9}\""), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .store_source(true);
+        lexer.tokenize().unwrap();
+        let span = lexer.tokens()[0].span().unwrap();
+        let (_, source) = lexer.into_parts();
+
+        assert_eq!(span.len_graphemes(), 3);
+        assert_eq!(span.len_bytes(&source), Some(4));
+        assert!(span.contains_byte(2, &source) && !span.contains_byte(4, &source));
+        assert_eq!(Span::empty(3).byte_range(&source), Some(4..4));
+        assert_eq!(span.grapheme_span(), 0..3);
+        assert_eq!(source.byte_span(span.grapheme_span()), Some(0..4));
+        assert_eq!(source.byte_span(1..1), Some(1..1));
+        assert_eq!(source.slice_span(1..2), Some("\u{e9}"));
+        assert_eq!(source.slice_span(3..3), Some(""));
+        assert_eq!(source.byte_span(2..4), None);
+    }
+
+    #[test]
+    fn test_source_ids() {
+        let mut registry = SourceRegistry::new();
+        let main = registry.register("main.txt");
+        let other = registry.register("other.txt");
+
+        let mut lexer = Lexer::new(Cursor::new("\"a\""), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .source(main);
+        lexer.tokenize().unwrap();
+
+        let span = lexer.tokens()[0].span().unwrap();
+        assert_eq!(
+            span.source().and_then(|id| registry.name(id)),
+            Some("main.txt")
+        );
+        assert!(!span.intersects(&span.clone().with_source(Some(other))));
+        assert_eq!(registry.find("other.txt"), Some(other));
+    }
+
+    #[test]
+    fn test_source_manager() {
+        let mut manager = SourceManager::new();
+        let first = manager.add_string("first.txt", "\"a\"");
+        let second = manager.add_string("second.txt", "\"x\"\n  \"\u{e9}t\u{e9}\"");
+
+        let mut lexer = manager
+            .lexer(second, None)
+            .unwrap()
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+        lexer.tokenize().unwrap();
+
+        let span = lexer.tokens()[1].span().unwrap();
+        let resolved = manager.resolve(&span).unwrap();
+
+        assert_eq!(resolved.name, "second.txt");
+        assert_eq!((resolved.line, resolved.column), (1, 2));
+        assert_eq!(resolved.snippet, "\"\u{e9}t\u{e9}\"");
+        assert_eq!(resolved.line_text, "  \"\u{e9}t\u{e9}\"");
+        assert!(manager.resolve(&Span::new(0, 2)).is_none());
+        assert_eq!(manager.source(first).unwrap().text(), "\"a\"");
+    }
+
+    struct Include(SourceId);
+
+    impl Tokenizer<Token> for Include {
+        fn can_tokenize(
+            &mut self,
+            _: &LexContext<Token>,
+            grapheme: &str,
+            _: &super::stream::GraphemeLocation,
+            _: &Option<SmallString>,
+            _: &mut (),
+        ) -> bool {
+            grapheme == "@"
+        }
+
+        fn lex<'a, 'b>(
+            &'b mut self,
+            _: &'b mut Vec<super::Token<Token>>,
+            _: &str,
+            _: &super::stream::GraphemeLocation,
+            incoming: &'b mut super::stream::Graphemes<'a>,
+            _: &mut (),
+        ) -> Result<Token, LexError> {
+            incoming.include(Graphemes::from_chunks(["\"inner\""]), Some(self.0));
+            Ok(Token::Whitespace)
+        }
+    }
+
+    #[test]
+    fn test_include() {
+        let mut registry = SourceRegistry::new();
+        let outer = registry.register("outer");
+        let inner = registry.register("inner");
+
+        let mut lexer = Lexer::new(Cursor::new("\"a\" @ \"b\""), true, Some(Token::Eof))
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace)
+            .tokenizer(move || Include(inner))
+            .source(outer);
+        lexer.tokenize().unwrap();
+
+        let tokens: Vec<(String, Option<SourceId>, Option<RangeInclusive<usize>>)> = lexer
+            .iter()
+            .map(|token| {
+                (
+                    token.token().to_string(),
+                    token.source(),
+                    token.range().cloned(),
+                )
+            })
+            .collect();
+
+        assert_eq!(
+            tokens,
+            [
+                (
+                    "Quoted String := \"a\"".to_string(),
+                    Some(outer),
+                    Some(0..=2)
+                ),
+                (
+                    "Quoted String := \"inner\"".to_string(),
+                    Some(inner),
+                    Some(0..=6)
+                ),
+                (
+                    "Quoted String := \"b\"".to_string(),
+                    Some(outer),
+                    Some(6..=8)
+                ),
+                ("EOF".to_string(), Some(outer), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_convenience_constructors() {
+        let mut lexer = Lexer::of_string("\"a\" \"b\"")
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+        lexer.tokenize().unwrap();
+        assert_eq!(lexer.tokens().len(), 3);
+        assert!(matches!(lexer.tokens()[2].token(), Token::Eof));
+        let (_, source) = lexer.into_parts();
+        assert_eq!(source.text(), "\"a\" \"b\"");
+
+        let mut lexer: Lexer<Token> =
+            Lexer::lossy(Cursor::new(b"\"\xff\"".to_vec())).tokenizer(DoubleQuotedStringLexer::new);
+        lexer.tokenize().unwrap();
+        assert!(matches!(
+            lexer.tokens()[0].token(),
+            Token::DoubleQuotedString(string) if string == "\u{FFFD}"
+        ));
+        assert_eq!(lexer.into_parts().1.text(), "\"\u{FFFD}\"");
+
+        let mut lexer: Lexer<Token> = Lexer::strict(Cursor::new(b"\"\xff\"".to_vec()))
+            .tokenizer(DoubleQuotedStringLexer::new);
+        assert!(lexer.tokenize().is_err());
+    }
+
+    #[test]
+    fn test_eof_span() {
+        let mut registry = SourceRegistry::new();
+        let id = registry.register("input");
+        let mut lexer = Lexer::new(Cursor::new("\"a\"\n\"b\" "), true, Some(Token::Eof))
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace)
+            .source(id);
+        lexer.tokenize().unwrap();
+
+        let eof = lexer.tokens().last().unwrap();
+        assert!(eof.is_zero_width());
+        assert_eq!(eof.range(), None);
+        assert_eq!(eof.grapheme_span(), Some(8..8));
+        assert_eq!(eof.span(), Some(Span::empty(8).with_source(Some(id))));
+        assert_eq!(eof.to_string(), "Eof @ 8..8 in #0");
+
+        let mut lexer = Lexer::new(Cursor::new(""), true, Some(Token::Eof));
+        lexer.tokenize().unwrap();
+        assert_eq!(lexer.tokens()[0].span(), Some(Span::empty(0)));
+    }
+
+    #[test]
+    fn test_span_remap() {
+        let mut registry = SourceRegistry::new();
+        let markdown = registry.register("README.md");
+        let snippet = registry.register("README.md code block");
+
+        let block = Span::new(40, 60).with_source(Some(markdown));
+        let local = Span::new(3, 5).with_source(Some(snippet));
+        let remapped = local.remap(&block);
+
+        assert_eq!((remapped.start(), remapped.end()), (43, 45));
+        assert_eq!(remapped.source(), Some(markdown));
+        assert_eq!(remapped.origin(), Some(&local));
+        assert_eq!(remapped.origins().count(), 1);
+    }
+
+    #[test]
+    fn test_repl_session() {
+        let mut session = ReplSession::new(None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+
+        assert!(matches!(
+            session.feed("\"first"),
+            ReplStatus::NeedsMoreInput
+        ));
+        assert!(session.is_continuing());
+
+        match session.feed("second\" \"done\"") {
+            ReplStatus::Complete(tokens) => assert_eq!(tokens.len(), 2),
+            status => panic!("Expected complete tokens, found {:?}", status),
+        }
+        assert!(!session.is_continuing());
+
+        assert!(matches!(session.feed("\"\\q\""), ReplStatus::Error(_)));
+        assert_eq!(session.buffer(), "");
+
+        // Only the unfinished token is lexed again, yet the ranges match lexing all of the input.
+        let lexed = std::cell::Cell::new(0);
+        let mut session = ReplSession::new(None)
+            .tokenizer(|| {
+                lexed.set(lexed.get() + 1);
+                DoubleQuotedStringLexer::new()
+            })
+            .tokenizer(|| Whitespace);
+        assert!(matches!(
+            session.feed("\"a\" \"b"),
+            ReplStatus::NeedsMoreInput
+        ));
+        let consulted = lexed.replace(0);
+        assert!(matches!(session.feed("c"), ReplStatus::NeedsMoreInput));
+        assert!(lexed.replace(0) < consulted);
+        let ranges: Vec<_> = match session.feed("d\"") {
+            ReplStatus::Complete(tokens) => tokens
+                .iter()
+                .map(|token| token.range_raw().clone())
+                .collect(),
+            status => panic!("Expected complete tokens, found {:?}", status),
+        };
+        assert_eq!(ranges, [0..=2, 4..=10]);
+
+        // An error is located within all of the accumulated input.
+        assert!(matches!(
+            session.feed("\"a\" \"b"),
+            ReplStatus::NeedsMoreInput
+        ));
+        match session.feed("\\q\"") {
+            ReplStatus::Error(error) => {
+                assert_eq!(error.location().map(|location| location.index), Some(4))
+            }
+            status => panic!("Expected an error, found {:?}", status),
+        }
+    }
+
+    #[test]
+    fn test_unterminated_token() {
+        let mut lexer = CsvDialect::csv().lexer(Cursor::new("\"b"));
+
+        let error = lexer.tokenize().unwrap_err();
+        assert!(error.is_incomplete());
+        assert!(!error.is_unexpected_end());
+        assert!(matches!(
+            error.inner(),
+            LexError::UnterminatedToken { start_span, .. } if start_span.start() == 0
+        ));
+
+        let mut lexer = Lexer::new(Cursor::new("\"a\" \"b"), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+
+        let error = lexer.tokenize().unwrap_err();
+        assert!(error.is_incomplete());
+        assert!(error.is_unexpected_end());
+    }
+
+    #[test]
+    fn test_progress() {
+        use std::{cell::RefCell, rc::Rc};
+
+        let reports = Rc::new(RefCell::new(vec![]));
+        let recorded = reports.clone();
+        let mut lexer = Lexer::new(Cursor::new("\"abc\"\n\"def\"\n\"ghi\""), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace)
+            .on_progress(6, move |progress| recorded.borrow_mut().push(progress));
+
+        lexer.tokenize().unwrap();
+
+        let reports = reports.borrow();
+        assert_eq!(
+            reports.first(),
+            Some(&Progress {
+                bytes: 6,
+                tokens: 1,
+                line: 1
+            })
+        );
+        assert_eq!(
+            reports.last(),
+            Some(&Progress {
+                bytes: 17,
+                tokens: 3,
+                line: 2
+            })
+        );
+        assert_eq!(lexer.progress(), *reports.last().unwrap());
+
+        // Invalid bytes are counted once, rather than as the U+FFFD each is replaced with.
+        let mut input = b"\"ab".to_vec();
+        input.extend([0xAD; 10]);
+        input.extend("\u{FFFD}\"".as_bytes());
+        let mut lexer =
+            Lexer::new(Cursor::new(input), true, None).tokenizer(DoubleQuotedStringLexer::new);
+        lexer.tokenize().unwrap();
+        assert_eq!(lexer.progress().bytes, 17);
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let cancel_flag = CancelFlag::new();
+        let mut lexer = Lexer::new(Cursor::new("\"a\" \"b\""), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+
+        assert!(lexer.next_token().unwrap().is_some());
+        cancel_flag.cancel();
+        assert!(lexer
+            .tokenize_cancellable(&cancel_flag)
+            .unwrap_err()
+            .is_cancelled());
+        assert_eq!(lexer.tokens().len(), 1);
+        assert_eq!(lexer.graphemes(), 3);
+
+        cancel_flag.reset();
+        lexer.tokenize_cancellable(&cancel_flag).unwrap();
+        assert_eq!(lexer.tokens().len(), 2);
+    }
+
+    #[test]
+    fn test_stats() {
+        let mut lexer = Lexer::new(Cursor::new("\"a\" \"bc\" \"\""), true, Some(Token::Eof))
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+        lexer.tokenize().unwrap();
+
+        let stats = lexer.stats();
+        assert_eq!(stats.count(&Token::double_quoted_string("")), 3);
+        assert_eq!(stats.count(&Token::Whitespace), 2);
+        assert_eq!(stats.count(&Token::Eof), 0);
+        assert_eq!((stats.significant(), stats.trivia()), (3, 2));
+        assert_eq!(
+            stats
+                .tokenizers()
+                .iter()
+                .find(|(name, _)| name.ends_with("Whitespace"))
+                .map(|(_, count)| *count),
+            Some(2)
+        );
+        assert_eq!(
+            stats.byte_histogram().iter().collect::<Vec<_>>(),
+            [(&1, &2), (&2, &1), (&3, &1), (&4, &1)]
+        );
+    }
+
+    #[cfg(feature = "profiling")]
+    #[test]
+    fn test_profile_report() {
+        let mut lexer = Lexer::new(Cursor::new("\"a\" \"b\""), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+        lexer.tokenize().unwrap();
+
+        let profile = lexer.profile_report();
+        assert_eq!(profile.tokenizer_lexes, 3);
+        assert_eq!(profile.tokenizer_checks, 4);
+        assert!(profile.peeks > 0);
+        assert_eq!(profile.heap_graphemes, 0);
+    }
+
+    #[test]
+    fn test_skip_if() {
+        let lexer = |skip_if: fn(&Token) -> bool| {
+            let mut lexer = Lexer::new(Cursor::new("\"a\" \"\""), true, None)
+                .tokenizer(DoubleQuotedStringLexer::new)
+                .tokenizer(|| Whitespace)
+                .skip_if(skip_if);
+            lexer.tokenize().unwrap();
+            lexer
+        };
+
+        let with_trivia = lexer(|_| false);
+        assert_eq!(with_trivia.tokens().len(), 3);
+        assert!(matches!(with_trivia.tokens()[1].token(), Token::Whitespace));
+        assert_eq!(with_trivia.stats().trivia(), 0);
+
+        let without_strings = lexer(|token| {
+            token.should_skip()
+                || matches!(token, Token::DoubleQuotedString(string) if string.is_empty())
+        });
+        assert_eq!(without_strings.tokens().len(), 1);
+        assert_eq!(without_strings.stats().significant(), 1);
+    }
+
+    #[test]
+    fn test_keep_skipped() {
+        let mut lexer = Lexer::new(Cursor::new("\"a\" \"b\" "), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace)
+            .keep_skipped(true);
+        lexer.tokenize().unwrap();
+
+        assert_eq!(lexer.tokens().len(), 2);
+        let ranges: Vec<_> = lexer
+            .skipped_tokens()
+            .iter()
+            .map(|token| token.range())
+            .collect();
+        assert_eq!(ranges, [Some(&(3..=3)), Some(&(7..=7))]);
+    }
+
+    #[test]
+    fn test_doc_comments() {
+        #[derive(Debug, Clone)]
+        enum Item {
+            Comment(&'static str),
+            Word,
+            Eof,
+        }
+
+        impl TokenValue for Item {}
+
+        let tokens = [
+            super::Token::new(Item::Word, Some(0..=2)),
+            super::Token::new(Item::Word, Some(30..=33)),
+            super::Token::new(Item::Word, Some(50..=52)),
+            super::Token::from(Item::Eof),
+        ];
+        let skipped = [
+            super::Token::new(Item::Comment("/// First line\n"), Some(4..=18)),
+            super::Token::new(Item::Comment("///second\n"), Some(19..=28)),
+            super::Token::new(Item::Comment("// plain"), Some(34..=41)),
+            super::Token::new(Item::Comment("/** Block */"), Some(42..=49)),
+            super::Token::new(Item::Comment("/// dangling"), Some(53..=64)),
+        ];
+
+        let docs = DocComments::rust().extract(&tokens, &skipped, |token| match token {
+            Item::Comment(text) => Some(*text),
+            _ => None,
+        });
+        assert_eq!(
+            docs.into_iter().collect::<Vec<_>>(),
+            [
+                (1, "First line\nsecond".to_string()),
+                (2, "Block".to_string())
+            ]
+        );
+        assert_eq!(DocComments::new().line("#:").strip("#: note"), Some("note"));
+    }
+
+    #[test]
+    fn test_token_sink() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut lexer = Lexer::new(Cursor::new("\"a\" \"b\" \"c\""), true, Some(Token::Eof))
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace)
+            .sink(sender, 2);
+
+        lexer.next_token().unwrap();
+        lexer.next_token().unwrap();
+        lexer.next_token().unwrap();
+        assert_eq!(lexer.tokens().len(), 2);
+        assert_eq!(receiver.try_iter().count(), 1);
+
+        lexer.tokenize().unwrap();
+        assert!(lexer.tokens().is_empty());
+        let rest: Vec<_> = receiver.try_iter().map(|token| token.to_string()).collect();
+        assert_eq!(rest.len(), 3);
+        assert!(rest[2].contains("Eof"));
+    }
+
+    #[test]
+    fn test_dfa_tokenizer() {
+        // Numbers with an optional fraction, where a trailing dot is not part of the number.
+        let number = DfaTokenizer::new(|text| Token::double_quoted_string(text))
+            .class(|c| c.is_ascii_digit())
+            .class(|c| c == '.')
+            .transition(0, 0, 1)
+            .transition(1, 0, 1)
+            .transition(1, 1, 2)
+            .transition(2, 0, 3)
+            .transition(3, 0, 3)
+            .accept(1)
+            .accept(3);
+        let words = DfaTokenizer::leading(char::is_alphabetic, char::is_alphanumeric, |text| {
+            Token::double_quoted_string(text)
+        });
+
+        let mut lexer = Lexer::new(Cursor::new("12.5 x2 7. 3"), true, None)
+            .tokenizer(move || number.clone())
+            .tokenizer(move || words.clone())
+            .tokenizer(|| {
+                DfaTokenizer::one_or_more(|c| c == '.' || c == ' ', |_| Token::Whitespace)
+            });
+        lexer.tokenize().unwrap();
+
+        let lexed: Vec<_> = lexer
+            .tokens()
+            .iter()
+            .map(|token| match token.token() {
+                Token::DoubleQuotedString(text) => (text.clone(), token.range().cloned()),
+                token => panic!("Unexpected {:?}", token),
+            })
+            .collect();
+        assert_eq!(
+            lexed,
+            [
+                ("12.5".to_string(), Some(0..=3)),
+                ("x2".to_string(), Some(5..=6)),
+                ("7".to_string(), Some(8..=8)),
+                ("3".to_string(), Some(11..=11)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_literal_set_tokenizer() {
+        let operators = LiteralSetTokenizer::new(["==", "=", "=>", "->", "-", "==="], |literal| {
+            Token::double_quoted_string(literal)
+        });
+        assert_eq!(operators.literals().len(), 6);
+
+        let mut lexer = Lexer::new(Cursor::new("====>= -> -=="), true, None)
+            .tokenizer(move || operators.clone())
+            .tokenizer(|| Whitespace);
+        lexer.tokenize().unwrap();
+
+        let lexed: Vec<_> = lexer
+            .tokens()
+            .iter()
+            .map(|token| match token.token() {
+                Token::DoubleQuotedString(literal) => literal.clone(),
+                token => panic!("Unexpected {:?}", token),
+            })
+            .collect();
+        assert_eq!(lexed, ["===", "=>", "=", "->", "-", "=="].map(String::from));
+    }
+
+    #[test]
+    fn test_display_formats() {
+        let mut token = super::Token::new(Token::Whitespace, Some(3..=5));
+        assert_eq!(token.to_string(), "Whitespace @ 3..=5");
+
+        let mut registry = SourceRegistry::new();
+        token.set_source(Some(registry.register("main.txt")));
+        token.set_metadata(1u8);
+        assert_eq!(token.to_string(), "Whitespace @ 3..=5 in #0");
+        assert_eq!(
+            format!("{:#}", token),
+            "Token\n    value: Whitespace\n    graphemes: 3..=5\n    source: #0\n    metadata: present"
+        );
+
+        let span = Span::new(1, 2).remap(&token.span().unwrap());
+        assert_eq!(
+            format!("{:#}", span),
+            "Span\n    graphemes: 4..=5\n    source: #0\n    from: 1..=2"
+        );
+
+        let mut lexer = Lexer::new(Cursor::new("\"a\"\n  \"bc\""), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace)
+            .store_source(true);
+        lexer.tokenize().unwrap();
+        let (tokens, source) = lexer.into_parts();
+        let displayed: Vec<String> = tokens
+            .iter()
+            .map(|token| token.display_in(&source).to_string())
+            .collect();
+        assert_eq!(
+            displayed,
+            [
+                "DoubleQuotedString(\"a\") @ 0:0..0:3",
+                "DoubleQuotedString(\"bc\") @ 1:2..1:6"
+            ]
+        );
+        assert_eq!(
+            token.display_in(&source).to_string(),
+            "Whitespace @ 0:3..1:2 in #0"
+        );
+        let outside = super::Token::new(Token::Whitespace, Some(20..=21));
+        assert_eq!(
+            outside.display_in(&source).to_string(),
+            "Whitespace @ 20..=21"
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let input = "\"abc\" \"de\" \"f\"";
+        let lexer = || {
+            Lexer::new(Cursor::new(input), true, None)
+                .tokenizer(DoubleQuotedStringLexer::new)
+                .tokenizer(|| Whitespace)
+        };
+        let exceeded = |mut lexer: Lexer<'static, Token>, limits| match lexer
+            .tokenize_with_limit(limits)
+            .unwrap_err()
+        {
+            LexError::Located(_, error) => match *error {
+                LexError::LimitExceeded { limit, max } => (limit, max),
+                error => panic!("Expected an exceeded limit, found {:?}", error),
+            },
+            error => panic!("Expected a located error, found {:?}", error),
+        };
+
+        assert_eq!(
+            exceeded(lexer(), LexLimits::new().max_tokens(2)),
+            (Limit::Tokens, 2)
+        );
+        assert_eq!(
+            exceeded(lexer(), LexLimits::new().max_bytes(10)),
+            (Limit::Bytes, 10)
+        );
+        assert_eq!(
+            exceeded(lexer(), LexLimits::new().max_token_length(4)),
+            (Limit::TokenLength, 4)
+        );
+
+        let mut lexer = lexer();
+        lexer
+            .tokenize_with_limit(
+                LexLimits::new()
+                    .max_tokens(3)
+                    .max_bytes(15)
+                    .max_token_length(5),
+            )
+            .unwrap();
+        assert_eq!(lexer.tokens().len(), 3);
+
+        let mut input = b"\"".to_vec();
+        input.extend([0xAD; 12]);
+        input.push(b'"');
+        let lexer = || {
+            Lexer::new(Cursor::new(input.clone()), true, None)
+                .tokenizer(DoubleQuotedStringLexer::new)
+        };
+        assert!(lexer()
+            .tokenize_with_limit(LexLimits::new().max_bytes(14))
+            .is_ok());
+        assert_eq!(
+            exceeded(lexer(), LexLimits::new().max_bytes(13)),
+            (Limit::Bytes, 13)
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let mut lexer = Lexer::new(Cursor::new("\"a\" ?"), true, None)
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+
+        let (explanations, result) = lexer.explain();
+        assert!(result.is_err());
+
+        let summary: Vec<_> = explanations
+            .iter()
+            .map(|explanation| {
+                (
+                    explanation.consulted.len(),
+                    explanation
+                        .matched
+                        .as_deref()
+                        .map(|name| name.rsplit("::").next().unwrap()),
+                    explanation.range.clone(),
+                    explanation.skipped,
+                    explanation.failed,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, Some("DoubleQuotedStringLexer"), 0..=2, false, false),
+                (2, Some("Whitespace"), 3..=3, true, false),
+                (2, None, 4..=4, false, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_case_insensitive_literals() {
+        let keywords = LiteralSetTokenizer::new(
+            ["SELECT", "FROM", "ÉTÉ", "straße", "ΣΟΦΟΣ"],
+            |literal| Token::double_quoted_string(literal),
+        );
+        let sensitive = keywords.clone();
+        let keywords = keywords.case_insensitive();
+        assert!(keywords.is_case_insensitive());
+        assert!(!sensitive.is_case_insensitive());
+
+        let input = "select FrOm été STRASSE σοφος";
+        let mut lexer = Lexer::new(Cursor::new(input), true, None)
+            .tokenizer(move || keywords.clone())
+            .tokenizer(|| Whitespace);
+        lexer.tokenize().unwrap();
+
+        let lexed: Vec<_> = lexer
+            .tokens()
+            .iter()
+            .map(|token| match token.token() {
+                Token::DoubleQuotedString(literal) => literal.clone(),
+                token => panic!("Unexpected {:?}", token),
+            })
+            .collect();
+        assert_eq!(
+            lexed,
+            ["SELECT", "FROM", "ÉTÉ", "straße", "ΣΟΦΟΣ"].map(String::from)
+        );
+    }
+
+    #[test]
+    fn test_prelude() {
+        let lexer = |input: &'static str| {
+            Lexer::new(Cursor::new(input), true, None)
+                .skip_bom()
+                .shebang(|line| Some(Token::double_quoted_string(line)))
+                .tokenizer(DoubleQuotedStringLexer::new)
+                .tokenizer(|| Whitespace)
+        };
+
+        let mut with_shebang = lexer("\u{FEFF}#!/bin/env x\n\"a\"");
+        with_shebang.tokenize().unwrap();
+        let tokens = with_shebang.tokens();
+        assert_eq!(tokens.len(), 2);
+        assert!(
+            matches!(tokens[0].token(), Token::DoubleQuotedString(line) if line == "#!/bin/env x")
+        );
+        assert_eq!(tokens[0].range(), Some(&(1..=12)));
+
+        let mut without_shebang = lexer("\"#!\"");
+        without_shebang.tokenize().unwrap();
+        assert_eq!(without_shebang.tokens().len(), 1);
+
+        let mut skipped = Lexer::new(Cursor::new("#!x"), true, None).shebang(|_| None::<Token>);
+        skipped.tokenize().unwrap();
+        assert!(skipped.tokens().is_empty());
+
+        // The shebang token is stored like any other token.
+        let mut explained = lexer("#!x\n\"a\"");
+        let (explanations, result) = explained.explain();
+        result.unwrap();
+        assert_eq!(explanations[0].matched.as_deref(), Some("Shebang"));
+        assert_eq!(explanations[0].range, 0..=2);
+        assert_eq!(explained.stats().tokenizers().get("Shebang"), Some(&1));
+
+        let mut filtered = lexer("#!x\n\"a\"")
+            .skip_if(|token| matches!(token, Token::DoubleQuotedString(line) if line == "#!x"))
+            .keep_skipped(true);
+        filtered.tokenize().unwrap();
+        assert_eq!(filtered.tokens().len(), 2);
+        assert_eq!(filtered.skipped_tokens().len(), 1);
+    }
+
+    #[test]
+    fn test_embedded_lexer() {
+        struct Text;
+
+        impl Tokenizer<Either<Token, Token>> for Text {
+            fn can_tokenize(
+                &mut self,
+                _: &LexContext<Either<Token, Token>>,
+                grapheme: &str,
+                _: &GraphemeLocation,
+                _: &Option<SmallString>,
+                _: &mut (),
+            ) -> bool {
+                grapheme != "{"
+            }
+
+            fn lex<'a, 'b>(
+                &'b mut self,
+                _: &'b mut Vec<super::Token<Either<Token, Token>>>,
+                grapheme: &str,
+                _: &GraphemeLocation,
+                incoming: &'b mut Graphemes<'a>,
+                _: &mut (),
+            ) -> Result<Either<Token, Token>, LexError> {
+                let mut text = grapheme.to_string();
+                while let Some(Ok(next)) = incoming.peek() {
+                    if next == "{" {
+                        break;
+                    }
+                    text.push_str(&next);
+                    incoming.reset_peek();
+                    incoming.next();
+                }
+                Ok(Either::Left(Token::double_quoted_string(text)))
+            }
+        }
+
+        fn inner(incoming: Graphemes) -> Lexer<Token> {
+            Lexer::with_graphemes(incoming, None, ())
+                .tokenizer(DoubleQuotedStringLexer::new)
+                .tokenizer(|| Whitespace)
+        }
+
+        let embedded = EmbeddedTokenizer::new(
+            ("{{", Token::double_quoted_string("{{")),
+            ("}}", Token::double_quoted_string("}}")),
+            inner,
+        );
+        let mut lexer = Lexer::new(Cursor::new("ab{{\"x\" \"y\"}}c"), true, None)
+            .tokenizer(move || embedded.clone())
+            .tokenizer(|| Text);
+        lexer.tokenize().unwrap();
+        assert_eq!(lexer.validate_spans(false), Ok(()));
+
+        let lexed: Vec<_> = lexer
+            .tokens()
+            .iter()
+            .map(|token| {
+                let (side, value) = match token.token() {
+                    Either::Left(value) => ("outer", value),
+                    Either::Right(value) => ("inner", value),
+                };
+                match value {
+                    Token::DoubleQuotedString(text) => (side, text.clone(), token.range().cloned()),
+                    value => panic!("Unexpected {:?}", value),
+                }
+            })
+            .collect();
+        assert_eq!(
+            lexed,
+            [
+                ("outer", "ab".to_string(), Some(0..=1)),
+                ("outer", "{{".to_string(), Some(2..=3)),
+                ("inner", "x".to_string(), Some(4..=6)),
+                ("inner", "y".to_string(), Some(8..=10)),
+                ("outer", "}}".to_string(), Some(11..=12)),
+                ("outer", "c".to_string(), Some(13..=13)),
+            ]
+        );
+
+        let unterminated = EmbeddedTokenizer::new(("{{", Token::Eof), ("}}", Token::Eof), inner);
+        let mut lexer =
+            Lexer::new(Cursor::new("{{\"x\""), true, None).tokenizer(move || unterminated.clone());
+        assert!(lexer.tokenize().unwrap_err().is_incomplete());
+    }
+
+    #[test]
+    fn test_popping_tokenizer() {
+        struct Pop;
+
+        impl Tokenizer<Token> for Pop {
+            fn can_tokenize(
+                &mut self,
+                _: &LexContext<Token>,
+                grapheme: &str,
+                _: &GraphemeLocation,
+                _: &Option<SmallString>,
+                _: &mut (),
+            ) -> bool {
+                grapheme == "+"
+            }
+
+            fn lex<'a, 'b>(
+                &'b mut self,
+                tokens: &'b mut Vec<super::Token<Token>>,
+                _: &str,
+                _: &GraphemeLocation,
+                _: &'b mut Graphemes<'a>,
+                _: &mut (),
+            ) -> Result<Token, LexError> {
+                tokens.pop();
+                Ok(Token::double_quoted_string("+"))
+            }
+        }
+
+        let mut lexer = Lexer::new(Cursor::new("\"a\" +"), true, Some(Token::Eof))
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace)
+            .tokenizer(|| Pop);
+        lexer.tokenize().unwrap();
+
+        let tokens = lexer.tokens();
+        assert_eq!(tokens.len(), 2);
+        assert!(matches!(tokens[0].token(), Token::DoubleQuotedString(string) if string == "+"));
+        assert_eq!(tokens[0].range(), Some(&(4..=4)));
+    }
+
+    #[test]
+    fn test_map_tokens() {
+        #[derive(Debug, Clone, PartialEq)]
+        enum Host {
+            Embedded(String),
+            Eof,
+        }
+
+        impl TokenValue for Host {}
+
+        impl From<Token> for Host {
+            fn from(token: Token) -> Self {
+                match token {
+                    Token::Eof => Host::Eof,
+                    token => Host::Embedded(token.to_string()),
+                }
+            }
+        }
+
+        let lexer = Lexer::new(Cursor::new("\"a\" \"b\""), true, Some(Token::Eof))
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace);
+        let tokens: Vec<super::Token<Host>> = lexer.map_tokens().collect::<Result<_, _>>().unwrap();
+
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            tokens[1].token(),
+            &Host::Embedded("Quoted String := \"b\"".into())
+        );
+        assert_eq!(tokens[1].range(), Some(&(4..=6)));
+        assert_eq!(tokens[2].token(), &Host::Eof);
+
+        let converted: Vec<super::Token<Host>> = tokens.clone().into_iter().map_tokens().collect();
+        assert_eq!(converted, tokens);
+    }
+
+    #[test]
+    fn test_byte_lexer() {
+        #[derive(Debug, Clone, PartialEq)]
+        enum Chunk {
+            Magic,
+            Text(String),
+            Eof,
+        }
+
+        impl TokenValue for Chunk {}
+
+        struct Magic;
+
+        impl ByteTokenizer<Chunk> for Magic {
+            fn can_tokenize(
+                &mut self,
+                _: &LexContext<Chunk>,
+                byte: u8,
+                _: usize,
+                next: Option<u8>,
+                _: &mut (),
+            ) -> bool {
+                byte == 0x89 && next == Some(b'P')
+            }
+
+            fn lex<'a, 'b>(
+                &'b mut self,
+                _: &'b mut Vec<super::Token<Chunk>>,
+                _: u8,
+                _: usize,
+                incoming: &'b mut Bytes<'a>,
+                _: &mut (),
+            ) -> Result<Chunk, LexError> {
+                incoming.take_bytes(2)?;
+                Ok(Chunk::Magic)
+            }
+        }
+
+        struct LengthPrefixed;
+
+        impl ByteTokenizer<Chunk> for LengthPrefixed {
+            fn can_tokenize(
+                &mut self,
+                _: &LexContext<Chunk>,
+                byte: u8,
+                _: usize,
+                _: Option<u8>,
+                _: &mut (),
+            ) -> bool {
+                byte < 0x20
+            }
+
+            fn lex<'a, 'b>(
+                &'b mut self,
+                _: &'b mut Vec<super::Token<Chunk>>,
+                byte: u8,
+                _: usize,
+                incoming: &'b mut Bytes<'a>,
+                _: &mut (),
+            ) -> Result<Chunk, LexError> {
+                let text = incoming.take_bytes(byte as usize)?;
+                Ok(Chunk::Text(String::from_utf8_lossy(&text).into_owned()))
+            }
+        }
+
+        let input: &[u8] = &[0x89, b'P', b'K', 2, b'h', 0xff, 3, b'a', b'b', b'c'];
+        let mut lexer = ByteLexer::new(input, Some(Chunk::Eof))
+            .tokenizer(|| Magic)
+            .tokenizer(|| LengthPrefixed);
+        lexer.tokenize().unwrap();
+
+        let tokens = lexer.take();
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[0].token(), &Chunk::Magic);
+        assert_eq!(tokens[0].range(), Some(&(0..=2)));
+        assert_eq!(tokens[1].token(), &Chunk::Text("h\u{fffd}".into()));
+        assert_eq!(tokens[1].range(), Some(&(3..=5)));
+        assert_eq!(tokens[2].range(), Some(&(6..=9)));
+        assert_eq!(tokens[3].token(), &Chunk::Eof);
+
+        let truncated: &[u8] = &[5, b'a'];
+        let error = ByteLexer::new(truncated, None)
+            .tokenizer(|| LengthPrefixed)
+            .tokenize()
+            .unwrap_err();
+        assert!(matches!(error.inner(), LexError::UnexpectedEndOfStream));
+
+        let unknown: &[u8] = &[0x7f];
+        assert!(ByteLexer::<Chunk>::new(unknown, None)
+            .tokenizer(|| LengthPrefixed)
+            .tokenize()
+            .is_err());
+
+        struct Pop;
+
+        impl ByteTokenizer<Chunk> for Pop {
+            fn can_tokenize(
+                &mut self,
+                _: &LexContext<Chunk>,
+                byte: u8,
+                _: usize,
+                _: Option<u8>,
+                _: &mut (),
+            ) -> bool {
+                byte == b'-'
+            }
+
+            fn lex<'a, 'b>(
+                &'b mut self,
+                tokens: &'b mut Vec<super::Token<Chunk>>,
+                _: u8,
+                _: usize,
+                _: &'b mut Bytes<'a>,
+                _: &mut (),
+            ) -> Result<Chunk, LexError> {
+                tokens.pop();
+                Ok(Chunk::Text("-".into()))
+            }
+        }
+
+        let popped: &[u8] = &[2, b'h', b'i', b'-'];
+        let mut lexer = ByteLexer::new(popped, None)
+            .tokenizer(|| LengthPrefixed)
+            .tokenizer(|| Pop);
+        lexer.tokenize().unwrap();
+        let tokens = lexer.take();
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].token(), &Chunk::Text("-".into()));
+        assert_eq!(tokens[0].range(), Some(&(3..=3)));
+    }
+
+    #[test]
+    fn test_hex_dump() {
+        let bytes: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+        let dump = HexDump::new(bytes, Span::new(4, 5)).row_width(8);
+        assert_eq!(
+            dump.to_string(),
+            concat!(
+                "00000000  89 50 4e 47 0d 0a 1a 0a  |.PNG....|\n",
+                "                      ^^ ^^             ^^\n",
+                "00000008  00 00 00 0d 49 48 44 52  |....IHDR|",
+            )
+        );
+
+        let dump = HexDump::new(bytes, Span::new(14, 20))
+            .row_width(4)
+            .context_rows(0);
+        assert_eq!(
+            dump.to_string(),
+            concat!(
+                "0000000c  49 48 44 52  |IHDR|\n",
+                "                ^^ ^^     ^^",
+            )
+        );
+    }
+
+    #[test]
+    fn test_lexer_factory() {
+        let factory = LexerFactory::new(true, Some(Token::Eof))
+            .tokenizer(DoubleQuotedStringLexer::new)
+            .tokenizer(|| Whitespace)
+            .limits(LexLimits::new().max_tokens(2));
+
+        for (input, expected) in [("\"a\"", 2), ("\"a\" \"b\"", 3)] {
+            let mut lexer = factory.lexer(Cursor::new(input));
+            lexer.tokenize().unwrap();
+            assert_eq!(lexer.tokens().len(), expected);
+        }
+
+        let mut lexer = factory.lexer(Cursor::new("\"a\" \"b\" \"c\""));
+        assert!(matches!(
+            lexer.tokenize().unwrap_err().inner(),
+            LexError::LimitExceeded { .. }
+        ));
+
+        // Tokenizers are created once for each lexer, rather than for each token.
+        let created = std::rc::Rc::new(std::cell::Cell::new(0));
+        let sunk = std::rc::Rc::new(std::cell::RefCell::new(vec![]));
+        let factory = {
+            let (created, sunk) = (created.clone(), sunk.clone());
+            LexerFactory::new(true, Some(Token::Eof))
+                .tokenizer(move || {
+                    created.set(created.get() + 1);
+                    DoubleQuotedStringLexer::new()
+                })
+                .tokenizer(|| Whitespace)
+                .shebang(|line| Some(Token::double_quoted_string(line)))
+                .sink(move |token| sunk.borrow_mut().push(token), 1)
+        };
+        for input in ["#!x\n\"a\"", "\"b\" \"c\""] {
+            factory.lexer(Cursor::new(input)).tokenize().unwrap();
+        }
+        assert_eq!(created.get(), 2);
+
+        let sunk: Vec<_> = sunk
+            .borrow()
+            .iter()
+            .map(|token: &super::Token<Token>| token.token().clone())
+            .collect();
+        assert!(matches!(
+            sunk.as_slice(),
+            [
+                Token::DoubleQuotedString(shebang),
+                Token::DoubleQuotedString(a),
+                Token::Eof,
+                Token::DoubleQuotedString(b),
+                Token::DoubleQuotedString(c),
+                Token::Eof,
+            ] if shebang == "#!x" && a == "a" && b == "b" && c == "c"
+        ));
+
+        // Whereas a lexer without a factory creates a tokenizer each time it is consulted.
+        created.set(0);
+        let counter = created.clone();
+        Lexer::new(Cursor::new("\"b\" \"c\""), true, Some(Token::Eof))
+            .tokenizer(move || {
+                counter.set(counter.get() + 1);
+                DoubleQuotedStringLexer::new()
+            })
+            .tokenizer(|| Whitespace)
+            .tokenize()
+            .unwrap();
+        assert_eq!(created.get(), 3);
+
+        // Lexers of the same factory don't share tokenizers, so they can be used at the same time.
+        let mut first = factory.lexer(Cursor::new("\"a\" \"b\""));
+        let mut second = factory.lexer(Cursor::new("\"c\" \"d\""));
+        let mut interleaved = vec![];
+        for _ in 0..3 {
+            for lexer in [&mut first, &mut second] {
+                interleaved.push(lexer.next_token().unwrap().unwrap().token().clone());
+            }
+        }
+        assert!(matches!(
+            interleaved.as_slice(),
+            [
+                Token::DoubleQuotedString(a),
+                Token::DoubleQuotedString(c),
+                Token::DoubleQuotedString(b),
+                Token::DoubleQuotedString(d),
+                Token::Eof,
+                Token::Eof,
+            ] if a == "a" && b == "b" && c == "c" && d == "d"
+        ));
+    }
+
+    #[test]
+    fn test_token_text() {
+        #[derive(Debug, Clone)]
+        enum Word {
+            Word(TokenText<'static>),
+            Space,
+        }
+
+        impl TokenValue for Word {
+            fn should_skip(&self) -> bool {
+                matches!(self, Word::Space)
+            }
+        }
+
+        struct Words;
+
+        impl Tokenizer<Word> for Words {
+            fn can_tokenize(
+                &mut self,
+                _: &LexContext<Word>,
+                _: &str,
+                _: &super::stream::GraphemeLocation,
+                _: &Option<SmallString>,
+                _: &mut (),
+            ) -> bool {
+                true
+            }
+
+            fn lex<'a, 'b>(
+                &'b mut self,
+                _: &'b mut Vec<super::Token<Word>>,
+                grapheme: &str,
+                _: &super::stream::GraphemeLocation,
+                incoming: &'b mut super::stream::Graphemes<'a>,
+                _: &mut (),
+            ) -> Result<Word, LexError> {
+                if grapheme == " " {
+                    return Ok(Word::Space);
+                }
+
+                let start = incoming.bytes_read() - grapheme.len();
+                while let Some(Ok(next)) = incoming.peek() {
+                    if next == " " {
+                        break;
+                    }
+                    incoming.next();
+                }
+                Ok(Word::Word(TokenText::since(start, incoming)))
+            }
+        }
+
+        let mut lexer = Lexer::new(Cursor::new("h\u{e9}llo w\u{f6}rld"), false, None)
+            .tokenizer(|| Words)
+            .store_source(true);
+        lexer.tokenize().unwrap();
+        let (tokens, source) = lexer.into_parts();
+
+        let words: Vec<&str> = tokens
+            .iter()
+            .filter_map(|token| match token.token() {
+                Word::Word(text) => text.resolve(&source),
+                Word::Space => None,
+            })
+            .collect();
+        assert_eq!(words, ["h\u{e9}llo", "w\u{f6}rld"]);
+
+        let from_range = TokenText::from_graphemes(tokens[1].range().unwrap(), &source).unwrap();
+        assert_eq!(from_range.resolve(&source), Some("w\u{f6}rld"));
+        assert!(from_range.is_source());
+
+        let owned = from_range.to_owned_text(&source).unwrap();
+        assert!(!owned.is_source());
+        assert_eq!(
+            owned.resolve(&SourceBuffer::from_text("")),
+            Some("w\u{f6}rld")
+        );
+        assert_eq!(
+            TokenText::from("text").into_owned(),
+            TokenText::from("text".to_string())
+        );
+    }
+
+    #[test]
+    fn test_csv() {
+        let fields = |dialect: CsvDialect, input: &'static str| {
+            let mut lexer = dialect.lexer(Cursor::new(input));
+            lexer.tokenize().map(|_| {
+                lexer
+                    .tokens()
+                    .iter()
+                    .map(|token| match token.token() {
+                        CsvToken::Field(field) => field.as_str(),
+                        CsvToken::RecordEnd => "\n",
+                    })
+                    .collect::<Vec<_>>()
+                    .join("|")
+            })
+        };
+
+        assert_eq!(
+            fields(
+                CsvDialect::csv(),
+                "a,\"b,\"\"c\"\"\"\r\n,\"multi\nline\",\n"
+            )
+            .unwrap(),
+            "a|b,\"c\"|\n||multi\nline||\n"
+        );
+        assert_eq!(
+            fields(CsvDialect::tsv(), "a\t\"b\"\tc").unwrap(),
+            "a|\"b\"|c"
+        );
+        assert_eq!(
+            fields(
+                CsvDialect::csv()
+                    .delimiter(';')
+                    .escape(CsvEscape::Character('\\')),
+                "\"a\\\";b\";c"
+            )
+            .unwrap(),
+            "a\";b|c"
+        );
+
+        assert!(fields(CsvDialect::csv(), "\"a\"b").is_err());
+        assert!(fields(CsvDialect::csv(), "\"a")
+            .unwrap_err()
+            .is_incomplete());
+        assert!(fields(CsvDialect::csv().newlines_in_quotes(false), "\"a\nb\"").is_err());
+
+        let mut lexer = CsvDialect::csv().lexer(Cursor::new("ab,cd"));
+        lexer.tokenize().unwrap();
+        assert_eq!(lexer.tokens()[0].range(), Some(&(0..=1)));
+        assert_eq!(lexer.tokens()[1].range(), Some(&(2..=4)));
+    }
+
+    #[test]
+    fn test_ini() {
+        let input = "; settings\n[server]\nhost = example.com ; primary\nname: \"a \\\"b\\\"\"\n\n[ paths ]\nroot='C:\\dir' # raw\nempty =\n";
+        let mut lexer = IniTokenizers::lexer(Cursor::new(input));
+        lexer.tokenize().unwrap();
+
+        let tokens: Vec<IniToken> = lexer
+            .tokens()
+            .iter()
+            .map(|token| token.token().clone())
+            .filter(|token| *token != IniToken::Newline)
+            .collect();
+        assert_eq!(
+            tokens,
+            [
+                IniToken::Comment("settings".into()),
+                IniToken::Section("server".into()),
+                IniToken::Key("host".into()),
+                IniToken::Assign,
+                IniToken::Value("example.com".into()),
+                IniToken::Comment("primary".into()),
+                IniToken::Key("name".into()),
+                IniToken::Assign,
+                IniToken::String("a \"b\"".into()),
+                IniToken::Section("paths".into()),
+                IniToken::Key("root".into()),
+                IniToken::Assign,
+                IniToken::String("C:\\dir".into()),
+                IniToken::Comment("raw".into()),
+                IniToken::Key("empty".into()),
+                IniToken::Assign,
+            ]
+        );
+
+        let mut lexer = IniTokenizers::lexer(Cursor::new("greeting = hello world  \n"));
+        lexer.tokenize().unwrap();
+        assert_eq!(
+            lexer.tokens()[2].token(),
+            &IniToken::Value("hello world".into())
+        );
+        assert_eq!(lexer.tokens()[2].range(), Some(&(11..=21)));
+
+        assert!(IniTokenizers::lexer(Cursor::new("[open\n"))
+            .tokenize()
+            .unwrap_err()
+            .is_incomplete());
+        for unterminated in ["a = \"b\\", "a = \"b\\\n\""] {
+            assert!(IniTokenizers::lexer(Cursor::new(unterminated))
+                .tokenize()
+                .unwrap_err()
+                .is_incomplete());
+        }
+        let error = IniTokenizers::lexer(Cursor::new(b"a = \"b\\\xff\""))
+            .tokenize()
+            .unwrap_err();
+        assert!(!error.is_incomplete());
+        assert!(matches!(error.inner(), LexError::OtherIndexed(7, _)));
+
+        #[derive(Debug, Clone, PartialEq)]
+        enum Config {
+            Ini(IniToken),
+            Include,
+        }
+
+        impl TokenValue for Config {}
+
+        impl From<IniToken> for Config {
+            fn from(token: IniToken) -> Self {
+                Config::Ini(token)
+            }
+        }
+
+        impl IniTokenType for Config {
+            fn as_ini(&self) -> Option<&IniToken> {
+                match self {
+                    Config::Ini(token) => Some(token),
+                    Config::Include => None,
+                }
+            }
+        }
+
+        let literals = LiteralSetTokenizer::new(["@include"], |_| Config::Include);
+        let mut lexer = Lexer::new(Cursor::new("@include\nkey=value"), false, None)
+            .tokenizer(move || literals.clone())
+            .tokenizer(|| IniLayoutTokenizer)
+            .tokenizer(|| IniKeyTokenizer)
+            .tokenizer(|| IniAssignTokenizer)
+            .tokenizer(|| IniValueTokenizer);
+        lexer.tokenize().unwrap();
+        assert_eq!(lexer.tokens()[0].token(), &Config::Include);
+        assert_eq!(
+            lexer.tokens()[4].token(),
+            &Config::Ini(IniToken::Value("value".into()))
+        );
+    }
+
+    #[test]
+    fn test_decode_escapes() {
+        use escape::{decode_escapes, EscapeErrorKind, EscapePolicy, UnknownEscape};
+
+        assert_eq!(
+            decode_escapes(r#"a\"b\n\t\\"#, &EscapePolicy::new()),
+            Ok("a\"b\n\t\\".to_string())
+        );
+        assert_eq!(
+            decode_escapes(r"\u00e9\ud83d\ude00\/", &EscapePolicy::json()),
+            Ok("\u{e9}\u{1f600}/".to_string())
+        );
+        assert_eq!(
+            decode_escapes(r"\u{1f600}\x41", &EscapePolicy::rust()),
+            Ok("\u{1f600}A".to_string())
+        );
+        assert_eq!(
+            decode_escapes(r"\d", &EscapePolicy::new().unknown(UnknownEscape::Keep)),
+            Ok(r"\d".to_string())
+        );
+
+        let raw = "\u{e9}t\u{e9} \\q";
+        let error = decode_escapes(raw, &EscapePolicy::new()).unwrap_err();
+        assert_eq!(error.kind, EscapeErrorKind::Unknown('q'));
+        assert_eq!(error.byte_range(), 6..8);
+        assert_eq!(error.graphemes(raw), 4..=5);
+        assert_eq!(error.grapheme_span(raw), 4..6);
+
+        let error = decode_escapes(r"ab\ud800x", &EscapePolicy::json()).unwrap_err();
+        assert_eq!(error.kind, EscapeErrorKind::InvalidCodePoint(0xd800));
+        assert_eq!(error.offset, 2);
+        assert_eq!(
+            decode_escapes(r"\u12", &EscapePolicy::json())
+                .unwrap_err()
+                .kind,
+            EscapeErrorKind::Incomplete
+        );
+        assert_eq!(
+            decode_escapes(r"\x80", &EscapePolicy::rust())
+                .unwrap_err()
+                .kind,
+            EscapeErrorKind::InvalidCodePoint(0x80)
+        );
+
+        let mut lexer = IniTokenizers::lexer(Cursor::new("key = \"ab\\q\""));
+        let error = lexer.tokenize().unwrap_err();
+        assert_eq!(error.location().map(|location| location.index), Some(9));
+    }
+
+    #[test]
+    fn test_heredoc() {
+        #[derive(Debug, Clone, PartialEq)]
+        enum Doc {
+            Text(String, String),
+            Newline,
+        }
+
+        impl TokenValue for Doc {}
+
+        let lex = |heredoc: HeredocTokenizer<Doc>, input: &'static str| {
+            let newlines = LiteralSetTokenizer::new(["\n"], |_| Doc::Newline);
+            let mut lexer = Lexer::new(Cursor::new(input), false, None)
+                .tokenizer(move || heredoc.clone())
+                .tokenizer(move || newlines.clone());
+            lexer.tokenize().map(|_| {
+                lexer
+                    .tokens()
+                    .iter()
+                    .map(|token| (token.token().clone(), token.range().cloned()))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let text = |tag: &str, text: &str| Doc::Text(tag.into(), text.into());
+
+        let heredoc = HeredocTokenizer::heredoc("<<", |tag, text| Doc::Text(tag.into(), text));
+        assert_eq!(
+            lex(heredoc.clone(), "<<EOF\na\n  EOF\nEOF\n<<END  \nEND").unwrap(),
+            [
+                (text("EOF", "a\n  EOF"), Some(0..=16)),
+                (Doc::Newline, Some(17..=17)),
+                (text("END", ""), Some(18..=28)),
+            ]
+        );
+        assert!(lex(heredoc.clone(), "<<EOF\na\n")
+            .unwrap_err()
+            .is_incomplete());
+        assert!(lex(heredoc, "<<EOF x\nEOF").is_err());
+
+        let indented = HeredocTokenizer::heredoc("<<~", |tag, text| Doc::Text(tag.into(), text))
+            .strip_indentation();
+        assert_eq!(
+            lex(indented, "<<~SQL\n    SELECT *\n\n      FROM t\n  SQL").unwrap(),
+            [(text("SQL", "SELECT *\n\n  FROM t"), Some(0..=38))]
+        );
+
+        let raw = HeredocTokenizer::raw_string('r', |hashes, text| Doc::Text(hashes.into(), text));
+        assert_eq!(
+            lex(raw.clone(), "r\"a\\b\"\nr##\"c\"#d\"##").unwrap(),
+            [
+                (text("", "a\\b"), Some(0..=5)),
+                (Doc::Newline, Some(6..=6)),
+                (text("##", "c\"#d"), Some(7..=17)),
+            ]
+        );
+        assert!(lex(raw, "r#\"a\"").unwrap_err().is_incomplete());
+    }
+
+    #[test]
+    fn test_token_diff() {
+        use diff::{token_diff, TokenEdit};
+
+        #[derive(Debug, Clone, PartialEq)]
+        struct Word(&'static str);
+
+        impl TokenValue for Word {}
+
+        let tokens = |words: &[(&'static str, usize)]| -> Vec<super::Token<Word>> {
+            words
+                .iter()
+                .map(|(word, start)| {
+                    super::Token::new(Word(word), Some(*start..=start + word.len() - 1))
+                })
+                .collect()
+        };
+        let old = tokens(&[("let", 1), ("x", 5), ("=", 7), ("1", 9), (";", 10)]);
+        let new = tokens(&[
+            ("let", 1),
+            ("y", 5),
+            ("=", 7),
+            ("2", 9),
+            ("+", 11),
+            ("3", 13),
+            (";", 14),
+        ]);
+
+        assert_eq!(
+            token_diff(&old, &new),
+            [
+                TokenEdit::Changed {
+                    old: 1,
+                    new: 1,
+                    old_span: Some(Span::new(5, 5)),
+                    new_span: Some(Span::new(5, 5)),
+                },
+                TokenEdit::Changed {
+                    old: 3,
+                    new: 3,
+                    old_span: Some(Span::new(9, 9)),
+                    new_span: Some(Span::new(9, 9)),
+                },
+                TokenEdit::Inserted {
+                    new: 4,
+                    span: Some(Span::new(11, 11)),
+                },
+                TokenEdit::Inserted {
+                    new: 5,
+                    span: Some(Span::new(13, 13)),
+                },
+            ]
+        );
+        assert_eq!(
+            token_diff(&new, &old)[2],
+            TokenEdit::Removed {
+                old: 4,
+                span: Some(Span::new(11, 11)),
+            }
+        );
+        assert!(token_diff(&old, &old).is_empty());
+
+        // The longest common subsequence of these is four tokens long, such as `b a b a`.
+        let words =
+            |text: &'static str| tokens(&text.split(' ').map(|word| (word, 1)).collect::<Vec<_>>());
+        let (old, new) = (words("a b c a b b a"), words("c b a b a c"));
+        let edits = token_diff(&old, &new);
+        let old_edited = edits
+            .iter()
+            .filter(|edit| !matches!(edit, TokenEdit::Inserted { .. }))
+            .count();
+        let new_edited = edits
+            .iter()
+            .filter(|edit| !matches!(edit, TokenEdit::Removed { .. }))
+            .count();
+        assert_eq!((old_edited, new_edited), (old.len() - 4, new.len() - 4));
+    }
+
+    #[test]
+    fn test_tokenize_all() {
+        use batch::{tokenize_all, tokenize_all_with_threads};
+
+        let sources = vec![
+            Cursor::new("a,b\n"),
+            Cursor::new("\"open"),
+            Cursor::new("c"),
+        ];
+        let lexer = |source| CsvDialect::csv().lexer(source);
+        let results = tokenize_all(sources.clone(), lexer);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap().len(), 3);
+        let error = results[1].as_ref().unwrap_err();
+        assert_eq!(error.source, 1);
+        assert!(error.error.is_incomplete());
+        assert!(matches!(
+            error.error.inner(),
+            LexError::UnterminatedToken { .. }
+        ));
+        assert_eq!(
+            results[2].as_ref().unwrap()[0].token(),
+            &CsvToken::Field("c".into())
+        );
+        let messages = |results: &[batch::BatchResult<CsvToken>]| -> Vec<_> {
+            results
+                .iter()
+                .map(|result| result.as_ref().map_err(ToString::to_string).cloned())
+                .collect()
+        };
+        assert_eq!(
+            messages(&tokenize_all_with_threads(sources, lexer, 1)),
+            messages(&results)
+        );
+    }
 }
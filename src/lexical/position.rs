@@ -0,0 +1,89 @@
+use super::GraphemeLocation;
+
+/// Returns whether `grapheme` is a line break, being `\n`, `\r\n` or `\r`.
+pub(crate) fn is_line_break(grapheme: &str) -> bool {
+    matches!(grapheme, "\n" | "\r\n" | "\r")
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Tracks the index, line and column at which the next grapheme of a stream starts.
+///
+/// `\n`, `\r\n` and `\r` each end a line. A tab advances the column to the next tab stop, which
+/// lies every [PositionTracker::tab_width] columns. Every other grapheme is one column wide.
+pub struct PositionTracker {
+    index: usize,
+    line: usize,
+    column: usize,
+    tab_width: usize,
+}
+
+impl PositionTracker {
+    /// Create a tracker at the start of the input, where tabs are one column wide.
+    pub fn new() -> Self {
+        Self::with_tab_width(1)
+    }
+
+    /// Create a tracker at the start of the input, with a tab stop every `tab_width` columns.
+    /// A `tab_width` of zero is treated as one.
+    pub fn with_tab_width(tab_width: usize) -> Self {
+        Self {
+            index: 0,
+            line: 0,
+            column: 0,
+            tab_width: tab_width.max(1),
+        }
+    }
+
+    /// Returns the amount of columns between tab stops.
+    pub fn tab_width(&self) -> usize {
+        self.tab_width
+    }
+
+    /// Sets the amount of columns between tab stops. A `tab_width` of zero is treated as one.
+    ///
+    /// This only affects graphemes that are read afterwards.
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.tab_width = tab_width.max(1)
+    }
+
+    /// Returns the index of the next grapheme.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the line, starting at zero, that the next grapheme lies on.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// Returns the column, starting at zero, that the next grapheme starts at.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Returns the location of the next grapheme.
+    pub fn location(&self) -> GraphemeLocation {
+        GraphemeLocation::new(self.index, self.line, self.column)
+    }
+
+    /// Moves past `grapheme`, returning the location it started at.
+    pub fn advance(&mut self, grapheme: &str) -> GraphemeLocation {
+        let location = self.location();
+        self.index += 1;
+        if is_line_break(grapheme) {
+            self.line += 1;
+            self.column = 0;
+        } else if grapheme == "\t" {
+            self.column += self.tab_width - self.column % self.tab_width;
+        } else {
+            self.column += 1;
+        }
+        location
+    }
+}
+
+impl Default for PositionTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
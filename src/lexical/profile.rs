@@ -0,0 +1,35 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+/// Counters of the work done while lexing, as returned by [Lexer::profile_report](super::Lexer::profile_report).
+///
+/// Only available with the `profiling` feature, as counting has a small cost on every grapheme.
+pub struct Profile {
+    /// How many times a tokenizer was asked whether it could lex a token.
+    pub tokenizer_checks: usize,
+    /// How many times a tokenizer lexed a token, whether it succeeded or not.
+    pub tokenizer_lexes: usize,
+    /// How many graphemes were peeked at through [Graphemes::peek](super::Graphemes::peek).
+    pub peeks: usize,
+    /// How many graphemes were too long to be stored inline, and were allocated on the heap.
+    pub heap_graphemes: usize,
+}
+
+impl Profile {
+    /// Adds the counters of `other` to this profile.
+    pub(crate) fn add(&mut self, other: &Profile) {
+        self.tokenizer_checks += other.tokenizer_checks;
+        self.tokenizer_lexes += other.tokenizer_lexes;
+        self.peeks += other.peeks;
+        self.heap_graphemes += other.heap_graphemes;
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "tokenizer checks: {}", self.tokenizer_checks)?;
+        writeln!(f, "tokenizer lexes:  {}", self.tokenizer_lexes)?;
+        writeln!(f, "peeks:            {}", self.peeks)?;
+        write!(f, "heap graphemes:   {}", self.heap_graphemes)
+    }
+}
@@ -0,0 +1,147 @@
+use std::{io::Cursor, rc::Rc};
+
+use super::{error::LexError, Lexer, Token, TokenValue, Tokenizer, TokenizerFn};
+
+#[derive(Debug)]
+/// The outcome of feeding a line to a [ReplSession].
+pub enum ReplStatus<TokenType: TokenValue> {
+    /// The accumulated input was lexed completely, producing these tokens.
+    Complete(Vec<Token<TokenType>>),
+    /// The input ended inside of a token, such as an unterminated string,
+    /// so another line is needed before it can be lexed.
+    NeedsMoreInput,
+    /// The input could not be lexed. The accumulated input is discarded.
+    Error(LexError),
+}
+
+/// Accumulates lines of input for an interactive shell, lexing them once they form
+/// complete tokens.
+///
+/// A line that ends inside of a token is reported as [ReplStatus::NeedsMoreInput] rather than an error,
+/// allowing the shell to prompt for a continuation line. Tokenizers signal this by returning
+/// [LexError::UnterminatedToken] or [LexError::UnexpectedEndOfStream] when the input ends early.
+///
+/// The tokens completed before an unfinished token are kept, so each line only lexes the input from
+/// the start of the unfinished token onwards, along with the earlier tokens as context. Only if the
+/// input cannot be lexed is all of it lexed again, so that the error is located within all of it.
+pub struct ReplSession<'a, TokenType: TokenValue> {
+    creation_funcs: Vec<Rc<dyn TokenizerFn<'a, TokenType>>>,
+    eof_token: Option<TokenType>,
+    buffer: String,
+    // The tokens completed before the unfinished token.
+    tokens: Vec<Token<TokenType>>,
+    // The byte offset and the grapheme index within `buffer` that the unfinished token starts at.
+    resume: (usize, usize),
+}
+
+impl<'a, TokenType: TokenValue + 'a> ReplSession<'a, TokenType> {
+    /// Create a session.
+    pub fn new(eof_token: Option<TokenType>) -> Self {
+        Self {
+            creation_funcs: vec![],
+            eof_token,
+            buffer: String::new(),
+            tokens: vec![],
+            resume: (0, 0),
+        }
+    }
+
+    /// Add a tokenizer function and return self.
+    pub fn tokenizer<F, T>(mut self, f: F) -> Self
+    where
+        F: Fn() -> T + 'a,
+        T: Tokenizer<TokenType> + 'a,
+    {
+        self.add_tokenizer(f);
+        self
+    }
+
+    /// Add a tokenizer function.
+    pub fn add_tokenizer<F, T>(&mut self, f: F)
+    where
+        F: Fn() -> T + 'a,
+        T: Tokenizer<TokenType> + 'a,
+    {
+        self.creation_funcs.push(Rc::new(move || {
+            Box::new(f()) as Box<dyn Tokenizer<TokenType>>
+        }));
+    }
+
+    /// Returns the input accumulated since the last complete or failed lex.
+    pub fn buffer(&self) -> &str {
+        &self.buffer
+    }
+
+    /// Returns whether previous lines are awaiting a continuation.
+    pub fn is_continuing(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// Discards the accumulated input.
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.tokens.clear();
+        self.resume = (0, 0);
+    }
+
+    /// Appends `line` to the accumulated input, followed by a line feed if it lacks one,
+    /// and attempts to lex the accumulated input.
+    pub fn feed(&mut self, line: &str) -> ReplStatus<TokenType> {
+        self.buffer.push_str(line);
+        if !line.ends_with('\n') {
+            self.buffer.push('\n');
+        }
+
+        let (resume_bytes, resume_index) = self.resume;
+        let mut lexer = self.lexer(&self.buffer[resume_bytes..]).store_source(true);
+        let completed = self.tokens.len();
+        lexer.tokens_mut().append(&mut self.tokens);
+
+        let result = lexer.tokenize();
+        let (mut tokens, source) = lexer.into_parts();
+        let resumed = tokens
+            .get(completed..)
+            .and_then(<[_]>::last)
+            .and_then(|token| {
+                let range = token.range_raw();
+                let end = match token.is_zero_width() {
+                    true => *range.start(),
+                    false => range.end() + 1,
+                };
+                Some((source.byte_offset(end)?, end))
+            });
+        for token in tokens.iter_mut().skip(completed) {
+            let range = token.range_raw_mut();
+            *range = range.start() + resume_index..=range.end() + resume_index;
+        }
+
+        match result {
+            Ok(()) => {
+                self.reset();
+                ReplStatus::Complete(tokens)
+            }
+            Err(error) if error.is_incomplete() => {
+                if let Some((bytes, graphemes)) = resumed {
+                    self.resume = (resume_bytes + bytes, resume_index + graphemes);
+                }
+                self.tokens = tokens;
+                ReplStatus::NeedsMoreInput
+            }
+            Err(_) => {
+                let error = self.lexer(&self.buffer).tokenize().unwrap_err();
+                self.reset();
+                ReplStatus::Error(error)
+            }
+        }
+    }
+
+    /// Create a lexer for `input` with the session's tokenizers.
+    fn lexer(&self, input: &str) -> Lexer<'a, TokenType> {
+        let mut lexer = Lexer::new(Cursor::new(input.to_string()), true, self.eof_token.clone());
+        for creation_func in &self.creation_funcs {
+            let creation_func = creation_func.clone();
+            lexer.add_tokenizer(move || creation_func());
+        }
+        lexer
+    }
+}
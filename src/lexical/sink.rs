@@ -0,0 +1,39 @@
+use std::sync::mpsc::{Sender, SyncSender};
+
+use super::{Token, TokenValue};
+
+/// Receives the tokens stored by a [Lexer](super::Lexer) that has been given a sink with
+/// [Lexer::sink](super::Lexer::sink), allowing tokens to be streamed elsewhere instead of
+/// being retained by the lexer.
+///
+/// It is implemented for [Vec], for channel senders, and for closures that accept a token.
+pub trait TokenSink<TokenType: TokenValue> {
+    /// Accept a token.
+    fn push(&mut self, token: Token<TokenType>);
+}
+
+impl<TokenType: TokenValue> TokenSink<TokenType> for Vec<Token<TokenType>> {
+    fn push(&mut self, token: Token<TokenType>) {
+        Vec::push(self, token)
+    }
+}
+
+/// Tokens sent after the receiver has been dropped are discarded.
+impl<TokenType: TokenValue> TokenSink<TokenType> for Sender<Token<TokenType>> {
+    fn push(&mut self, token: Token<TokenType>) {
+        let _ = self.send(token);
+    }
+}
+
+/// Tokens sent after the receiver has been dropped are discarded.
+impl<TokenType: TokenValue> TokenSink<TokenType> for SyncSender<Token<TokenType>> {
+    fn push(&mut self, token: Token<TokenType>) {
+        let _ = self.send(token);
+    }
+}
+
+impl<TokenType: TokenValue, F: FnMut(Token<TokenType>)> TokenSink<TokenType> for F {
+    fn push(&mut self, token: Token<TokenType>) {
+        self(token)
+    }
+}
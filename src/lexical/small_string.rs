@@ -0,0 +1,166 @@
+use std::{
+    borrow::Borrow,
+    fmt::{Debug, Display},
+    hash::{Hash, Hasher},
+    ops::Deref,
+};
+
+use smallvec::SmallVec;
+
+/// The amount of bytes a [SmallString] can hold before it spills onto the heap.
+pub const INLINE_CAPACITY: usize = 24;
+
+#[derive(Clone, Default)]
+/// A UTF-8 string that stores up to [INLINE_CAPACITY] bytes inline, only allocating
+/// when it grows beyond that.
+///
+/// Nearly every grapheme fits inline, so this is the type yielded by [Graphemes](super::Graphemes)
+/// in order to avoid a heap allocation per grapheme.
+///
+/// It dereferences into a [str], and can be compared against [str], [&str](str) and [String].
+pub struct SmallString {
+    // Only ever extended with whole `str`s, so it always holds valid UTF-8.
+    bytes: SmallVec<[u8; INLINE_CAPACITY]>,
+}
+
+impl SmallString {
+    /// Create an empty string.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the string as a [str].
+    pub fn as_str(&self) -> &str {
+        debug_assert!(std::str::from_utf8(&self.bytes).is_ok());
+        // SAFETY: `bytes` is only ever written by `push_str` and `From<&str>`, which copy a whole
+        // `str`, and by `clear`, so it always holds valid UTF-8.
+        unsafe { std::str::from_utf8_unchecked(&self.bytes) }
+    }
+
+    /// Append `string` to the end of this string.
+    pub fn push_str(&mut self, string: &str) {
+        self.bytes.extend_from_slice(string.as_bytes())
+    }
+
+    /// Append `character` to the end of this string.
+    pub fn push(&mut self, character: char) {
+        self.push_str(character.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Remove every character from this string.
+    pub fn clear(&mut self) {
+        self.bytes.clear()
+    }
+
+    /// Returns whether the string has spilled onto the heap.
+    pub fn spilled(&self) -> bool {
+        self.bytes.spilled()
+    }
+
+    /// Converts the string into a [String].
+    pub fn into_string(self) -> String {
+        self.as_str().to_string()
+    }
+}
+
+impl Deref for SmallString {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for SmallString {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for SmallString {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Debug for SmallString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.as_str())
+    }
+}
+
+impl Display for SmallString {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl PartialEq for SmallString {
+    fn eq(&self, other: &Self) -> bool {
+        self.bytes == other.bytes
+    }
+}
+
+impl Eq for SmallString {}
+
+impl PartialEq<str> for SmallString {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for SmallString {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for SmallString {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialOrd for SmallString {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SmallString {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for SmallString {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl From<&str> for SmallString {
+    fn from(string: &str) -> Self {
+        Self {
+            bytes: SmallVec::from_slice(string.as_bytes()),
+        }
+    }
+}
+
+impl From<String> for SmallString {
+    fn from(string: String) -> Self {
+        Self::from(string.as_str())
+    }
+}
+
+impl From<char> for SmallString {
+    fn from(character: char) -> Self {
+        Self::from(character.encode_utf8(&mut [0; 4]) as &str)
+    }
+}
+
+impl From<SmallString> for String {
+    fn from(string: SmallString) -> Self {
+        string.into_string()
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    ops::{Range, RangeInclusive},
+    sync::Arc,
+};
+
+use super::{position::is_line_break, Clusters, SourceId};
+
+#[derive(Debug, Clone, Default)]
+/// Maps [SourceId]s to descriptions of their sources, such as file paths.
+pub struct SourceRegistry {
+    names: Vec<String>,
+}
+
+impl SourceRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a source described by `name`, returning its new id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if more than [u32::MAX] sources have already been registered.
+    pub fn register<S: Into<String>>(&mut self, name: S) -> SourceId {
+        let id = u32::try_from(self.names.len()).expect("Too many sources have been registered");
+        self.names.push(name.into());
+        SourceId(id)
+    }
+
+    /// Returns the description of the source with the id `id`.
+    pub fn name(&self, id: SourceId) -> Option<&str> {
+        self.names.get(id.index()).map(String::as_str)
+    }
+
+    /// Returns the id of the first source described by `name`.
+    pub fn find(&self, name: &str) -> Option<SourceId> {
+        self.names
+            .iter()
+            .position(|registered| registered == name)
+            .map(|index| SourceId(index as u32))
+    }
+
+    /// Returns the amount of registered sources.
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// Returns whether no sources have been registered.
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// The text that a lexer consumed, which can outlive the lexer itself.
+///
+/// Cloning a [SourceBuffer] is cheap, as the underlying storage is shared.
+///
+/// Token ranges are inclusive ranges of grapheme indexes, and can be resolved
+/// back into text with [SourceBuffer::slice].
+pub struct SourceBuffer {
+    text: Arc<str>,
+    graphemes: Arc<[usize]>,
+    lines: Arc<[usize]>,
+}
+
+impl SourceBuffer {
+    /// Create a buffer from `text`, split into graphemes in the same way a lexer would.
+    pub fn from_text(text: &str) -> Self {
+        let mut recorder = SourceRecorder::new();
+        for grapheme in Clusters::new(text.chars().map(Ok)).flatten() {
+            recorder.push(&grapheme);
+        }
+
+        recorder.freeze()
+    }
+
+    /// Returns the consumed text.
+    ///
+    /// If the lexer was lossy, invalid UTF-8 sequences will have been replaced with U+FFFD.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the consumed text as bytes.
+    pub fn bytes(&self) -> &[u8] {
+        self.text.as_bytes()
+    }
+
+    /// Returns whether no text was consumed.
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// Returns the amount of graphemes that were consumed.
+    pub fn graphemes(&self) -> usize {
+        self.graphemes.len()
+    }
+
+    /// Returns the amount of lines that were consumed.
+    pub fn lines(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// Returns the byte offset of the grapheme at `index`.
+    ///
+    /// An `index` equal to the amount of graphemes returns the length of the text.
+    pub fn byte_offset(&self, index: usize) -> Option<usize> {
+        match self.graphemes.get(index) {
+            Some(offset) => Some(*offset),
+            None if index == self.graphemes.len() => Some(self.text.len()),
+            None => None,
+        }
+    }
+
+    /// Converts an inclusive range of grapheme indexes, such as the range of a token, into a range of bytes.
+    pub fn byte_range(&self, range: &RangeInclusive<usize>) -> Option<Range<usize>> {
+        if range.start() > range.end() {
+            return None;
+        }
+
+        self.byte_span(*range.start()..range.end().checked_add(1)?)
+    }
+
+    /// Converts an exclusive range of grapheme indexes into a range of bytes.
+    ///
+    /// Unlike [SourceBuffer::byte_range], an empty range is converted into an empty range of bytes.
+    pub fn byte_span(&self, range: Range<usize>) -> Option<Range<usize>> {
+        if range.start > range.end {
+            return None;
+        }
+
+        Some(self.byte_offset(range.start)?..self.byte_offset(range.end)?)
+    }
+
+    /// Returns the text of the graphemes within `range`.
+    pub fn slice(&self, range: &RangeInclusive<usize>) -> Option<&str> {
+        self.text.get(self.byte_range(range)?)
+    }
+
+    /// Returns the text of the graphemes within the exclusive `range`.
+    pub fn slice_span(&self, range: Range<usize>) -> Option<&str> {
+        self.text.get(self.byte_span(range)?)
+    }
+
+    /// Returns the text of the line at `line`, starting at zero, including its line ending.
+    pub fn line(&self, line: usize) -> Option<&str> {
+        let start = *self.lines.get(line)?;
+        let end = self.lines.get(line + 1).copied().unwrap_or(self.text.len());
+        self.text.get(start..end)
+    }
+
+    /// Returns the line, starting at zero, that the byte at `offset` lies on.
+    pub fn line_of(&self, offset: usize) -> usize {
+        self.lines
+            .partition_point(|start| *start <= offset)
+            .saturating_sub(1)
+    }
+
+    /// Returns the line and the column, both starting at zero, of the grapheme at `index`.
+    /// The column is counted in graphemes.
+    pub fn location_of(&self, index: usize) -> Option<(usize, usize)> {
+        let offset = self.byte_offset(index)?;
+        let line = self.line_of(offset);
+        let line_start = self.lines.get(line).copied().unwrap_or_default();
+        let first_grapheme = self.graphemes.partition_point(|start| *start < line_start);
+        Some((line, index - first_grapheme))
+    }
+}
+
+#[derive(Debug, Default)]
+/// Accumulates graphemes as they are read, to later be frozen into a [SourceBuffer].
+pub(crate) struct SourceRecorder {
+    text: String,
+    graphemes: Vec<usize>,
+    lines: Vec<usize>,
+}
+
+impl SourceRecorder {
+    pub(crate) fn new() -> Self {
+        Self {
+            lines: vec![0],
+            ..Self::default()
+        }
+    }
+
+    pub(crate) fn push(&mut self, grapheme: &str) {
+        self.graphemes.push(self.text.len());
+        self.text.push_str(grapheme);
+        if is_line_break(grapheme) {
+            self.lines.push(self.text.len());
+        }
+    }
+
+    pub(crate) fn freeze(self) -> SourceBuffer {
+        SourceBuffer {
+            text: self.text.into(),
+            graphemes: self.graphemes.into(),
+            lines: self.lines.into(),
+        }
+    }
+}
@@ -0,0 +1,78 @@
+use std::{fmt::Display, ops::Range};
+
+use super::{SourceBuffer, Span, Token, TokenValue};
+
+impl Span {
+    /// Returns the range of bytes that the span covers within `source`.
+    pub fn byte_range(&self, source: &SourceBuffer) -> Option<Range<usize>> {
+        source.byte_span(self.grapheme_span())
+    }
+
+    /// Returns the amount of bytes that the span covers within `source`.
+    pub fn len_bytes(&self, source: &SourceBuffer) -> Option<usize> {
+        self.byte_range(source).map(|range| range.len())
+    }
+
+    /// Returns whether the byte at `offset` within `source` lies within the span.
+    pub fn contains_byte(&self, offset: usize, source: &SourceBuffer) -> bool {
+        self.byte_range(source)
+            .is_some_and(|range| range.contains(&offset))
+    }
+}
+
+impl<TokenType: TokenValue> Token<TokenType> {
+    /// Returns a value that displays the token on a single line like its [Display] implementation,
+    /// but with its span given as lines and columns within `source`, such as `Ident("foo") @ 3:14..3:17`.
+    ///
+    /// Lines and columns start at zero, and the span ends after the token's last grapheme.
+    pub fn display_in<'t>(&'t self, source: &'t SourceBuffer) -> LocatedToken<'t, TokenType> {
+        LocatedToken {
+            token: self,
+            source,
+        }
+    }
+}
+
+/// Displays a [Token] with its span given as lines and columns, created by [Token::display_in].
+///
+/// Falls back to grapheme indexes if the span doesn't lie within the source.
+pub struct LocatedToken<'t, TokenType: TokenValue> {
+    token: &'t Token<TokenType>,
+    source: &'t SourceBuffer,
+}
+
+impl<TokenType: TokenValue> LocatedToken<'_, TokenType> {
+    /// Returns the line and column that the span of the token starts at and ends before.
+    fn locations(&self) -> Option<((usize, usize), (usize, usize))> {
+        let span = self.token.grapheme_span()?;
+        let start = self.source.location_of(span.start)?;
+        if span.is_empty() {
+            return Some((start, start));
+        }
+
+        let (line, column) = self.source.location_of(span.end - 1)?;
+        Some((start, (line, column + 1)))
+    }
+}
+
+impl<TokenType: TokenValue> Display for LocatedToken<'_, TokenType> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(((start_line, start_column), (end_line, end_column))) = self.locations() else {
+            return write!(f, "{}", self.token);
+        };
+
+        write!(
+            f,
+            "{:?} @ {}:{}..{}:{}",
+            self.token.token(),
+            start_line,
+            start_column,
+            end_line,
+            end_column
+        )?;
+        if let Some(source) = self.token.source() {
+            write!(f, " in {}", source)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    mem::Discriminant,
+    time::Duration,
+};
+
+use super::TokenValue;
+
+#[derive(Debug, Clone)]
+/// Statistics about the tokens lexed by a [Lexer](super::Lexer), useful for profiling
+/// sets of tokenizers and tuning their order.
+///
+/// Skipped tokens, such as whitespace, are counted as trivia, while stored tokens are counted
+/// as significant. The EOF token is not counted.
+pub struct LexStats<TokenType: TokenValue> {
+    counts: HashMap<Discriminant<TokenType>, usize>,
+    tokenizers: HashMap<String, usize>,
+    significant: usize,
+    trivia: usize,
+    byte_histogram: BTreeMap<usize, usize>,
+    elapsed: Duration,
+}
+
+impl<TokenType: TokenValue> Default for LexStats<TokenType> {
+    fn default() -> Self {
+        Self {
+            counts: HashMap::new(),
+            tokenizers: HashMap::new(),
+            significant: 0,
+            trivia: 0,
+            byte_histogram: BTreeMap::new(),
+            elapsed: Duration::ZERO,
+        }
+    }
+}
+
+impl<TokenType: TokenValue> LexStats<TokenType> {
+    /// Create empty statistics.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the amount of lexed tokens, including trivia, with the same enum variant as `token`.
+    pub fn count(&self, token: &TokenType) -> usize {
+        self.counts
+            .get(&std::mem::discriminant(token))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns the amount of lexed tokens, including trivia, for each enum variant that was lexed.
+    pub fn counts(&self) -> &HashMap<Discriminant<TokenType>, usize> {
+        &self.counts
+    }
+
+    /// Returns the amount of tokens, including trivia, lexed by each tokenizer, keyed by
+    /// [Tokenizer::name](super::Tokenizer::name).
+    pub fn tokenizers(&self) -> &HashMap<String, usize> {
+        &self.tokenizers
+    }
+
+    /// Returns the amount of tokens that were stored.
+    pub fn significant(&self) -> usize {
+        self.significant
+    }
+
+    /// Returns the amount of tokens that were skipped.
+    pub fn trivia(&self) -> usize {
+        self.trivia
+    }
+
+    /// Returns the total amount of tokens that were lexed.
+    pub fn total(&self) -> usize {
+        self.significant + self.trivia
+    }
+
+    /// Returns how many tokens, including trivia, were lexed from each amount of bytes.
+    pub fn byte_histogram(&self) -> &BTreeMap<usize, usize> {
+        &self.byte_histogram
+    }
+
+    /// Returns the total time spent lexing.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Records a lexed token that was lexed from `bytes` bytes by the tokenizer named `tokenizer`,
+    /// counting it as trivia if it was `skipped`.
+    pub(crate) fn record(
+        &mut self,
+        token: &TokenType,
+        bytes: usize,
+        tokenizer: &str,
+        skipped: bool,
+    ) {
+        match self.tokenizers.get_mut(tokenizer) {
+            Some(count) => *count += 1,
+            None => {
+                self.tokenizers.insert(tokenizer.to_string(), 1);
+            }
+        }
+        *self
+            .counts
+            .entry(std::mem::discriminant(token))
+            .or_default() += 1;
+        *self.byte_histogram.entry(bytes).or_default() += 1;
+        if skipped {
+            self.trivia += 1;
+        } else {
+            self.significant += 1;
+        }
+    }
+
+    /// Adds `elapsed` to the total time spent lexing.
+    pub(crate) fn add_elapsed(&mut self, elapsed: Duration) {
+        self.elapsed += elapsed
+    }
+}
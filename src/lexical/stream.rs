@@ -1,19 +1,36 @@
 use std::{
     cell::RefCell,
-    io::{Error, ErrorKind, Read, Result as IoResult},
+    collections::VecDeque,
+    io::{Error, Read, Result as IoResult},
     rc::Rc,
 };
 
 use character_stream::{CharacterIterator, CharacterStream, CharacterStreamError};
 use itertools::{Itertools, MultiPeek};
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
 
-/// Wrapper for [character_stream::CharacterIterator] that ensures compatibility with [unicode_reader::Graphemes].
+use super::{
+    error::LexError,
+    position::{is_line_break, PositionTracker},
+    source::{SourceBuffer, SourceRecorder},
+    SmallString, SourceId, Span,
+};
+
+/// A boxed iterator of characters, which is the input of [Graphemes].
+pub type CharIter<'a> = Box<dyn Iterator<Item = IoResult<char>> + 'a>;
+
+/// Wrapper for [character_stream::CharacterIterator] that ensures compatibility with [Clusters].
 pub struct Chars<Reader: Read> {
     incoming: CharacterIterator<Reader>,
     is_lossy: bool,
     failed_count: Option<Rc<RefCell<usize>>>,
+    raw_lengths: Option<RawLengths>,
 }
 
+/// The amount of input bytes behind each U+FFFD character and each error yielded by a [Chars],
+/// in the order they were yielded, as a U+FFFD may replace any amount of invalid UTF-8 bytes.
+type RawLengths = Rc<RefCell<VecDeque<usize>>>;
+
 impl<Reader: Read> Chars<Reader> {
     /// Create a [Chars] from `reader`.
     /// `is_lossy` determines whether the stream will replace invalid UTF-8 byte sequences with a U+FFFD.
@@ -25,9 +42,16 @@ impl<Reader: Read> Chars<Reader> {
             incoming: CharacterIterator::new(CharacterStream::new(reader, false)),
             failed_count,
             is_lossy,
+            raw_lengths: None,
         }
     }
 
+    /// Record the amount of input bytes behind each U+FFFD and error into `raw_lengths`.
+    fn record_raw_lengths(mut self, raw_lengths: RawLengths) -> Self {
+        self.raw_lengths = Some(raw_lengths);
+        self
+    }
+
     /// Returns the amount of invalid UTF-8 bytes.
     pub fn invalid(&self) -> usize {
         self.failed_count.as_ref().map_or(0, |c| *c.borrow())
@@ -39,21 +63,26 @@ impl<Reader: Read> Iterator for Chars<Reader> {
 
     fn next(&mut self) -> Option<Self::Item> {
         Some(match self.incoming.next()? {
-            Ok(character) => Ok(character),
+            Ok(character) => {
+                if let (Some(raw_lengths), '\u{FFFD}') = (&self.raw_lengths, character) {
+                    raw_lengths.borrow_mut().push_back(character.len_utf8());
+                }
+                Ok(character)
+            }
             Err(error) => {
                 let CharacterStreamError(bytes, boxed_error) = error;
                 if let Some(ref count) = self.failed_count {
                     *count.borrow_mut() += bytes.len();
                 }
+                if let Some(raw_lengths) = &self.raw_lengths {
+                    raw_lengths.borrow_mut().push_back(bytes.len());
+                }
                 if self.is_lossy {
                     Ok('\u{FFFD}')
                 } else {
                     match boxed_error.downcast::<Error>() {
                         Ok(error) => Err(*error),
-                        Err(error) => Err(Error::new(
-                            ErrorKind::Other,
-                            CharacterStreamError(bytes, error),
-                        )),
+                        Err(error) => Err(Error::other(CharacterStreamError(bytes, error))),
                     }
                 }
             }
@@ -68,6 +97,7 @@ impl<Reader: Read> From<CharacterIterator<Reader>> for Chars<Reader> {
             incoming: iter,
             failed_count: None,
             is_lossy,
+            raw_lengths: None,
         }
     }
 }
@@ -79,18 +109,96 @@ impl<Reader: Read> From<CharacterStream<Reader>> for Chars<Reader> {
             incoming: CharacterIterator::new(stream),
             failed_count: None,
             is_lossy,
+            raw_lengths: None,
+        }
+    }
+}
+
+/// Groups the characters of an underlying character iterator into extended grapheme clusters.
+///
+/// Because a grapheme cluster is of indeterminate length, a grapheme is only yielded once the
+/// start of the following grapheme has been read, or the underlying iterator has ended.
+pub struct Clusters<I: Iterator<Item = IoResult<char>>> {
+    input: I,
+    buffer: String,
+    pending_error: Option<Error>,
+}
+
+impl<I: Iterator<Item = IoResult<char>>> Clusters<I> {
+    /// Create a [Clusters] from an iterator of characters.
+    pub fn new(input: I) -> Self {
+        Self {
+            input,
+            buffer: String::new(),
+            pending_error: None,
+        }
+    }
+
+    fn is_boundary(buffer: &str, offset: usize) -> bool {
+        GraphemeCursor::new(offset, buffer.len(), true)
+            .is_boundary(buffer, 0)
+            .unwrap_or(false)
+    }
+
+    fn take_buffer(&mut self) -> SmallString {
+        let grapheme = SmallString::from(self.buffer.as_str());
+        self.buffer.clear();
+        grapheme
+    }
+}
+
+impl<I: Iterator<Item = IoResult<char>>> Iterator for Clusters<I> {
+    type Item = IoResult<SmallString>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.pending_error.take() {
+            return Some(Err(error));
+        }
+
+        loop {
+            let previous_len = self.buffer.len();
+            match self.input.next() {
+                Some(Ok(character)) => self.buffer.push(character),
+                // The buffer never holds more than one grapheme, so whatever is buffered
+                // is complete once the input has ended or failed.
+                Some(Err(error)) => {
+                    if self.buffer.is_empty() {
+                        return Some(Err(error));
+                    }
+                    self.pending_error = Some(error);
+                    return Some(Ok(self.take_buffer()));
+                }
+                None if self.buffer.is_empty() => return None,
+                None => return Some(Ok(self.take_buffer())),
+            }
+
+            // The buffer always starts at a grapheme boundary, so it provides all of the context
+            // needed to decide whether the newly read character starts a new grapheme.
+            if previous_len > 0 && Self::is_boundary(&self.buffer, previous_len) {
+                if let Some(grapheme) = self.buffer.get(..previous_len).map(SmallString::from) {
+                    self.buffer.drain(..previous_len);
+                    return Some(Ok(grapheme));
+                }
+            }
         }
     }
 }
 
-#[derive(Clone, Debug)]
+impl<I: Iterator<Item = IoResult<char>>> From<I> for Clusters<I> {
+    fn from(input: I) -> Self {
+        Self::new(input)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
 /// Describes where a grapheme is from the start of the input.
 pub struct GraphemeLocation {
     /// The index of the grapheme, barring invalid UTF-8 sequences.
     pub index: usize,
-    /// Which line the grapheme is on, starting at zero.
+    /// Which line the grapheme is on, starting at zero. A line break lies on the line it ends.
     pub line: usize,
-    /// The offset from the start of the line in which the grapheme lies.
+    /// The column, starting at zero, that the grapheme starts at within its line.
+    /// See [PositionTracker] for how tabs are counted.
     pub offset: usize,
 }
 
@@ -104,32 +212,157 @@ impl GraphemeLocation {
     }
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// A grapheme read from a [Graphemes] stream, along with where it was read from.
+///
+/// It dereferences into a [str], and can be compared against [str] and [&str](str).
+pub struct Grapheme {
+    text: SmallString,
+    location: GraphemeLocation,
+}
+
+impl Grapheme {
+    pub fn new<S: Into<SmallString>>(text: S, location: GraphemeLocation) -> Self {
+        Self {
+            text: text.into(),
+            location,
+        }
+    }
+
+    /// Returns the text of the grapheme.
+    pub fn text(&self) -> &SmallString {
+        &self.text
+    }
+
+    /// Returns the text of the grapheme as a [str].
+    pub fn as_str(&self) -> &str {
+        self.text.as_str()
+    }
+
+    /// Returns where the grapheme was read from.
+    pub fn location(&self) -> &GraphemeLocation {
+        &self.location
+    }
+
+    /// Returns the index of the grapheme.
+    pub fn index(&self) -> usize {
+        self.location.index
+    }
+
+    /// Returns whether the grapheme is a line break, being `\n`, `\r\n` or `\r`.
+    pub fn is_newline(&self) -> bool {
+        is_line_break(self.as_str())
+    }
+
+    /// Returns whether every character of the grapheme is whitespace.
+    pub fn is_whitespace(&self) -> bool {
+        self.chars().all(char::is_whitespace)
+    }
+
+    /// Returns the characters of the grapheme.
+    pub fn chars(&self) -> std::str::Chars<'_> {
+        self.as_str().chars()
+    }
+
+    /// Returns the text of the grapheme, discarding its location.
+    pub fn into_text(self) -> SmallString {
+        self.text
+    }
+
+    /// Splits the grapheme into its location and text.
+    pub fn into_parts(self) -> (GraphemeLocation, SmallString) {
+        (self.location, self.text)
+    }
+}
+
+impl std::ops::Deref for Grapheme {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Grapheme {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<str> for Grapheme {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Grapheme {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl std::fmt::Display for Grapheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 /// A wrapper struct to simplify the utilization of the enumerated multipeek grapheme iterator
 /// that is utilized for lexing.
 pub struct Graphemes<'a> {
-    iter: MultiPeek<unicode_reader::Graphemes<Chars<Box<dyn Read + 'a>>>>,
-    successful_reads: usize,
+    iter: MultiPeek<Clusters<CharIter<'a>>>,
+    position: PositionTracker,
+    peek_position: PositionTracker,
     failed_reads: usize,
-    line: usize,
-    line_offset: usize,
+    bytes_read: usize,
+    input_bytes: usize,
     invalid_bytes: Rc<RefCell<usize>>,
+    raw_lengths: RawLengths,
+    source: Option<SourceRecorder>,
+    include: Option<Box<(Graphemes<'a>, Option<SourceId>)>>,
+    #[cfg(feature = "profiling")]
+    profile: super::Profile,
 }
 
 impl<'a> Graphemes<'a> {
     pub fn new<Reader: Read + 'a>(reader: Reader, is_lossy: bool) -> Self {
         let invalid_bytes = Rc::new(RefCell::new(0));
+        let raw_lengths = RawLengths::default();
+        let chars = Chars::new(reader, is_lossy, Some(invalid_bytes.clone()))
+            .record_raw_lengths(raw_lengths.clone());
+        Self::from_char_iter(Box::new(chars), invalid_bytes, raw_lengths)
+    }
+
+    /// Create a stream from chunks of already decoded text, such as the chunks of a rope.
+    ///
+    /// Graphemes that span multiple chunks are joined together.
+    pub fn from_chunks<I: IntoIterator<Item = &'a str> + 'a>(chunks: I) -> Self {
+        let chars = chunks.into_iter().flat_map(str::chars).map(Ok);
+        Self::from_char_iter(
+            Box::new(chars),
+            Rc::new(RefCell::new(0)),
+            RawLengths::default(),
+        )
+    }
+
+    fn from_char_iter(
+        chars: CharIter<'a>,
+        invalid_bytes: Rc<RefCell<usize>>,
+        raw_lengths: RawLengths,
+    ) -> Self {
         Self {
-            iter: unicode_reader::Graphemes::from(Chars::new(
-                Box::new(reader) as Box<dyn Read>,
-                is_lossy,
-                Some(invalid_bytes.clone()),
-            ))
-            .multipeek(),
-            successful_reads: 0,
+            iter: Clusters::new(chars).multipeek(),
+            position: PositionTracker::new(),
+            peek_position: PositionTracker::new(),
             failed_reads: 0,
-            line: 0,
-            line_offset: 0,
-            invalid_bytes: invalid_bytes.clone(),
+            bytes_read: 0,
+            input_bytes: 0,
+            invalid_bytes,
+            raw_lengths,
+            source: None,
+            include: None,
+            #[cfg(feature = "profiling")]
+            profile: super::Profile::default(),
         }
     }
 
@@ -137,36 +370,165 @@ impl<'a> Graphemes<'a> {
         Self::new(reader, true)
     }
 
-    pub fn peek<'b>(
-        &'b mut self,
-    ) -> Option<Result<(GraphemeLocation, &'b String), (usize, &'b Error)>> {
-        let index = self.current_index() + 1;
+    /// Peeks at the grapheme after the last one peeked at, or at the next grapheme if the peek
+    /// cursor was reset. Its location is tracked in the same way as [Graphemes::next] would.
+    pub fn peek(&mut self) -> Option<Result<Grapheme, (usize, &Error)>> {
+        #[cfg(feature = "profiling")]
+        {
+            self.profile.peeks += 1;
+        }
+        let index = self.peek_position.index();
         match self.iter.peek() {
             Some(Ok(grapheme)) => {
-                let location = GraphemeLocation::new(index, self.line, self.line_offset);
-                Some(Ok((location, grapheme)))
+                let location = self.peek_position.advance(grapheme);
+                Some(Ok(Grapheme::new(grapheme.clone(), location)))
             }
             Some(Err(error)) => Some(Err((index, error))),
             None => None,
         }
     }
 
+    /// Peeks at the next grapheme like [Graphemes::peek], except that a grapheme that failed to be
+    /// read is returned as a [LexError], so that it can be propagated rather than mistaken for the
+    /// end of the stream.
+    ///
+    /// The peek cursor is reset when an error is returned.
+    pub fn try_peek(&mut self) -> Result<Option<Grapheme>, LexError> {
+        let error = match self.peek() {
+            Some(Ok(grapheme)) => return Ok(Some(grapheme)),
+            Some(Err((index, error))) => {
+                LexError::other_indexed(index, Error::new(error.kind(), error.to_string()))
+            }
+            None => return Ok(None),
+        };
+        self.reset_peek();
+        Err(error)
+    }
+
     pub fn reset_peek(&mut self) {
-        self.iter.reset_peek()
+        self.iter.reset_peek();
+        self.peek_position = self.position.clone();
     }
 
-    pub fn inner(&self) -> &MultiPeek<unicode_reader::Graphemes<Chars<Box<dyn Read + 'a>>>> {
+    pub fn inner(&self) -> &MultiPeek<Clusters<CharIter<'a>>> {
         &self.iter
     }
 
-    pub fn inner_mut(
-        &mut self,
-    ) -> &mut MultiPeek<unicode_reader::Graphemes<Chars<Box<dyn Read + 'a>>>> {
+    pub fn inner_mut(&mut self) -> &mut MultiPeek<Clusters<CharIter<'a>>> {
         &mut self.iter
     }
 
+    /// Consume graphemes until the text read ends with `delimiter`, returning everything
+    /// that came before it. The delimiter itself is consumed but not included.
+    ///
+    /// This lets a tokenizer drop out of grapheme-by-grapheme lexing for things such as heredocs
+    /// or raw blocks, while the line and index counters continue to be updated so the resulting
+    /// token's range remains accurate.
+    ///
+    /// If the stream ends before `delimiter` is found, [LexError::UnexpectedEndOfStream] is returned.
+    pub fn raw_read_until(&mut self, delimiter: &str) -> Result<String, LexError> {
+        let mut read = String::new();
+        if delimiter.is_empty() {
+            return Ok(read);
+        }
+
+        loop {
+            match self.next() {
+                Some(Ok(grapheme)) => {
+                    read.push_str(&grapheme);
+                    if read.ends_with(delimiter) {
+                        read.truncate(read.len() - delimiter.len());
+                        return Ok(read);
+                    }
+                }
+                Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
+                None => return Err(LexError::UnexpectedEndOfStream),
+            }
+        }
+    }
+
+    /// Consume graphemes until the `close` that balances an `open` which was just read, returning
+    /// everything between them along with the span of the whole block, including both delimiters.
+    ///
+    /// If `allow_nesting` is set, every `open` within the block must be balanced by its own `close`,
+    /// so `consume_balanced("{", "}", true)` after reading `{` consumes `a {b} c}` and returns `a {b} c`.
+    /// Otherwise the first `close` ends the block.
+    ///
+    /// If the stream ends before the block is balanced, a [LexError::UnterminatedToken] starting at
+    /// the opening delimiter is returned.
+    pub fn consume_balanced(
+        &mut self,
+        open: &str,
+        close: &str,
+        allow_nesting: bool,
+    ) -> Result<(String, Span), LexError> {
+        let open_length = open.graphemes(true).count().max(1);
+        let start = (self.current_index() + 1).saturating_sub(open_length);
+        let mut read = String::new();
+        if close.is_empty() {
+            return Ok((read, Span::new(start, self.current_index())));
+        }
+
+        // Delimiters may not overlap, so matches are only looked for after the end of the last one.
+        let mut matched = 0;
+        let mut depth = 1;
+        loop {
+            match self.next() {
+                Some(Ok(grapheme)) => read.push_str(&grapheme),
+                Some(Err((index, error))) => return Err(LexError::other_indexed(index, error)),
+                None => {
+                    return Err(LexError::unterminated(
+                        format!("block opened by {:?}", open),
+                        Span::new(start, start + open_length - 1),
+                    ))
+                }
+            }
+
+            if read.len() - matched >= close.len() && read.ends_with(close) {
+                depth -= 1;
+                matched = read.len();
+                if depth == 0 {
+                    read.truncate(read.len() - close.len());
+                    return Ok((read, Span::new(start, self.current_index())));
+                }
+            } else if allow_nesting
+                && !open.is_empty()
+                && read.len() - matched >= open.len()
+                && read.ends_with(open)
+            {
+                depth += 1;
+                matched = read.len();
+            }
+        }
+    }
+
+    /// Consumes the upcoming graphemes if they spell out `text` exactly, returning whether they did.
+    pub fn eat(&mut self, text: &str) -> bool {
+        let mut graphemes = 0;
+        let mut matched = 0;
+        while matched < text.len() {
+            match self.iter.peek() {
+                Some(Ok(grapheme)) if text[matched..].starts_with(grapheme.as_str()) => {
+                    matched += grapheme.len();
+                    graphemes += 1;
+                }
+                _ => break,
+            }
+        }
+        self.reset_peek();
+
+        if text.is_empty() || matched != text.len() {
+            return false;
+        }
+
+        for _ in 0..graphemes {
+            self.next();
+        }
+        true
+    }
+
     pub fn successes(&self) -> usize {
-        self.successful_reads
+        self.position.index()
     }
 
     pub fn failures(&self) -> usize {
@@ -174,42 +536,134 @@ impl<'a> Graphemes<'a> {
     }
 
     pub fn attempts_total(&self) -> usize {
-        self.successful_reads + self.failed_reads
+        self.successes() + self.failed_reads
     }
 
     pub fn current_index(&self) -> usize {
-        self.successful_reads.saturating_sub(1)
+        self.successes().saturating_sub(1)
     }
 
     pub fn lines(&self) -> usize {
-        self.line + 1
+        self.position.line() + 1
+    }
+
+    /// Returns the location the next grapheme will be read at.
+    pub fn position(&self) -> GraphemeLocation {
+        self.position.location()
+    }
+
+    /// Returns the amount of columns between tab stops.
+    pub fn tab_width(&self) -> usize {
+        self.position.tab_width()
+    }
+
+    /// Sets the amount of columns between tab stops, which is one by default.
+    /// See [PositionTracker::set_tab_width].
+    pub fn set_tab_width(&mut self, tab_width: usize) {
+        self.position.set_tab_width(tab_width);
+        self.peek_position.set_tab_width(tab_width);
+    }
+
+    /// Returns the amount of bytes of decoded text that have been consumed.
+    ///
+    /// Invalid UTF-8 bytes are not included, see [Graphemes::invalid_bytes].
+    pub fn bytes_read(&self) -> usize {
+        self.bytes_read
     }
 
     pub fn invalid_bytes(&self) -> usize {
         *self.invalid_bytes.borrow()
     }
+
+    /// Returns the amount of bytes of input that have been consumed, including invalid UTF-8 bytes.
+    ///
+    /// Unlike [Graphemes::bytes_read], invalid bytes that were replaced with a U+FFFD are counted
+    /// as the bytes they were in the input, rather than as the three bytes of the U+FFFD.
+    pub fn input_bytes(&self) -> usize {
+        self.input_bytes
+    }
+
+    /// Returns the amount of input bytes behind `grapheme`, which was just consumed.
+    fn raw_len(&self, grapheme: &str) -> usize {
+        let mut raw_lengths = self.raw_lengths.borrow_mut();
+        grapheme
+            .chars()
+            .map(|character| match character {
+                '\u{FFFD}' => raw_lengths.pop_front().unwrap_or(character.len_utf8()),
+                character => character.len_utf8(),
+            })
+            .sum()
+    }
+
+    /// Sets whether consumed graphemes are stored, so that they can be retrieved
+    /// as a [SourceBuffer] with [Graphemes::take_source].
+    ///
+    /// This should be enabled before any graphemes are consumed, otherwise the grapheme
+    /// indexes of the [SourceBuffer] will not line up with those of the stream.
+    pub fn set_store_source(&mut self, store_source: bool) {
+        match (store_source, &self.source) {
+            (true, None) => self.source = Some(SourceRecorder::new()),
+            (false, Some(_)) => self.source = None,
+            _ => {}
+        }
+    }
+
+    /// Returns whether consumed graphemes are being stored.
+    pub fn stores_source(&self) -> bool {
+        self.source.is_some()
+    }
+
+    /// Requests that the lexer switch to lexing `incoming`, whose tokens will carry `source`,
+    /// once the current token has been lexed. When `incoming` is exhausted, the lexer resumes
+    /// lexing this stream.
+    ///
+    /// This allows a tokenizer to implement includes or imports. Only the last request made
+    /// while lexing a single token is honoured.
+    pub fn include(&mut self, incoming: Graphemes<'a>, source: Option<SourceId>) {
+        self.include = Some(Box::new((incoming, source)));
+    }
+
+    /// Takes the stream requested by [Graphemes::include], if there is one.
+    pub(crate) fn take_include(&mut self) -> Option<(Graphemes<'a>, Option<SourceId>)> {
+        self.include.take().map(|include| *include)
+    }
+
+    /// Returns the peeks and heap graphemes counted for this stream.
+    #[cfg(feature = "profiling")]
+    pub fn profile(&self) -> super::Profile {
+        self.profile
+    }
+
+    /// Takes the stored graphemes, if they were being stored, and stops storing them.
+    pub fn take_source(&mut self) -> Option<SourceBuffer> {
+        self.source.take().map(SourceRecorder::freeze)
+    }
 }
 
 impl Iterator for Graphemes<'_> {
-    type Item = Result<(GraphemeLocation, String), (usize, Error)>;
+    type Item = Result<Grapheme, (usize, Error)>;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.iter.next() {
             Some(Ok(grapheme)) => {
-                if grapheme == "\n" {
-                    self.line += 1;
-                    self.line_offset = 0;
-                } else {
-                    self.line_offset += 1;
+                let location = self.position.advance(&grapheme);
+                self.peek_position = self.position.clone();
+                if let Some(source) = &mut self.source {
+                    source.push(&grapheme);
+                }
+                #[cfg(feature = "profiling")]
+                if grapheme.spilled() {
+                    self.profile.heap_graphemes += 1;
                 }
-                self.successful_reads += 1;
-                let location =
-                    GraphemeLocation::new(self.current_index(), self.line, self.line_offset);
-                Some(Ok((location, grapheme)))
+                self.bytes_read += grapheme.len();
+                self.input_bytes += self.raw_len(&grapheme);
+                Some(Ok(Grapheme::new(grapheme, location)))
             }
             Some(Err(error)) => {
                 self.failed_reads += 1;
-                Some(Err((self.current_index() + 1, error)))
+                self.input_bytes += self.raw_lengths.borrow_mut().pop_front().unwrap_or(0);
+                self.peek_position = self.position.clone();
+                Some(Err((self.position.index(), error)))
             }
             None => None,
         }
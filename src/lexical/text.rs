@@ -0,0 +1,89 @@
+use std::{
+    borrow::Cow,
+    ops::{Range, RangeInclusive},
+};
+
+use super::{Graphemes, SourceBuffer};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The text of a token, stored either as a range of bytes within the source or as text of its own.
+///
+/// Storing a range rather than a [String] keeps tokens small for large token streams, with the text
+/// resolved lazily from the [SourceBuffer] the tokens were lexed from. Text that is not a verbatim
+/// slice of the source, such as an unescaped string literal, can be stored directly instead.
+pub enum TokenText<'src> {
+    /// A range of bytes within the source.
+    Source(Range<usize>),
+    /// Text that is stored in the token itself.
+    Text(Cow<'src, str>),
+}
+
+impl<'src> TokenText<'src> {
+    /// Creates text from a range of bytes within the source.
+    pub fn from_bytes(range: Range<usize>) -> Self {
+        Self::Source(range)
+    }
+
+    /// Creates text from an inclusive range of grapheme indexes, such as a token's range, within `source`.
+    ///
+    /// Returns `None` if the range does not lie within `source`.
+    pub fn from_graphemes(range: &RangeInclusive<usize>, source: &SourceBuffer) -> Option<Self> {
+        source.byte_range(range).map(Self::Source)
+    }
+
+    /// Creates text from the bytes that `incoming` has consumed since `start`, which is a value
+    /// previously returned by [Graphemes::bytes_read].
+    ///
+    /// This allows a tokenizer to record the text of the token it lexes without copying it.
+    /// The resulting range only lines up with the source if the input was valid UTF-8.
+    pub fn since(start: usize, incoming: &Graphemes) -> Self {
+        Self::Source(start..incoming.bytes_read())
+    }
+
+    /// Returns whether the text is a range within the source.
+    pub fn is_source(&self) -> bool {
+        matches!(self, Self::Source(_))
+    }
+
+    /// Returns the text as a [str], resolving a range against `source`.
+    ///
+    /// Returns `None` if the range does not lie within `source` or does not fall on character boundaries.
+    pub fn resolve<'s>(&'s self, source: &'s SourceBuffer) -> Option<&'s str> {
+        match self {
+            Self::Source(range) => source.text().get(range.clone()),
+            Self::Text(text) => Some(text),
+        }
+    }
+
+    /// Returns text that is stored in the token itself, copying it out of `source` if needed.
+    pub fn to_owned_text(&self, source: &SourceBuffer) -> Option<TokenText<'static>> {
+        self.resolve(source)
+            .map(|text| TokenText::Text(Cow::Owned(text.to_string())))
+    }
+
+    /// Converts the text into one that does not borrow, copying borrowed text if needed.
+    pub fn into_owned(self) -> TokenText<'static> {
+        match self {
+            Self::Source(range) => TokenText::Source(range),
+            Self::Text(text) => TokenText::Text(Cow::Owned(text.into_owned())),
+        }
+    }
+}
+
+impl<'src> From<&'src str> for TokenText<'src> {
+    fn from(text: &'src str) -> Self {
+        Self::Text(Cow::Borrowed(text))
+    }
+}
+
+impl From<String> for TokenText<'_> {
+    fn from(text: String) -> Self {
+        Self::Text(Cow::Owned(text))
+    }
+}
+
+impl From<Range<usize>> for TokenText<'_> {
+    fn from(range: Range<usize>) -> Self {
+        Self::Source(range)
+    }
+}
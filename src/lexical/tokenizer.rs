@@ -0,0 +1,131 @@
+use super::{
+    error::LexError,
+    stream::{GraphemeLocation, Graphemes},
+    SmallString, Token, TokenValue,
+};
+
+/// Provides a [Tokenizer] with information about the tokens lexed so far.
+///
+/// Deciding whether a token can start should usually only require constant time checks,
+/// such as looking at the last token. The full list of tokens remains available through
+/// [LexContext::tokens] for rare cases.
+pub struct LexContext<'l, TokenType: TokenValue> {
+    tokens: &'l [Token<TokenType>],
+}
+
+impl<'l, TokenType: TokenValue> LexContext<'l, TokenType> {
+    /// Create a context over the tokens lexed so far.
+    pub fn new(tokens: &'l [Token<TokenType>]) -> Self {
+        Self { tokens }
+    }
+
+    /// Returns the last token that was lexed.
+    pub fn last(&self) -> Option<&'l Token<TokenType>> {
+        self.tokens.last()
+    }
+
+    /// Returns the token `n` tokens before the last token.
+    pub fn nth_back(&self, n: usize) -> Option<&'l Token<TokenType>> {
+        self.tokens.iter().nth_back(n)
+    }
+
+    /// Returns the amount of tokens lexed so far, which is also the index the next token will have.
+    pub fn count(&self) -> usize {
+        self.tokens.len()
+    }
+
+    /// Returns every token lexed so far.
+    pub fn tokens(&self) -> &'l [Token<TokenType>] {
+        self.tokens
+    }
+}
+
+/// Represents a tokenizer.
+///
+/// `Context` is the type of the context shared by the tokenizers of a [Lexer](super::Lexer),
+/// which is passed to both methods as `context`.
+pub trait Tokenizer<TokenType: TokenValue, Context = ()> {
+    /// Returns a name identifying the tokenizer in errors and diagnostics.
+    ///
+    /// Defaults to the name of the implementing type.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Determines whether or not the given grapheme and potential next grapheme consitutes the start
+    /// of a potentially valid token. Do not store `next`, as it will be handled in the [lex](Self::lex) function.
+    ///
+    /// `lex_context` provides access to the previously lexed tokens.
+    fn can_tokenize(
+        &mut self,
+        lex_context: &LexContext<TokenType>,
+        grapheme: &str,
+        grapheme_location: &GraphemeLocation,
+        next: &Option<SmallString>,
+        context: &mut Context,
+    ) -> bool;
+    /// Given [can_tokenize](Sel::can_tokenize) evaluates to `true`, this function is called.
+    ///
+    /// It provides access to an immutable reference to the previous tokens, `tokens`.
+    /// Tokens pushed onto `tokens` are stored before the returned token, whose range then starts
+    /// after the range of the last pushed token.
+    ///
+    /// `grapheme` is the grapheme that [can_tokenize](Self::can_tokenize) accepted, which has already
+    /// been consumed from `incoming`, and `grapheme_location` is its location.
+    /// As `grapheme` has already been consumed, the lexer always makes progress, even if `lex`
+    /// consumes nothing further, so a tokenizer cannot cause the lexer to loop on the same position.
+    ///
+    /// It also provides access to a mutable reference to the incoming stream, `incoming`.
+    /// This stream is a stream of Unicode graphemes, from an underlying UTF-8 stream.
+    /// Meaning rather than relying on singular characters, which doesn't include items
+    /// such as emojis.
+    fn lex<'a, 'b>(
+        &'b mut self,
+        tokens: &'b mut Vec<Token<TokenType>>,
+        grapheme: &str,
+        grapheme_location: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        context: &mut Context,
+    ) -> Result<TokenType, LexError>;
+
+    /// Clears any state left behind by lexing a previous token.
+    ///
+    /// A [Lexer](super::Lexer) creates a new tokenizer for each token, so this is only called on
+    /// the tokenizers of a [LexerFactory](super::LexerFactory), which are created once for each lexer and reset
+    /// before each token instead. Does nothing by default.
+    fn reset(&mut self) {}
+}
+
+impl<TokenType: TokenValue, Context, T: Tokenizer<TokenType, Context> + ?Sized>
+    Tokenizer<TokenType, Context> for Box<T>
+{
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn can_tokenize(
+        &mut self,
+        lex_context: &LexContext<TokenType>,
+        grapheme: &str,
+        grapheme_location: &GraphemeLocation,
+        next: &Option<SmallString>,
+        context: &mut Context,
+    ) -> bool {
+        (**self).can_tokenize(lex_context, grapheme, grapheme_location, next, context)
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        tokens: &'b mut Vec<Token<TokenType>>,
+        grapheme: &str,
+        grapheme_location: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        context: &mut Context,
+    ) -> Result<TokenType, LexError> {
+        (**self).lex(tokens, grapheme, grapheme_location, incoming, context)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
@@ -1,4 +1,22 @@
+/// Module that stores a flag for cancelling lexing or parsing.
+pub mod cancel;
+/// Module that stores miette integration for lexer and parser errors.
+#[cfg(feature = "miette")]
+pub mod diagnostics;
+/// Module that stores example grammars built on the lexer and parser.
+#[cfg(feature = "examples")]
+pub mod examples;
+/// Module that stores a JSON lexer and parser.
+#[cfg(feature = "json")]
+pub mod json;
 /// Module that stores types and methods for lexical analysis.
+#[cfg(feature = "lexical")]
 pub mod lexical;
-// Module that stores types and methods for parsing.
-//pub mod parsing;
+/// Module that stores types and methods for parsing.
+#[cfg(feature = "parsing")]
+pub mod parsing;
+/// Module that stores helpers for testing lexer and parser output.
+#[cfg(feature = "lexical")]
+pub mod testing;
+/// Module that stores the token types shared by the lexer and parser.
+pub mod token;
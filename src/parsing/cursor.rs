@@ -0,0 +1,510 @@
+use std::{
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    cancel::CancelFlag,
+    token::{Kind, Token, TokenKind, TokenValue},
+};
+
+use super::error::{Label, ParseError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// A saved position of a [ParseCursor], used to backtrack or to compute the range
+/// of the tokens consumed since.
+pub struct Marker(usize);
+
+/// A parser that is one of several alternatives passed to [ParseCursor::any_of] or [ParseCursor::longest_of].
+pub type Alternative<'p, 't, TokenType, Output> =
+    &'p mut dyn FnMut(&mut ParseCursor<'t, TokenType>) -> Result<Output, ParseError>;
+
+/// The value parsed by [ParseCursor::validate] being checked, along with where it was parsed from.
+///
+/// Problems that should not fail the parse can be recorded with [Validation::warn], which
+/// reports them to the cursor as non-fatal errors.
+pub struct Validation {
+    position: usize,
+    range: Option<RangeInclusive<usize>>,
+    warnings: Vec<ParseError>,
+}
+
+impl Validation {
+    /// Returns the index of the first token of the value.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns the grapheme range of the value, if any of its tokens have a range.
+    pub fn range(&self) -> Option<&RangeInclusive<usize>> {
+        self.range.as_ref()
+    }
+
+    /// Records a non-fatal problem with the value.
+    pub fn warn<S: Into<String>>(&mut self, message: S) {
+        let error = ParseError::custom(self.position, self.range.clone(), message);
+        self.warnings.push(error)
+    }
+
+    /// Creates the error returned when the value is rejected.
+    fn reject(&self, message: String) -> ParseError {
+        ParseError::custom(self.position, self.range.clone(), message)
+    }
+}
+
+/// A cursor over lexed tokens, meant for writing recursive descent parsers by hand.
+///
+/// Non-fatal errors can be reported to the cursor with [ParseCursor::report], and retrieved
+/// afterwards with [ParseCursor::errors].
+///
+/// Parsing can be made cancellable by giving the cursor a [CancelFlag] with
+/// [ParseCursor::cancel_flag], and calling [ParseCursor::check_cancelled] between parsers.
+///
+/// Similarly, a parser can be stopped from hanging on pathological input by giving the cursor
+/// a budget with [ParseCursor::step_limit] or [ParseCursor::time_limit], and calling
+/// [ParseCursor::step] at the start of each rule. Recursive rules can be kept from overflowing the
+/// stack on deeply nested input by giving the cursor a [ParseCursor::depth_limit], and parsing
+/// nested values through [ParseCursor::nested].
+pub struct ParseCursor<'t, TokenType: TokenValue> {
+    tokens: &'t [Token<TokenType>],
+    position: usize,
+    errors: Vec<ParseError>,
+    cancel_flag: Option<CancelFlag>,
+    steps: usize,
+    step_limit: Option<usize>,
+    deadline: Option<Instant>,
+    depth: usize,
+    depth_limit: Option<usize>,
+}
+
+impl<'t, TokenType: TokenValue> ParseCursor<'t, TokenType> {
+    /// Create a cursor at the start of `tokens`.
+    pub fn new(tokens: &'t [Token<TokenType>]) -> Self {
+        Self {
+            tokens,
+            position: 0,
+            errors: vec![],
+            cancel_flag: None,
+            steps: 0,
+            step_limit: None,
+            deadline: None,
+            depth: 0,
+            depth_limit: None,
+        }
+    }
+
+    /// Parses all of `tokens` with `parser` into a plain value, failing if any tokens are left over.
+    ///
+    /// This suits parsing small inputs, such as configuration strings, directly into a struct.
+    pub fn parse_all<O, F>(tokens: &'t [Token<TokenType>], parser: F) -> Result<O, ParseError>
+    where
+        F: FnOnce(&mut Self) -> Result<O, ParseError>,
+    {
+        let mut cursor = Self::new(tokens);
+        let output = parser(&mut cursor)?;
+        cursor.expect_end()?;
+        Ok(output)
+    }
+
+    /// Set the maximum amount of calls to [ParseCursor::step] and return self.
+    pub fn step_limit(mut self, step_limit: usize) -> Self {
+        self.step_limit = Some(step_limit);
+        self
+    }
+
+    /// Set the maximum amount of time, starting now, after which [ParseCursor::step] fails and return self.
+    pub fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.deadline = Instant::now().checked_add(time_limit);
+        self
+    }
+
+    /// Returns the amount of calls to [ParseCursor::step] so far.
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    /// Records that the parser is invoking the rule described by `rule`, returning an error at
+    /// the next token naming the rule if the step or time limit has been exceeded.
+    pub fn step(&mut self, rule: &str) -> Result<(), ParseError> {
+        self.steps += 1;
+        let exceeded = match (self.step_limit, self.deadline) {
+            (Some(step_limit), _) if self.steps > step_limit => {
+                format!("more than {} parsing steps", step_limit)
+            }
+            (_, Some(deadline)) if Instant::now() >= deadline => "the parsing time limit".into(),
+            _ => return Ok(()),
+        };
+
+        Err(self.error(format!(
+            "Parsing exceeded {} while parsing {}",
+            exceeded, rule
+        )))
+    }
+
+    /// Set the maximum depth of calls to [ParseCursor::nested] and return self.
+    pub fn depth_limit(mut self, depth_limit: usize) -> Self {
+        self.depth_limit = Some(depth_limit);
+        self
+    }
+
+    /// Returns the depth of the calls to [ParseCursor::nested] that are being parsed.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Parses a value nested within another with `parser`, such as the elements of an array,
+    /// returning an error at the next token naming `rule` if the depth limit would be exceeded.
+    ///
+    /// The error can be told apart with [ParseError::is_max_depth_exceeded].
+    pub fn nested<O, F>(&mut self, rule: &str, parser: F) -> Result<O, ParseError>
+    where
+        F: FnOnce(&mut Self) -> Result<O, ParseError>,
+    {
+        if let Some(depth_limit) = self.depth_limit {
+            if self.depth >= depth_limit {
+                let range = self.peek().and_then(|token| token.range().cloned());
+                return Err(self.at_next(ParseError::max_depth_exceeded(
+                    self.position,
+                    range,
+                    depth_limit,
+                    rule,
+                )));
+            }
+        }
+
+        self.depth += 1;
+        let output = parser(self);
+        self.depth -= 1;
+        output
+    }
+
+    /// Set the flag checked by [ParseCursor::check_cancelled] and return self.
+    pub fn cancel_flag(mut self, cancel_flag: CancelFlag) -> Self {
+        self.cancel_flag = Some(cancel_flag);
+        self
+    }
+
+    /// Returns an error at the next token if the cursor's [CancelFlag] has been cancelled.
+    pub fn check_cancelled(&self) -> Result<(), ParseError> {
+        match &self.cancel_flag {
+            Some(cancel_flag) if cancel_flag.is_cancelled() => {
+                let range = self.peek().and_then(|token| token.range().cloned());
+                Err(self.at_next(ParseError::cancelled(self.position, range)))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the index of the next token.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Returns all of the tokens.
+    pub fn tokens(&self) -> &'t [Token<TokenType>] {
+        self.tokens
+    }
+
+    /// Returns the tokens that have not been consumed.
+    pub fn remaining(&self) -> &'t [Token<TokenType>] {
+        self.tokens.get(self.position..).unwrap_or_default()
+    }
+
+    /// Returns the tokens within `range`.
+    pub fn slice(&self, range: std::ops::Range<usize>) -> Option<&'t [Token<TokenType>]> {
+        self.tokens.get(range)
+    }
+
+    /// Returns whether every token has been consumed.
+    pub fn is_at_end(&self) -> bool {
+        self.position >= self.tokens.len()
+    }
+
+    /// Returns the next token without consuming it.
+    pub fn peek(&self) -> Option<&'t Token<TokenType>> {
+        self.peek_nth(0)
+    }
+
+    /// Returns the token `n` tokens ahead of the next token without consuming anything.
+    pub fn peek_nth(&self, n: usize) -> Option<&'t Token<TokenType>> {
+        self.tokens.get(self.position + n)
+    }
+
+    /// Returns whether the next token satisfies `predicate`.
+    pub fn at<F: FnOnce(&TokenType) -> bool>(&self, predicate: F) -> bool {
+        self.peek().is_some_and(|token| predicate(token.token()))
+    }
+
+    /// Consumes and returns the next token.
+    pub fn bump(&mut self) -> Option<&'t Token<TokenType>> {
+        let token = self.tokens.get(self.position)?;
+        self.position += 1;
+        Some(token)
+    }
+
+    /// Consumes and returns the next token if it satisfies `predicate`.
+    pub fn eat<F: FnOnce(&TokenType) -> bool>(
+        &mut self,
+        predicate: F,
+    ) -> Option<&'t Token<TokenType>> {
+        if self.at(predicate) {
+            self.bump()
+        } else {
+            None
+        }
+    }
+
+    /// Consumes and returns the next token if it satisfies `predicate`, otherwise returns an error
+    /// stating that `expected` was expected.
+    pub fn expect<F: FnOnce(&TokenType) -> bool, L: Into<Label>>(
+        &mut self,
+        predicate: F,
+        expected: L,
+    ) -> Result<&'t Token<TokenType>, ParseError> {
+        match self.eat(predicate) {
+            Some(token) => Ok(token),
+            None => Err(self.unexpected([expected])),
+        }
+    }
+
+    /// Returns an error at the next token if any tokens have not been consumed.
+    pub fn expect_end(&self) -> Result<(), ParseError> {
+        match self.is_at_end() {
+            true => Ok(()),
+            false => Err(self.unexpected([Label::End])),
+        }
+    }
+
+    /// Creates an error at the next token stating that one of `expected` was expected.
+    pub fn unexpected<L: Into<Label>>(&self, expected: impl IntoIterator<Item = L>) -> ParseError {
+        let token = self.peek();
+        self.at_next(ParseError::unexpected(
+            self.position,
+            token.and_then(|token| token.range().cloned()),
+            expected,
+            token.map(|token| token.token().display_name()),
+        ))
+    }
+
+    /// Creates an error at the next token with a custom message.
+    pub fn error<S: Into<String>>(&self, message: S) -> ParseError {
+        let range = self.peek().and_then(|token| token.range().cloned());
+        self.at_next(ParseError::custom(self.position, range, message))
+    }
+
+    /// Places an error created at the next token at its position if the token covers no graphemes,
+    /// such as an end of file token.
+    fn at_next(&self, error: ParseError) -> ParseError {
+        match self.peek().filter(|token| token.is_zero_width()) {
+            Some(token) => error.with_empty_span(*token.range_raw().start()),
+            None => error,
+        }
+    }
+
+    /// Stores a non-fatal error.
+    pub fn report(&mut self, error: ParseError) {
+        self.errors.push(error)
+    }
+
+    /// Returns the reported errors.
+    pub fn errors(&self) -> &[ParseError] {
+        &self.errors
+    }
+
+    /// Returns the reported errors and consumes `self`.
+    pub fn take_errors(self) -> Vec<ParseError> {
+        self.errors
+    }
+
+    /// Saves the current position.
+    pub fn mark(&self) -> Marker {
+        Marker(self.position)
+    }
+
+    /// Returns to a saved position.
+    pub fn reset(&mut self, marker: Marker) {
+        self.position = marker.0
+    }
+
+    /// Returns the tokens consumed since `marker`.
+    pub fn consumed_since(&self, marker: Marker) -> &'t [Token<TokenType>] {
+        self.tokens.get(marker.0..self.position).unwrap_or_default()
+    }
+
+    /// Returns the grapheme range covering the tokens consumed since `marker`.
+    ///
+    /// Tokens without a range are ignored. If no consumed token has a range, `None` is returned.
+    pub fn range_since(&self, marker: Marker) -> Option<RangeInclusive<usize>> {
+        let mut ranges = self
+            .consumed_since(marker)
+            .iter()
+            .filter_map(|token| token.range());
+        let first = ranges.next()?;
+        let last = ranges.last().unwrap_or(first);
+        Some(*first.start()..=*last.end())
+    }
+
+    /// Runs `parser`, then runs the semantic `check` on its value, such as rejecting an integer
+    /// literal that overflows.
+    ///
+    /// If `check` returns an error message, the parse fails with an error covering the value's
+    /// tokens. Warnings recorded with [Validation::warn] are reported to the cursor either way.
+    pub fn validate<O, P, C>(&mut self, parser: P, check: C) -> Result<O, ParseError>
+    where
+        P: FnOnce(&mut Self) -> Result<O, ParseError>,
+        C: FnOnce(&O, &mut Validation) -> Result<(), String>,
+    {
+        let start = self.mark();
+        let output = parser(self)?;
+        let mut validation = Validation {
+            position: start.0,
+            range: self.range_since(start),
+            warnings: vec![],
+        };
+
+        let result = check(&output, &mut validation);
+        self.errors.append(&mut validation.warnings);
+        match result {
+            Ok(()) => Ok(output),
+            Err(message) => Err(validation.reject(message)),
+        }
+    }
+
+    /// Tries each parser in `alternatives` in order from the current position, and commits to the first
+    /// one that succeeds.
+    ///
+    /// Errors reported by alternatives that failed are discarded. If every alternative fails, the furthest
+    /// failure is returned, see [ParseError::furthest].
+    pub fn any_of<O>(
+        &mut self,
+        alternatives: &mut [Alternative<'_, 't, TokenType, O>],
+    ) -> Result<O, ParseError> {
+        let start = self.mark();
+        let reported = self.errors.len();
+        let mut failures = vec![];
+
+        for alternative in alternatives.iter_mut() {
+            match alternative(self) {
+                Ok(output) => return Ok(output),
+                Err(error) => failures.push(error),
+            }
+            self.errors.truncate(reported);
+            self.reset(start);
+        }
+
+        Err(self.furthest_failure(failures))
+    }
+
+    fn furthest_failure(&self, failures: Vec<ParseError>) -> ParseError {
+        ParseError::furthest(failures).unwrap_or_else(|| self.error("No alternatives to parse"))
+    }
+
+    /// Tries every parser in `alternatives` from the current position, and commits to the successful
+    /// one that consumed the most tokens, preferring the earliest on a tie.
+    ///
+    /// This is for grammars where committing to the first successful alternative would silently
+    /// pick the wrong parse. In debug builds, a tie between successful alternatives is reported
+    /// to the cursor as an ambiguity.
+    ///
+    /// Errors reported by alternatives that were not chosen are discarded. If every alternative fails,
+    /// the furthest failure is returned, see [ParseError::furthest].
+    pub fn longest_of<O>(
+        &mut self,
+        alternatives: &mut [Alternative<'_, 't, TokenType, O>],
+    ) -> Result<O, ParseError> {
+        let start = self.mark();
+        let reported = self.errors.len();
+        let mut longest: Option<(O, usize, Vec<ParseError>)> = None;
+        let mut ambiguous = false;
+        let mut failures = vec![];
+
+        for alternative in alternatives.iter_mut() {
+            let result = alternative(self);
+            let errors = self.errors.split_off(reported);
+            match result {
+                Ok(output) => match &longest {
+                    Some((_, position, _)) if self.position < *position => {}
+                    Some((_, position, _)) if self.position == *position => ambiguous = true,
+                    _ => {
+                        ambiguous = false;
+                        longest = Some((output, self.position, errors));
+                    }
+                },
+                Err(error) => failures.push(error),
+            }
+            self.reset(start);
+        }
+
+        match longest {
+            Some((output, position, errors)) => {
+                if cfg!(debug_assertions) && ambiguous {
+                    self.report(self.error(
+                        "Ambiguous parse, as multiple alternatives consumed the same tokens",
+                    ));
+                }
+                self.position = position;
+                self.errors.extend(errors);
+                Ok(output)
+            }
+            None => Err(self.furthest_failure(failures)),
+        }
+    }
+}
+
+impl<'t, TokenType: TokenKind> ParseCursor<'t, TokenType> {
+    /// Returns whether the next token is of the kind `kind`.
+    pub fn at_kind(&self, kind: Kind) -> bool {
+        self.at(|token| token.kind() == kind)
+    }
+
+    /// Consumes and returns the next token if it is of the kind `kind`, otherwise returns an error
+    /// stating that the kind was expected.
+    pub fn expect_kind(&mut self, kind: Kind) -> Result<&'t Token<TokenType>, ParseError> {
+        self.expect(|token| token.kind() == kind, kind.describe())
+    }
+}
+
+/// Consumes the next token of a [ParseCursor] if its value matches a pattern.
+///
+/// With only a pattern, the consumed [Token] is returned as an [Option]. With a pattern followed by
+/// `=> output`, the bindings of the pattern can be used to produce a typed output instead, which is
+/// returned as an [Option]. Patterns may have an `if` guard.
+///
+/// ```
+/// # use parsley_rs::{token_matches, token::{Token, TokenValue}, parsing::ParseCursor};
+/// #[derive(Debug, Clone, PartialEq)]
+/// enum Tok {
+///     Ident(String),
+///     Plus,
+/// }
+///
+/// impl TokenValue for Tok {}
+///
+/// let tokens = vec![
+///     Token::new(Tok::Ident("a".into()), Some(0..=0)),
+///     Token::new(Tok::Plus, Some(1..=1)),
+/// ];
+/// let mut cursor = ParseCursor::new(&tokens);
+///
+/// assert!(token_matches!(&mut cursor, Tok::Plus).is_none());
+/// assert_eq!(token_matches!(&mut cursor, Tok::Ident(name) => name.as_str()), Some("a"));
+/// assert!(token_matches!(&mut cursor, Tok::Plus).is_some());
+/// ```
+#[macro_export]
+macro_rules! token_matches {
+    ($cursor:expr, $pattern:pat $(if $guard:expr)? => $output:expr) => {{
+        let cursor: &mut $crate::parsing::ParseCursor<_> = $cursor;
+        match cursor.peek().map(|token| token.token()) {
+            ::std::option::Option::Some($pattern) $(if $guard)? => {
+                let output = $output;
+                cursor.bump();
+                ::std::option::Option::Some(output)
+            }
+            _ => ::std::option::Option::None,
+        }
+    }};
+    ($cursor:expr, $pattern:pat $(if $guard:expr)?) => {{
+        let cursor: &mut $crate::parsing::ParseCursor<_> = $cursor;
+        cursor.eat(|token| matches!(token, $pattern $(if $guard)?))
+    }};
+}
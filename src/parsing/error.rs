@@ -0,0 +1,260 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    ops::{Range, RangeInclusive},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// Something that was expected by a parser, as returned by [ParseError::expected].
+///
+/// Labels are ordered by variant and then by text, which is the order expected sets are kept in.
+pub enum Label {
+    /// A token written exactly as it appears in the input, such as `+`, which is displayed within backticks.
+    Literal(String),
+    /// A description of a token or construct, such as `identifier`.
+    Named(String),
+    /// The end of the tokens.
+    End,
+}
+
+impl Label {
+    /// Creates a [Label::Literal].
+    pub fn literal<S: Into<String>>(literal: S) -> Self {
+        Self::Literal(literal.into())
+    }
+
+    /// Returns the text of the label, without backticks.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Label::Literal(text) | Label::Named(text) => text,
+            Label::End => "the end of the tokens",
+        }
+    }
+}
+
+impl From<&str> for Label {
+    fn from(name: &str) -> Self {
+        Self::Named(name.to_string())
+    }
+}
+
+impl From<String> for Label {
+    fn from(name: String) -> Self {
+        Self::Named(name)
+    }
+}
+
+impl PartialEq<&str> for Label {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Display for Label {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Label::Literal(literal) => write!(f, "`{}`", literal),
+            _ => write!(f, "{}", self.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Represents an error that occurs when parsing.
+///
+/// With the `miette` feature, a [ParseError] implements `miette::Diagnostic` with only a code, as
+/// it does not hold the source text. To report it with the source text and a label at its location,
+/// wrap it in a [SourceReport](crate::diagnostics::SourceReport).
+pub struct ParseError {
+    position: usize,
+    range: Option<RangeInclusive<usize>>,
+    // Whether the error lies at the empty span before the start of `range`, such as at the end of the input.
+    empty_span: bool,
+    expected: Vec<Label>,
+    found: Option<String>,
+    message: Option<String>,
+    cancelled: bool,
+    max_depth_exceeded: bool,
+}
+
+impl ParseError {
+    /// Creates an error stating that one of `expected` was expected at the token index `position`,
+    /// but `found` was found instead. If `found` is `None`, the end of the tokens was reached.
+    ///
+    /// `found` is usually the [display name](crate::token::TokenValue::display_name) of a token.
+    ///
+    /// `range` is the grapheme range of the offending token, if there is one. The expected labels
+    /// are sorted and deduplicated, so the error does not depend on the order they were given in.
+    pub fn unexpected<L: Into<Label>>(
+        position: usize,
+        range: Option<RangeInclusive<usize>>,
+        expected: impl IntoIterator<Item = L>,
+        found: Option<String>,
+    ) -> Self {
+        let mut expected: Vec<Label> = expected.into_iter().map(Into::into).collect();
+        expected.sort();
+        expected.dedup();
+
+        Self {
+            position,
+            range,
+            empty_span: false,
+            expected,
+            found,
+            message: None,
+            cancelled: false,
+            max_depth_exceeded: false,
+        }
+    }
+
+    /// Creates an error with a custom message at the token index `position`.
+    pub fn custom<S: Into<String>>(
+        position: usize,
+        range: Option<RangeInclusive<usize>>,
+        message: S,
+    ) -> Self {
+        Self {
+            position,
+            range,
+            empty_span: false,
+            expected: vec![],
+            found: None,
+            message: Some(message.into()),
+            cancelled: false,
+            max_depth_exceeded: false,
+        }
+    }
+
+    /// Creates an error stating that parsing was stopped at the token index `position`
+    /// because its [CancelFlag](crate::cancel::CancelFlag) was cancelled.
+    pub fn cancelled(position: usize, range: Option<RangeInclusive<usize>>) -> Self {
+        Self {
+            cancelled: true,
+            ..Self::custom(position, range, "Parsing was cancelled")
+        }
+    }
+
+    /// Creates an error stating that parsing `rule` at the token index `position` would nest
+    /// values deeper than `max`, as returned by [ParseCursor::nested](super::ParseCursor::nested).
+    pub fn max_depth_exceeded(
+        position: usize,
+        range: Option<RangeInclusive<usize>>,
+        max: usize,
+        rule: &str,
+    ) -> Self {
+        let message = format!("Parsing {} exceeded the maximum depth of {}", rule, max);
+        Self {
+            max_depth_exceeded: true,
+            ..Self::custom(position, range, message)
+        }
+    }
+
+    /// Returns the error that occurred furthest into the tokens, which is usually the most helpful
+    /// error when every alternative of a choice has failed.
+    ///
+    /// The expected sets of the errors that occurred at that position are merged, sorted and
+    /// without duplicates. Returns `None` if there are no errors.
+    pub fn furthest(errors: impl IntoIterator<Item = ParseError>) -> Option<Self> {
+        let mut furthest: Option<Self> = None;
+        for error in errors {
+            match &mut furthest {
+                Some(current) if error.position < current.position => {}
+                Some(current) if error.position == current.position => {
+                    current.expected.extend(error.expected);
+                    current.expected.sort();
+                    current.expected.dedup();
+                }
+                _ => furthest = Some(error),
+            }
+        }
+
+        furthest
+    }
+
+    /// Returns the index of the token at which the error occurred.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Sets the error to lie at the empty span before the grapheme index `position`, such as
+    /// the end of the input, and returns self. This replaces the range of the error.
+    pub fn with_empty_span(mut self, position: usize) -> Self {
+        self.range = Some(position..=position);
+        self.empty_span = true;
+        self
+    }
+
+    /// Returns the grapheme range of the token at which the error occurred.
+    ///
+    /// Returns `None` if the error lies at an empty span, see [ParseError::grapheme_span].
+    pub fn range(&self) -> Option<&RangeInclusive<usize>> {
+        self.range.as_ref().filter(|_| !self.empty_span)
+    }
+
+    /// Returns the grapheme range of the token at which the error occurred, as an exclusive range.
+    ///
+    /// This is empty if the error occurred at a zero width token, such as an end of file token.
+    pub fn grapheme_span(&self) -> Option<Range<usize>> {
+        let range = self.range.as_ref()?;
+        match self.empty_span {
+            true => Some(*range.start()..*range.start()),
+            false => Some(*range.start()..range.end() + 1),
+        }
+    }
+
+    /// Returns what was expected instead, sorted and without duplicates.
+    pub fn expected(&self) -> &[Label] {
+        &self.expected
+    }
+
+    /// Returns what was found instead. If `None`, the end of the tokens was reached.
+    pub fn found(&self) -> Option<&str> {
+        self.found.as_deref()
+    }
+
+    /// Returns whether parsing was stopped by a [CancelFlag](crate::cancel::CancelFlag).
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    /// Returns whether parsing was stopped by the depth limit of a [ParseCursor](super::ParseCursor).
+    pub fn is_max_depth_exceeded(&self) -> bool {
+        self.max_depth_exceeded
+    }
+
+    /// Returns the custom message, if there is one.
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(message) = &self.message {
+            return write!(f, "{} (at token {})", message, self.position);
+        }
+
+        let found = self.found.as_deref().unwrap_or("the end of the tokens");
+        match self.expected.as_slice() {
+            [] => write!(f, "Unexpected {} at token {}", found, self.position),
+            [expected] => write!(
+                f,
+                "Expected {}, found {} at token {}",
+                expected, found, self.position
+            ),
+            [rest @ .., last] => write!(
+                f,
+                "Expected {} or {}, found {} at token {}",
+                rest.iter()
+                    .map(Label::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                last,
+                found,
+                self.position
+            ),
+        }
+    }
+}
+
+impl Error for ParseError {}
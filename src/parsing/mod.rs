@@ -0,0 +1,338 @@
+mod cursor;
+
+pub use cursor::*;
+
+/// Stores error types.
+pub mod error;
+
+#[cfg(test)]
+mod tests {
+    use crate::token::{Kind, Token, TokenKind, TokenValue};
+
+    use super::{
+        error::{Label, ParseError},
+        *,
+    };
+
+    #[derive(Debug, Clone, PartialEq)]
+    enum Tok {
+        Number(i64),
+        Plus,
+        Eof,
+    }
+
+    impl TokenValue for Tok {}
+
+    impl TokenKind for Tok {
+        fn kind(&self) -> Kind {
+            match self {
+                Tok::Number(_) => Kind::Literal,
+                Tok::Plus => Kind::Punctuation,
+                Tok::Eof => Kind::Other,
+            }
+        }
+    }
+
+    fn tokens() -> Vec<Token<Tok>> {
+        vec![
+            Token::new(Tok::Number(1), Some(1..=1)),
+            Token::new(Tok::Plus, Some(3..=3)),
+            Token::new(Tok::Number(20), Some(5..=6)),
+        ]
+    }
+
+    fn number(cursor: &mut ParseCursor<Tok>) -> Result<i64, error::ParseError> {
+        match cursor
+            .expect(|token| matches!(token, Tok::Number(_)), "number")?
+            .token()
+        {
+            Tok::Number(number) => Ok(*number),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_cursor() {
+        let tokens = tokens();
+        let mut cursor = ParseCursor::new(&tokens);
+        let start = cursor.mark();
+
+        let mut sum = number(&mut cursor).unwrap();
+        while cursor.eat(|token| *token == Tok::Plus).is_some() {
+            sum += number(&mut cursor).unwrap();
+        }
+
+        assert_eq!(sum, 21);
+        assert!(cursor.is_at_end());
+        assert_eq!(cursor.range_since(start), Some(1..=6));
+
+        let error = number(&mut cursor).unwrap_err();
+        assert_eq!(error.position(), 3);
+        assert_eq!(error.expected(), ["number"]);
+        assert_eq!(error.found(), None);
+
+        cursor.reset(start);
+        assert_eq!(cursor.remaining().len(), 3);
+    }
+
+    #[test]
+    fn test_error_at_end_of_input() {
+        let mut tokens = tokens();
+        tokens.push(Token::at(Tok::Eof, 7));
+        let mut cursor = ParseCursor::new(&tokens);
+        let start = cursor.mark();
+        cursor.bump();
+
+        let error = number(&mut cursor).unwrap_err();
+        assert_eq!(error.range(), Some(&(3..=3)));
+        assert_eq!(error.grapheme_span(), Some(3..4));
+
+        cursor.bump();
+        cursor.bump();
+        let error = number(&mut cursor).unwrap_err();
+        assert_eq!(error.range(), None);
+        assert_eq!(error.grapheme_span(), Some(7..7));
+        assert_eq!(cursor.range_since(start), Some(1..=6));
+    }
+
+    #[test]
+    fn test_cancellation() {
+        let tokens = tokens();
+        let cancel_flag = crate::cancel::CancelFlag::new();
+        let mut cursor = ParseCursor::new(&tokens).cancel_flag(cancel_flag.clone());
+
+        assert!(cursor.check_cancelled().is_ok());
+        cursor.bump();
+        cancel_flag.cancel();
+
+        let error = cursor.check_cancelled().unwrap_err();
+        assert!(error.is_cancelled());
+        assert_eq!(error.position(), 1);
+    }
+
+    #[test]
+    fn test_budget() {
+        fn sum(cursor: &mut ParseCursor<Tok>) -> Result<i64, error::ParseError> {
+            cursor.step("sum")?;
+            let left = number(cursor)?;
+            if cursor.eat(|token| *token == Tok::Plus).is_some() {
+                return Ok(left + sum(cursor)?);
+            }
+            Ok(left)
+        }
+
+        let tokens = tokens();
+        let mut cursor = ParseCursor::new(&tokens).step_limit(2);
+        assert_eq!(sum(&mut cursor).unwrap(), 21);
+        assert_eq!(cursor.steps(), 2);
+
+        let mut cursor = ParseCursor::new(&tokens).step_limit(1);
+        let error = sum(&mut cursor).unwrap_err();
+        assert_eq!(error.position(), 2);
+        assert!(error.message().unwrap().contains("sum"));
+
+        let mut cursor = ParseCursor::new(&tokens).time_limit(std::time::Duration::ZERO);
+        assert!(sum(&mut cursor).is_err());
+    }
+
+    #[test]
+    fn test_depth_limit() {
+        fn sum(cursor: &mut ParseCursor<Tok>) -> Result<i64, ParseError> {
+            cursor.nested("sum", |cursor| {
+                let left = number(cursor)?;
+                if cursor.eat(|token| *token == Tok::Plus).is_some() {
+                    return Ok(left + sum(cursor)?);
+                }
+                Ok(left)
+            })
+        }
+
+        let tokens = tokens();
+        let mut cursor = ParseCursor::new(&tokens).depth_limit(2);
+        assert_eq!(sum(&mut cursor), Ok(21));
+        assert_eq!(cursor.depth(), 0);
+
+        let mut cursor = ParseCursor::new(&tokens).depth_limit(1);
+        let error = sum(&mut cursor).unwrap_err();
+        assert!(error.is_max_depth_exceeded());
+        assert_eq!(error.position(), 2);
+        assert_eq!(cursor.depth(), 0);
+    }
+
+    #[test]
+    fn test_token_kinds() {
+        let tokens = tokens();
+        let mut cursor = ParseCursor::new(&tokens);
+
+        assert!(cursor.at_kind(Kind::Literal));
+        cursor.expect_kind(Kind::Literal).unwrap();
+        let error = cursor.expect_kind(Kind::Identifier).unwrap_err();
+        assert_eq!(error.expected(), ["identifier"]);
+        assert_eq!(
+            error.to_string(),
+            "Expected identifier, found Plus at token 1"
+        );
+        assert_eq!(Tok::Number(42).display_name(), "Number");
+    }
+
+    #[test]
+    fn test_longest_of() {
+        let tokens = tokens();
+        let mut cursor = ParseCursor::new(&tokens);
+        let start = cursor.mark();
+
+        let sum = cursor
+            .longest_of(&mut [&mut |cursor| number(cursor), &mut |cursor| {
+                let left = number(cursor)?;
+                cursor.expect(|token| *token == Tok::Plus, "+")?;
+                Ok(left + number(cursor)?)
+            }])
+            .unwrap();
+        assert_eq!(sum, 21);
+        assert!(cursor.is_at_end());
+        assert!(cursor.errors().is_empty());
+
+        cursor.reset(start);
+        cursor.longest_of(&mut [&mut number, &mut number]).unwrap();
+        assert_eq!(cursor.position(), 1);
+        assert_eq!(cursor.errors().len(), usize::from(cfg!(debug_assertions)));
+
+        let error = cursor
+            .longest_of(&mut [&mut number, &mut |cursor| {
+                cursor.expect(|_| false, "x").map(|_| 0)
+            }])
+            .unwrap_err();
+        assert_eq!(error.expected(), ["number", "x"]);
+    }
+
+    #[test]
+    fn test_furthest_failure() {
+        let tokens = tokens();
+        let mut cursor = ParseCursor::new(&tokens);
+
+        let error = cursor
+            .any_of(&mut [
+                &mut |cursor| {
+                    number(cursor)?;
+                    cursor.expect(|token| *token == Tok::Plus, "+")?;
+                    cursor.expect(|_| false, "identifier").map(|_| 0)
+                },
+                &mut |cursor| {
+                    number(cursor)?;
+                    cursor.expect(|_| false, "-").map(|_| 0)
+                },
+                &mut |cursor| {
+                    number(cursor)?;
+                    cursor.expect(|token| *token == Tok::Plus, "+")?;
+                    cursor.expect(|_| false, "(").map(|_| 0)
+                },
+            ])
+            .unwrap_err();
+        assert_eq!(error.position(), 2);
+        assert_eq!(error.expected(), ["(", "identifier"]);
+        assert_eq!(cursor.position(), 0);
+
+        let error = ParseError::unexpected(
+            0,
+            None,
+            [Label::End, "b".into(), Label::literal("+"), "b".into()],
+            None,
+        );
+        assert_eq!(
+            error.expected(),
+            [Label::literal("+"), "b".into(), Label::End]
+        );
+        assert_eq!(
+            error.to_string(),
+            "Expected `+`, b or the end of the tokens, found the end of the tokens at token 0"
+        );
+
+        assert_eq!(
+            cursor.any_of(&mut [
+                &mut |cursor| cursor.expect(|_| false, "x").map(|_| 0),
+                &mut number
+            ]),
+            Ok(1)
+        );
+    }
+
+    #[test]
+    fn test_parse_all() {
+        let tokens = tokens();
+
+        assert_eq!(
+            ParseCursor::parse_all(&tokens, |cursor| {
+                let left = number(cursor)?;
+                cursor.expect(|token| *token == Tok::Plus, "+")?;
+                Ok(left + number(cursor)?)
+            }),
+            Ok(21)
+        );
+
+        let error = ParseCursor::parse_all(&tokens, number).unwrap_err();
+        assert_eq!(error.position(), 1);
+        assert_eq!(error.expected(), ["the end of the tokens"]);
+    }
+
+    #[test]
+    fn test_validate() {
+        let tokens = tokens();
+        let mut cursor = ParseCursor::new(&tokens);
+
+        let small = |value: &i64, validation: &mut Validation| {
+            if *value > 10 {
+                validation.warn("Number is larger than 10");
+            }
+            match *value > 100 {
+                true => Err("Number is larger than 100".to_string()),
+                false => Ok(()),
+            }
+        };
+
+        assert_eq!(cursor.validate(number, small), Ok(1));
+        assert!(cursor.errors().is_empty());
+
+        cursor.bump();
+        assert_eq!(cursor.validate(number, small), Ok(20));
+        assert_eq!(cursor.errors().len(), 1);
+        assert_eq!(cursor.errors()[0].position(), 2);
+        assert_eq!(cursor.errors()[0].range(), Some(&(5..=6)));
+
+        let mut cursor = ParseCursor::new(&tokens[2..]);
+        let error = cursor
+            .validate(number, |value, _| match *value < 10 {
+                true => Ok(()),
+                false => Err("Expected a single digit".to_string()),
+            })
+            .unwrap_err();
+        assert_eq!(error.message(), Some("Expected a single digit"));
+        assert_eq!(error.range(), Some(&(5..=6)));
+    }
+
+    #[cfg(feature = "lexical")]
+    #[test]
+    fn test_parse_corpus() {
+        let dir = std::env::temp_dir().join(format!("parsley-corpus-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, text: &str| std::fs::write(dir.join(name), text).unwrap();
+        write("number", "42");
+        write("number.expected", "42\n");
+        write("word", "forty");
+        write("word.expected", "error: invalid digit found in string\n");
+
+        let parse = |input: &str| input.parse::<i64>();
+        crate::testing::parse_corpus_with(&dir, false, parse);
+
+        write("word.expected", "40\n");
+        let failure =
+            std::panic::catch_unwind(|| crate::testing::parse_corpus_with(&dir, false, parse));
+        let message = failure.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("word:\nexpected:\n40\nactual:\nerror: invalid digit"));
+        assert!(!message.contains("number"));
+
+        crate::testing::parse_corpus_with(&dir, true, parse);
+        let blessed = std::fs::read_to_string(dir.join("word.expected")).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(blessed, "error: invalid digit found in string\n");
+    }
+}
@@ -0,0 +1,373 @@
+use std::{
+    fmt::{Debug, Display, Write},
+    fs,
+    path::Path,
+};
+
+use crate::lexical::{
+    error::LexError, GraphemeLocation, Graphemes, LexContext, Lexer, SmallString, Token,
+    TokenValue, Tokenizer,
+};
+
+/// Renders tokens in a deterministic format, with one token per line,
+/// suitable for snapshot testing.
+///
+/// Each line consists of the token's grapheme range, or `-` if it has none,
+/// followed by the [Debug] representation of the token's value.
+pub fn dump_tokens<TokenType: TokenValue>(tokens: &[Token<TokenType>]) -> String {
+    let mut dump = String::new();
+    for token in tokens {
+        let _ = match token.range() {
+            Some(range) => writeln!(
+                dump,
+                "{}..={} {:?}",
+                range.start(),
+                range.end(),
+                token.token()
+            ),
+            None => writeln!(dump, "- {:?}", token.token()),
+        };
+    }
+
+    dump
+}
+
+/// Asserts that the values of some tokens are equal to the expected values.
+///
+/// The first argument is anything that can be iterated into references to [Token](crate::lexical::Token)s,
+/// such as a slice of tokens or a tokenized [Lexer](crate::lexical::Lexer). The second argument is a list of the expected values.
+/// Token ranges are not compared.
+///
+/// ```
+/// # use parsley_rs::{assert_tokens, lexical::{Token, TokenValue}};
+/// #[derive(Debug, Clone, PartialEq)]
+/// enum Tok {
+///     Plus,
+/// }
+///
+/// impl TokenValue for Tok {}
+///
+/// let tokens = vec![Token::new(Tok::Plus, Some(0..=0))];
+/// assert_tokens!(&tokens, [Tok::Plus]);
+/// ```
+#[macro_export]
+macro_rules! assert_tokens {
+    ($tokens:expr, [$($expected:expr),* $(,)?]) => {{
+        let actual: ::std::vec::Vec<_> = ::std::iter::IntoIterator::into_iter($tokens)
+            .map(|token| ::std::clone::Clone::clone(token.token()))
+            .collect();
+        let expected: ::std::vec::Vec<_> = ::std::vec![$($expected),*];
+        assert_eq!(actual, expected);
+    }};
+}
+
+/// Splits `text` at the lines that start with `header`, returning the rest of each such line
+/// along with the text up to the next one. The text before the first such line has no header.
+fn fixture_sections<'t>(text: &'t str, header: &str) -> Vec<(Option<&'t str>, &'t str)> {
+    let mut sections = vec![(None, 0, text.len())];
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if let Some(rest) = line.strip_prefix(header) {
+            if let Some(last) = sections.last_mut() {
+                last.2 = offset;
+            }
+            sections.push((Some(rest.trim()), offset + line.len(), text.len()));
+        }
+        offset += line.len();
+    }
+
+    sections
+        .into_iter()
+        .map(|(header, start, end)| (header, &text[start..end]))
+        .collect()
+}
+
+/// Returns whether a line of [dump_tokens] output matches an expected line of a fixture.
+///
+/// An expected value that is just a name, such as `Number`, matches any value of that variant.
+fn fixture_line_matches(expected: &str, actual: &str) -> bool {
+    if expected == actual {
+        return true;
+    }
+
+    let (expected_range, kind) = expected.split_once(' ').unwrap_or((expected, ""));
+    let (actual_range, value) = actual.split_once(' ').unwrap_or((actual, ""));
+    let is_name = !kind.is_empty()
+        && kind
+            .chars()
+            .all(|character| character.is_alphanumeric() || character == '_' || character == ':');
+    is_name
+        && expected_range == actual_range
+        && value
+            .strip_prefix(kind)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['(', ' ']))
+}
+
+/// Lexes every case of a fixture with a lexer created by `lexer`, and asserts that the tokens match
+/// the expected tokens, panicking with every mismatch otherwise.
+///
+/// A fixture consists of cases that each start with a line `=== name`. The lines after it are the
+/// input, up to a line `---`, after which each line is an expected token in the format of
+/// [dump_tokens]. The line break before `---` is not part of the input, and blank lines among the
+/// expected tokens are ignored. Rather than the full value of a token, an expected line may give
+/// the name of its variant, such as `0..=2 Number`, to only check its kind.
+///
+/// ```
+/// # use std::io::Cursor;
+/// # use parsley_rs::{lexical::{Lexer, LiteralSetTokenizer, TokenValue}, testing::lex_fixture};
+/// #[derive(Debug, Clone)]
+/// enum Tok {
+///     Increment,
+///     Decrement,
+/// }
+///
+/// impl TokenValue for Tok {}
+///
+/// let fixture = "\
+/// === operators
+/// ++--++
+/// ---
+/// 0..=1 Increment
+/// 2..=3 Decrement
+/// 4..=5 Increment
+/// ";
+///
+/// lex_fixture(fixture, |input| {
+///     let operators = LiteralSetTokenizer::new(["++", "--"], |operator| match operator {
+///         "++" => Tok::Increment,
+///         _ => Tok::Decrement,
+///     });
+///     Lexer::new(Cursor::new(input), false, None).tokenizer(move || operators.clone())
+/// });
+/// ```
+///
+/// # Panics
+///
+/// Panics if a case fails to lex or its tokens do not match, or if the fixture does not start with a case.
+pub fn lex_fixture<'f, TokenType, Context, F>(fixture: &'f str, mut lexer: F)
+where
+    TokenType: TokenValue,
+    F: FnMut(&'f str) -> Lexer<'f, TokenType, Context>,
+{
+    let mut failures = String::new();
+    for (name, case) in fixture_sections(fixture, "=== ") {
+        let name = match name {
+            Some(name) => name,
+            None if case.trim().is_empty() => continue,
+            None => panic!("A fixture must start with a line `=== name`"),
+        };
+        let (input, expected) = match fixture_sections(case, "---").as_slice() {
+            [(None, input), (Some(""), expected)] => (*input, *expected),
+            _ => {
+                let _ = writeln!(failures, "=== {}\nexpected a single `---` line", name);
+                continue;
+            }
+        };
+        let input = input
+            .strip_suffix('\n')
+            .map_or(input, |input| input.strip_suffix('\r').unwrap_or(input));
+
+        let mut lexed = lexer(input);
+        let actual = match lexed.tokenize() {
+            Ok(()) => dump_tokens(lexed.tokens()),
+            Err(error) => {
+                let _ = writeln!(failures, "=== {}\nfailed to lex: {}", name, error);
+                continue;
+            }
+        };
+
+        let expected: Vec<_> = expected
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect();
+        let actual: Vec<_> = actual.lines().collect();
+        let matches = expected.len() == actual.len()
+            && expected
+                .iter()
+                .zip(&actual)
+                .all(|(expected, actual)| fixture_line_matches(expected, actual));
+        if !matches {
+            let _ = writeln!(
+                failures,
+                "=== {}\nexpected:\n{}\nactual:\n{}",
+                name,
+                expected.join("\n"),
+                actual.join("\n")
+            );
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!("Fixture cases failed:\n{}", failures);
+    }
+}
+
+/// The environment variable that makes [parse_corpus] write the expected files instead of comparing them.
+pub const BLESS_VAR: &str = "PARSLEY_BLESS";
+
+/// Parses every file in `dir` with `parse`, and compares a dump of each result against the file of
+/// the same name with `.expected` appended, panicking with every mismatch.
+///
+/// The dump of a parsed value is its pretty-printed [Debug] representation, while the dump of an
+/// error is `error: ` followed by the error, so files that should fail to parse can be kept
+/// alongside those that should not. Files are visited in order of their names, and
+/// subdirectories and `.expected` files are not parsed.
+///
+/// If the environment variable [BLESS_VAR] is set to anything but `0`, the expected files are
+/// written from the dumps instead, such as by `PARSLEY_BLESS=1 cargo test`.
+///
+/// # Panics
+///
+/// Panics if `dir` cannot be read, if a dump does not match its expected file, or if an expected
+/// file is missing.
+pub fn parse_corpus<P, F, T, E>(dir: P, parse: F)
+where
+    P: AsRef<Path>,
+    F: FnMut(&str) -> Result<T, E>,
+    T: Debug,
+    E: Display,
+{
+    let bless = std::env::var_os(BLESS_VAR).is_some_and(|bless| bless != "0");
+    parse_corpus_with(dir, bless, parse)
+}
+
+/// Parses every file in `dir` with `parse` like [parse_corpus], but writes the expected files only
+/// if `bless` is `true`, ignoring [BLESS_VAR].
+///
+/// # Panics
+///
+/// Panics if `dir` cannot be read, or unless blessing, if a dump does not match its expected file
+/// or an expected file is missing.
+pub fn parse_corpus_with<P, F, T, E>(dir: P, bless: bool, mut parse: F)
+where
+    P: AsRef<Path>,
+    F: FnMut(&str) -> Result<T, E>,
+    T: Debug,
+    E: Display,
+{
+    let dir = dir.as_ref();
+    let mut paths: Vec<_> = fs::read_dir(dir)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect()
+        })
+        .unwrap_or_else(|error| panic!("Failed to read the corpus {}: {}", dir.display(), error));
+    paths.retain(|path| {
+        path.is_file()
+            && path
+                .extension()
+                .is_none_or(|extension| extension != "expected")
+    });
+    paths.sort();
+
+    let mut failures = String::new();
+    for path in paths {
+        let input = match fs::read_to_string(&path) {
+            Ok(input) => input,
+            Err(error) => {
+                let _ = writeln!(failures, "{}: failed to read: {}", path.display(), error);
+                continue;
+            }
+        };
+        let dump = match parse(&input) {
+            Ok(parsed) => format!("{:#?}\n", parsed),
+            Err(error) => format!("error: {}\n", error),
+        };
+
+        let mut expected_path = path.clone().into_os_string();
+        expected_path.push(".expected");
+        if bless {
+            if let Err(error) = fs::write(&expected_path, &dump) {
+                let _ = writeln!(failures, "{}: failed to bless: {}", path.display(), error);
+            }
+            continue;
+        }
+
+        match fs::read_to_string(&expected_path) {
+            Ok(expected) if expected == dump => {}
+            Ok(expected) => {
+                let _ = writeln!(
+                    failures,
+                    "{}:\nexpected:\n{}actual:\n{}",
+                    path.display(),
+                    expected,
+                    dump
+                );
+            }
+            Err(error) => {
+                let _ = writeln!(
+                    failures,
+                    "{}: failed to read the expected file: {}",
+                    path.display(),
+                    error
+                );
+            }
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!(
+            "Corpus files failed, set {}=1 to update the expected files:\n{}",
+            BLESS_VAR, failures
+        );
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FuzzToken;
+
+impl TokenValue for FuzzToken {}
+
+struct FuzzTokenizer;
+
+impl Tokenizer<FuzzToken> for FuzzTokenizer {
+    fn can_tokenize(
+        &mut self,
+        _: &LexContext<FuzzToken>,
+        _: &str,
+        _: &GraphemeLocation,
+        _: &Option<SmallString>,
+        _: &mut (),
+    ) -> bool {
+        true
+    }
+
+    fn lex<'a, 'b>(
+        &'b mut self,
+        _: &'b mut Vec<Token<FuzzToken>>,
+        grapheme: &str,
+        _: &GraphemeLocation,
+        incoming: &'b mut Graphemes<'a>,
+        _: &mut (),
+    ) -> Result<FuzzToken, LexError> {
+        if grapheme == "\"" {
+            incoming.raw_read_until("\"")?;
+        }
+
+        Ok(FuzzToken)
+    }
+}
+
+/// An entry point for fuzz targets, such as those run by `cargo fuzz`.
+///
+/// It lexes `data`, both lossily and strictly, with a tokenizer that exercises the
+/// grapheme stream, and resolves every token against the stored source. Lexing errors
+/// are expected for arbitrary input; panics are not.
+pub fn fuzz_lexer(data: &[u8]) {
+    for is_lossy in [true, false] {
+        let mut lexer = Lexer::new(data, is_lossy, Some(FuzzToken))
+            .tokenizer(|| FuzzTokenizer)
+            .store_source(true);
+        let _ = lexer.tokenize();
+
+        let (tokens, source) = lexer.into_parts();
+        for token in &tokens {
+            let _ = source.slice(token.range_raw());
+        }
+        for line in 0..source.lines() {
+            let _ = source.line(line);
+        }
+    }
+}
@@ -0,0 +1,385 @@
+mod source;
+mod span;
+
+pub use source::*;
+pub use span::*;
+
+use std::{
+    any::Any,
+    fmt::{Debug, Display},
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut, Range, RangeInclusive},
+    sync::Arc,
+};
+
+/// Trait that dictates whether a type is considered a token.
+/// Used to avoid implementation conflicts.
+pub trait TokenValue: Debug + Clone {
+    /// Defines whether the lexer should avoid pushing the current token into the token
+    /// list. This is useful for say whitespace.
+    fn should_skip(&self) -> bool {
+        false
+    }
+
+    /// Returns a short name for the token, which error messages use in place of the full
+    /// representation of the token, such as `found identifier`.
+    ///
+    /// By default, this is the name of the variant within the [Debug] representation,
+    /// such as `Number` for `Number(42)`.
+    fn display_name(&self) -> String {
+        let debug = format!("{:?}", self);
+        let end = debug
+            .find(|character: char| !(character.is_alphanumeric() || character == '_'))
+            .unwrap_or(debug.len());
+        match end {
+            0 => debug,
+            end => debug[..end].to_string(),
+        }
+    }
+
+    /// Returns the token that the convenience constructors of [Lexer](crate::lexical::Lexer), such as
+    /// [Lexer::lossy](crate::lexical::Lexer::lossy), push once the input has been lexed.
+    ///
+    /// By default, this is `None` and no end of file token is pushed.
+    fn eof() -> Option<Self> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A standard category of token, which generic tooling such as highlighters and error messages
+/// can rely on without knowing the concrete token type.
+pub enum Kind {
+    Keyword,
+    Identifier,
+    Literal,
+    Punctuation,
+    /// Tokens without meaning to the grammar, such as whitespace and comments.
+    Trivia,
+    /// Tokens produced for invalid input.
+    Error,
+    /// Tokens that do not fit any other category, such as the EOF token.
+    Other,
+}
+
+impl Kind {
+    /// Returns a lowercase description of the category, such as `"identifier"`.
+    pub fn describe(&self) -> &'static str {
+        match self {
+            Kind::Keyword => "keyword",
+            Kind::Identifier => "identifier",
+            Kind::Literal => "literal",
+            Kind::Punctuation => "punctuation",
+            Kind::Trivia => "trivia",
+            Kind::Error => "error",
+            Kind::Other => "other",
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.describe())
+    }
+}
+
+/// An optional trait for token types that can be sorted into a standard [Kind].
+pub trait TokenKind: TokenValue {
+    /// Returns the category of the token.
+    fn kind(&self) -> Kind;
+}
+
+#[derive(Clone)]
+/// Represents a lexical token.
+/// It has an inclusive range dictating the grapheme indexes that the token was lexed from.
+///
+/// `TokenType` dictates the value of the token, and this is likely to be an enum provided by the developer.
+///
+/// It can be dereferenced into it's `TokenType` both mutably and immutably.
+///
+/// It also implements [std::convert::AsRef] and [std::convert::AsMut] for `TokenType`, and [RangeInclusive<usize>].
+///
+/// A token can also carry a single value of any type as metadata, allowing passes between lexing and parsing
+/// to annotate tokens without changing `TokenType`. Cloning a token shares its metadata with the clone.
+pub struct Token<TokenType: TokenValue> {
+    range: RangeInclusive<usize>,
+    // line: usize,
+    // offset: usize,
+    value: TokenType,
+    source: Option<SourceId>,
+    metadata: Option<Arc<dyn Any + Send + Sync>>,
+    zero_width: bool,
+}
+
+impl<TokenType: TokenValue> Debug for Token<TokenType> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut debug = f.debug_struct("Token");
+        debug
+            .field("range", &self.range)
+            .field("value", &self.value);
+        if let Some(source) = &self.source {
+            debug.field("source", source);
+        }
+        if self.zero_width {
+            debug.field("zero_width", &true);
+        }
+        if self.metadata.is_some() {
+            debug.field("metadata", &"..");
+        }
+        debug.finish()
+    }
+}
+
+/// Displays the token on a single line, such as `Ident("foo") @ 3..=5`, followed by the source
+/// if it is known.
+///
+/// A token only knows its range of grapheme indexes, so its span is displayed as such. To display
+/// it as lines and columns, such as `Ident("foo") @ 3:14..3:17`, use `Token::display_in` with the
+/// source the token was lexed from.
+///
+/// The alternate format, `{:#}`, displays a multi-line block that also notes whether the token has metadata.
+impl<TokenType: TokenValue> Display for Token<TokenType> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let span = self.grapheme_span().map(Span::from);
+        if !f.alternate() {
+            write!(f, "{:?}", self.value)?;
+            if let Some(span) = span {
+                write!(f, " @ {}", span.with_source(self.source))?;
+            } else if let Some(source) = self.source {
+                write!(f, " in {}", source)?;
+            }
+            return Ok(());
+        }
+
+        writeln!(f, "Token")?;
+        write!(f, "    value: {:?}", self.value)?;
+        if let Some(span) = span {
+            write!(f, "\n    graphemes: {}", span)?;
+        }
+        if let Some(source) = self.source {
+            write!(f, "\n    source: {}", source)?;
+        }
+        if self.metadata.is_some() {
+            write!(f, "\n    metadata: present")?;
+        }
+        Ok(())
+    }
+}
+
+impl<TokenType: TokenValue + PartialEq> PartialEq for Token<TokenType> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl<TokenType: TokenValue + Eq> Eq for Token<TokenType> {}
+
+/// Like equality, only the value of the token is hashed.
+impl<TokenType: TokenValue + Hash> Hash for Token<TokenType> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.value.hash(state)
+    }
+}
+
+impl<TokenType: TokenValue> Token<TokenType> {
+    /// Creates a new token with the provided `token` and `range`.
+    ///
+    /// `token` is a `TokenType` that will contain the actual value of the token.
+    ///
+    /// `range` is an [Option] for a [std::ops::RangeInclusive<usize>]. If `range` is `None`, then the internal range will be `0..=0`.
+    pub fn new(
+        token: TokenType,
+        range: Option<RangeInclusive<usize>>, /*, line: usize, offset: usize*/
+    ) -> Self {
+        let range = match range {
+            Some(range) => range,
+            None => 0..=0,
+        };
+
+        Self {
+            range,
+            // line,
+            // offset,
+            value: token,
+            source: None,
+            metadata: None,
+            zero_width: false,
+        }
+    }
+
+    /// Creates a token that covers no graphemes, lying before the grapheme index `position`.
+    ///
+    /// This suits tokens that do not correspond to any text, such as an end of file token lying at the
+    /// end of the input. Such a token has no [range](Token::range), but has an empty [span](Token::span).
+    pub fn at(token: TokenType, position: usize) -> Self {
+        Self {
+            zero_width: true,
+            ..Self::new(token, Some(position..=position))
+        }
+    }
+
+    /// Returns whether the token was created with [Token::at], and so covers no graphemes.
+    pub fn is_zero_width(&self) -> bool {
+        self.zero_width
+    }
+
+    /// Creates a token from a `TokenType` that has a range of `0..=0`, which is meaningless.
+    pub fn from(token: TokenType) -> Self {
+        Self::new(token, None /*, 0, 0*/)
+    }
+
+    /// Converts the token's value with `map`, keeping its range, source and metadata.
+    pub fn map<U: TokenValue, F: FnOnce(TokenType) -> U>(self, map: F) -> Token<U> {
+        Token {
+            range: self.range,
+            value: map(self.value),
+            source: self.source,
+            metadata: self.metadata,
+            zero_width: self.zero_width,
+        }
+    }
+
+    /// Returns a reference to the token's value.
+    pub fn token(&self) -> &TokenType {
+        &self.value
+    }
+
+    /// Returns a mutable reference to the token's value.
+    pub fn token_mut(&mut self) -> &mut TokenType {
+        &mut self.value
+    }
+
+    /// Returns a reference to the metadata if it is of type `M`.
+    pub fn metadata<M: Any>(&self) -> Option<&M> {
+        self.metadata.as_deref()?.downcast_ref()
+    }
+
+    /// Returns a mutable reference to the metadata if it is of type `M`
+    /// and it is not shared with a clone of this token.
+    pub fn metadata_mut<M: Any>(&mut self) -> Option<&mut M> {
+        Arc::get_mut(self.metadata.as_mut()?)?.downcast_mut()
+    }
+
+    /// Returns whether the token has metadata.
+    pub fn has_metadata(&self) -> bool {
+        self.metadata.is_some()
+    }
+
+    /// Sets the metadata, replacing any existing metadata.
+    pub fn set_metadata<M: Any + Send + Sync>(&mut self, metadata: M) {
+        self.metadata = Some(Arc::new(metadata))
+    }
+
+    /// Sets the metadata and returns self.
+    pub fn with_metadata<M: Any + Send + Sync>(mut self, metadata: M) -> Self {
+        self.set_metadata(metadata);
+        self
+    }
+
+    /// Removes the metadata.
+    pub fn clear_metadata(&mut self) {
+        self.metadata = None
+    }
+
+    /// Returns a reference to the range.
+    ///
+    /// If the range is `0..=0`, or the token is [zero width](Token::is_zero_width), the returned value will be `None`.
+    pub fn range(&self) -> Option<&RangeInclusive<usize>> {
+        if self.zero_width || self.range == (0..=0) {
+            None
+        } else {
+            Some(&self.range)
+        }
+    }
+
+    /// Returns a mutable reference to the range.
+    ///
+    /// If the range is `0..=0`, or the token is [zero width](Token::is_zero_width), the returned value will be `None`.
+    pub fn range_mut(&mut self) -> Option<&mut RangeInclusive<usize>> {
+        if self.zero_width || self.range == (0..=0) {
+            None
+        } else {
+            Some(&mut self.range)
+        }
+    }
+
+    /// Returns the range as an exclusive range, which ends after the last grapheme.
+    ///
+    /// If the range is `0..=0` the returned value will be `None`. A [zero width](Token::is_zero_width)
+    /// token returns an empty range at its position.
+    pub fn grapheme_span(&self) -> Option<Range<usize>> {
+        if self.zero_width {
+            let position = *self.range.start();
+            return Some(position..position);
+        }
+
+        self.range().map(|range| *range.start()..range.end() + 1)
+    }
+
+    /// Returns the range as a [Span].
+    ///
+    /// If the range is `0..=0` the returned value will be `None`. A [zero width](Token::is_zero_width)
+    /// token returns an [empty](Span::empty) span at its position.
+    pub fn span(&self) -> Option<Span> {
+        self.grapheme_span()
+            .map(|range| Span::from(range).with_source(self.source))
+    }
+
+    /// Returns the id of the source that the token was lexed from, if it is known.
+    pub fn source(&self) -> Option<SourceId> {
+        self.source
+    }
+
+    /// Sets the id of the source that the token was lexed from.
+    pub fn set_source(&mut self, source: Option<SourceId>) {
+        self.source = source
+    }
+
+    /// Returns a reference to the range, without checking if it's equal to `0..=0`.
+    pub fn range_raw(&self) -> &RangeInclusive<usize> {
+        &self.range
+    }
+
+    /// Returns a mutable reference to the range, without checking if it's equal to `0..=0`.
+    pub fn range_raw_mut(&mut self) -> &mut RangeInclusive<usize> {
+        &mut self.range
+    }
+}
+
+impl<TokenType: TokenValue> Deref for Token<TokenType> {
+    type Target = TokenType;
+
+    fn deref(&self) -> &Self::Target {
+        &self.value
+    }
+}
+
+impl<TokenType: TokenValue> DerefMut for Token<TokenType> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.value
+    }
+}
+
+impl<TokenType: TokenValue> AsRef<TokenType> for Token<TokenType> {
+    fn as_ref(&self) -> &TokenType {
+        &self.value
+    }
+}
+
+impl<TokenType: TokenValue> AsMut<TokenType> for Token<TokenType> {
+    fn as_mut(&mut self) -> &mut TokenType {
+        &mut self.value
+    }
+}
+
+impl<TokenType: TokenValue> AsRef<RangeInclusive<usize>> for Token<TokenType> {
+    fn as_ref(&self) -> &RangeInclusive<usize> {
+        &self.range
+    }
+}
+
+impl<TokenType: TokenValue> AsMut<RangeInclusive<usize>> for Token<TokenType> {
+    fn as_mut(&mut self) -> &mut RangeInclusive<usize> {
+        &mut self.range
+    }
+}
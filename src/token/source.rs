@@ -0,0 +1,16 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// A small identifier for an input source, which is mapped to a description of the source by a [SourceRegistry](crate::lexical::SourceRegistry).
+pub struct SourceId(pub(crate) u32);
+
+impl SourceId {
+    /// Returns the index of the source within its registry.
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl std::fmt::Display for SourceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    fmt::Display,
+    ops::{Range, RangeInclusive},
+    sync::Arc,
+};
+
+use super::SourceId;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A range of grapheme indexes within the input, such as the range a token was lexed from.
+///
+/// A span is usually non-empty, but may also be an empty span created with [Span::empty], which
+/// marks a position between graphemes, such as the end of the input.
+///
+/// A span may also identify which source it lies within, through a [SourceId], and which span it
+/// was derived from, such as the location of the snippet it was embedded in. See [Span::remap].
+///
+/// A span only knows grapheme indexes, not the text they index into, so its methods measure
+/// graphemes. With the `lexical` feature, byte offsets can be resolved through the `SourceBuffer`
+/// the span lies within, with `Span::byte_range`, `Span::len_bytes` and `Span::contains_byte`.
+pub struct Span {
+    start: usize,
+    // Exclusive, so that empty spans can be represented.
+    end: usize,
+    source: Option<SourceId>,
+    origin: Option<Arc<Span>>,
+}
+
+impl Span {
+    /// Creates a span from `start` to `end`, both inclusive. If `end` is before `start`, they are swapped.
+    ///
+    /// As the end is stored exclusively, a span cannot include the grapheme at [usize::MAX], and
+    /// ends before it instead.
+    pub fn new(start: usize, end: usize) -> Self {
+        Self {
+            start: start.min(end),
+            end: start.max(end).saturating_add(1),
+            source: None,
+            origin: None,
+        }
+    }
+
+    /// Creates an empty span at `position`, which lies before the grapheme at that index.
+    pub fn empty(position: usize) -> Self {
+        Self {
+            start: position,
+            end: position,
+            source: None,
+            origin: None,
+        }
+    }
+
+    /// Returns whether the span covers no graphemes.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// Sets the source of the span and returns self.
+    pub fn with_source(mut self, source: Option<SourceId>) -> Self {
+        self.source = source;
+        self
+    }
+
+    /// Returns the id of the source the span lies within, if it is known.
+    pub fn source(&self) -> Option<SourceId> {
+        self.source
+    }
+
+    /// Returns the index of the first grapheme.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Returns the index of the last grapheme, or the position of an empty span.
+    pub fn end(&self) -> usize {
+        if self.is_empty() {
+            self.start
+        } else {
+            self.end - 1
+        }
+    }
+
+    /// Returns the span as an inclusive range.
+    ///
+    /// An empty span cannot be represented as an inclusive range, and is returned as `position..=position`.
+    /// Prefer [Span::grapheme_span] where spans may be empty.
+    pub fn range(&self) -> RangeInclusive<usize> {
+        self.start..=self.end()
+    }
+
+    /// Returns the span as an exclusive range, which ends after the last grapheme.
+    pub fn grapheme_span(&self) -> Range<usize> {
+        self.start..self.end
+    }
+
+    /// Returns the smallest span that covers both `self` and `other`.
+    ///
+    /// The source of `self` is kept, or the source of `other` if `self` has none.
+    pub fn merge(&self, other: &Span) -> Span {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+            source: self.source.or(other.source),
+            origin: self.origin.clone().or_else(|| other.origin.clone()),
+        }
+    }
+
+    /// Sets the span this span was derived from and returns self.
+    pub fn with_origin(mut self, origin: Span) -> Self {
+        self.origin = Some(Arc::new(origin));
+        self
+    }
+
+    /// Returns the span this span was derived from, if there is one.
+    pub fn origin(&self) -> Option<&Span> {
+        self.origin.as_deref()
+    }
+
+    /// Returns the chain of spans this span was derived from, starting with its direct origin.
+    pub fn origins(&self) -> impl Iterator<Item = &Span> {
+        std::iter::successors(self.origin(), |span| span.origin())
+    }
+
+    /// Translates a span within an embedded snippet, such as a code block in a Markdown file, into the
+    /// source the snippet was embedded in. `origin` is the span the snippet's text occupies within that source.
+    ///
+    /// The returned span lies within the source of `origin`, and its origin is `self`, so both the
+    /// local and the original locations remain available.
+    pub fn remap(&self, origin: &Span) -> Span {
+        Self {
+            start: origin.start.saturating_add(self.start),
+            end: origin.start.saturating_add(self.end),
+            source: origin.source,
+            origin: Some(Arc::new(self.clone())),
+        }
+    }
+
+    /// Returns whether the spans could lie within the same source, which is the case
+    /// unless both have a source and the sources differ.
+    pub fn same_source(&self, other: &Span) -> bool {
+        match (self.source, other.source) {
+            (Some(source), Some(other)) => source == other,
+            _ => true,
+        }
+    }
+
+    /// Returns whether the grapheme at `index` lies within the span. See the [Span] documentation
+    /// for checking a byte offset.
+    pub fn contains(&self, index: usize) -> bool {
+        self.grapheme_span().contains(&index)
+    }
+
+    /// Returns whether the spans share at least one grapheme within the same source.
+    pub fn intersects(&self, other: &Span) -> bool {
+        self.same_source(other) && self.start.max(other.start) < self.end.min(other.end)
+    }
+
+    /// Returns the amount of graphemes within the span.
+    pub fn len_graphemes(&self) -> usize {
+        self.end - self.start
+    }
+}
+
+/// Displays the span on a single line, such as `3..=5 in #1`, or `3..3` for an empty span.
+///
+/// The alternate format, `{:#}`, displays a multi-line block that also includes the chain of origins.
+impl Display for Span {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let range = match self.is_empty() {
+            true => format!("{}..{}", self.start, self.end),
+            false => format!("{}..={}", self.start, self.end()),
+        };
+        if !f.alternate() {
+            write!(f, "{}", range)?;
+            if let Some(source) = self.source {
+                write!(f, " in {}", source)?;
+            }
+            return Ok(());
+        }
+
+        writeln!(f, "Span")?;
+        write!(f, "    graphemes: {}", range)?;
+        if let Some(source) = self.source {
+            write!(f, "\n    source: {}", source)?;
+        }
+        for origin in self.origins() {
+            write!(f, "\n    from: {}", origin)?;
+        }
+        Ok(())
+    }
+}
+
+impl From<RangeInclusive<usize>> for Span {
+    fn from(range: RangeInclusive<usize>) -> Self {
+        Self::new(*range.start(), *range.end())
+    }
+}
+
+/// Converts an exclusive range, which may be empty. If the range ends before it starts, the span is empty.
+impl From<Range<usize>> for Span {
+    fn from(range: Range<usize>) -> Self {
+        Self {
+            start: range.start,
+            end: range.start.max(range.end),
+            source: None,
+            origin: None,
+        }
+    }
+}
+
+impl From<&Span> for RangeInclusive<usize> {
+    fn from(span: &Span) -> Self {
+        span.range()
+    }
+}
+
+impl From<&Span> for Range<usize> {
+    fn from(span: &Span) -> Self {
+        span.grapheme_span()
+    }
+}